@@ -0,0 +1,361 @@
+use crate::date::{Date, Duration};
+use crate::eval::{Expression, Ident, Op, UserFunction};
+use crate::function::Function;
+use crate::function::angle::Angle;
+use crate::number::Number;
+use crate::number::radix::Word;
+use crate::unit::Unit;
+use crate::errors::Span;
+use std::fmt::Display;
+
+#[cfg(test)]
+mod tests;
+
+/// Expression tree built by the parser. Span of a node covers
+/// the whole subexpression, spans inside node kinds point at
+/// the operator or name that is reported when evaluation fails.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Node {
+    pub kind: NodeKind,
+    pub span: Span
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum NodeKind {
+    Num(Number),
+    /// "text", without the quotes and escapes
+    Str(String),
+    /// 2026-10-18
+    Date(Date),
+    /// 17:45
+    Duration(Duration),
+    Var(Ident),
+    /// Named constant with its value, pi or e, printed by name
+    Const(String, Number),
+    /// -x, with span of the operator
+    Unary(Op, Span, Box<Node>),
+    /// x + y, with span of the operator
+    Binary(Op, Span, Box<Node>, Box<Node>),
+    /// Call of function from the registry, with span of the name
+    Func(Function, Span, Vec<Node>),
+    /// Call of user function, with span of the name
+    Call(Ident, Span, Vec<Node>),
+    /// 5 km, with span of the unit
+    WithUnit(Box<Node>, Unit, Span),
+    /// x to km, with span of the to keyword
+    Convert(Box<Node>, Unit, Span),
+    /// x as u32, with span of the as keyword
+    Cast(Box<Node>, Word, Span),
+    /// x = 1, with span of the variable name
+    Assign(Ident, Span, Box<Node>),
+    /// f(x, y) = x * y
+    Define(Ident, Vec<Ident>, Box<Node>),
+    /// Derivative of given order, diff(x^2, x, 2), with span of the name
+    Diff(Box<Node>, Ident, u32, Span),
+    /// Definite integral, integrate(x^2, x, 0, 1), with span of the name
+    Integrate(Box<Node>, Ident, Box<Node>, Box<Node>, Span),
+    /// Root near the guess, solve(x^2 - 2, x, 1), with span of the name
+    Solve(Box<Node>, Ident, Box<Node>, Span),
+    /// if(x > 0, x, -x), with span of the name
+    If(Box<Node>, Box<Node>, Box<Node>, Span),
+    /// Prime factors, factor(360), with span of the name
+    Factor(Box<Node>, Span),
+    /// [1, 2, 3]
+    List(Vec<Node>),
+    /// Element of a list, x[0], with span of the brackets
+    Index(Box<Node>, Box<Node>, Span),
+    /// Body evaluated for each element, map(x^2, x, [1, 2]), with span of the name
+    Map(Box<Node>, Ident, Box<Node>, Span),
+    /// Elements for which the condition is true, filter(x > 1, x, [1, 2]),
+    /// with span of the name
+    Filter(Box<Node>, Ident, Box<Node>, Span)
+}
+
+impl Node {
+    pub fn new(kind: NodeKind, span: Span) -> Node {
+        Node { kind, span }
+    }
+
+    /// `a + b%` and `a - b%` change a by b percent, like on a desk calculator.
+    /// Returns such node rewritten as `a*(1 + b/100)`, None for other nodes.
+    pub fn percent_change(&self) -> Option<Node> {
+        let (op, span, lhs, rate) = match &self.kind {
+            NodeKind::Binary(op @ (Op::Add | Op::Sub), span, lhs, rhs) => match &rhs.kind {
+                NodeKind::Unary(Op::Percent, _, rate) => (*op, span, lhs, rate),
+                _ => return None
+            },
+            _ => return None
+        };
+        let node = |kind| Node::new(kind, self.span.clone());
+        let hundred = node(NodeKind::Num(Number::Int(100)));
+        let rate = node(NodeKind::Binary(Op::Div, span.clone(), rate.clone(), Box::new(hundred)));
+        let one = node(NodeKind::Num(Number::Int(1)));
+        let factor = node(NodeKind::Binary(op, span.clone(), Box::new(one), Box::new(rate)));
+        Some(node(NodeKind::Binary(Op::Mul, span.clone(), lhs.clone(), Box::new(factor))))
+    }
+
+    /// Lowers the tree to postfix program evaluated by the Calculator
+    pub fn lower(&self) -> Vec<Expression> {
+        let mut program = vec![];
+        self.lower_into(&mut program);
+        program
+    }
+
+    fn lower_into(&self, program: &mut Vec<Expression>) {
+        if let Some(node) = self.percent_change() {
+            return node.lower_into(program);
+        }
+        match &self.kind {
+            NodeKind::Num(n) | NodeKind::Const(_, n) => program.push(Expression::Val(n.clone())),
+            NodeKind::Str(s) => program.push(Expression::Str(s.clone())),
+            NodeKind::Date(d) => program.push(Expression::Date(d.clone())),
+            NodeKind::Duration(d) => program.push(Expression::Duration(d.clone())),
+            NodeKind::Var(id) => program.push(Expression::Ref(id.clone(), self.span.clone())),
+            NodeKind::Unary(op, span, arg) => {
+                arg.lower_into(program);
+                program.push(Expression::UnaryOp(*op, span.clone()));
+            },
+            // right side of && and || is evaluated only when needed
+            NodeKind::Binary(op @ (Op::And | Op::Or), span, lhs, rhs) => {
+                lhs.lower_into(program);
+                program.push(Expression::Logic(*op, rhs.lower(), span.clone()));
+            },
+            NodeKind::Binary(op, span, lhs, rhs) => {
+                lhs.lower_into(program);
+                rhs.lower_into(program);
+                program.push(Expression::BinaryOp(*op, span.clone()));
+            },
+            NodeKind::Func(f, span, args) => {
+                args.iter().for_each(|arg| arg.lower_into(program));
+                program.push(Expression::FuncCall(f.clone(), args.len(), span.clone()));
+            },
+            NodeKind::Call(id, span, args) => {
+                args.iter().for_each(|arg| arg.lower_into(program));
+                program.push(Expression::Call(id.clone(), args.len(), span.clone()));
+            },
+            NodeKind::WithUnit(node, unit, span) => {
+                node.lower_into(program);
+                program.push(Expression::WithUnit(unit.clone(), span.clone()));
+            },
+            NodeKind::Convert(node, unit, span) => {
+                node.lower_into(program);
+                program.push(Expression::Convert(unit.clone(), span.clone()));
+            },
+            NodeKind::Cast(node, word, span) => {
+                node.lower_into(program);
+                program.push(Expression::Cast(*word, span.clone()));
+            },
+            NodeKind::Assign(id, span, value) => {
+                value.lower_into(program);
+                program.push(Expression::Assign(id.clone(), span.clone()));
+            },
+            NodeKind::Define(id, params, body) => {
+                let f = UserFunction { params: params.clone(), body: (**body).clone() };
+                program.push(Expression::Define(id.clone(), f));
+            },
+            NodeKind::Diff(node, var, order, span) => {
+                program.push(Expression::Diff((**node).clone(), var.clone(), *order, span.clone()));
+            },
+            // bounds and guess are evaluated first, the body each time
+            // the variable gets a new value
+            NodeKind::Integrate(body, var, a, b, span) => {
+                a.lower_into(program);
+                b.lower_into(program);
+                program.push(Expression::Integrate(body.lower(), var.clone(), span.clone()));
+            },
+            NodeKind::Solve(body, var, guess, span) => {
+                guess.lower_into(program);
+                program.push(Expression::Solve(body.lower(), var.clone(), span.clone()));
+            },
+            NodeKind::If(cond, then, otherwise, span) => {
+                cond.lower_into(program);
+                program.push(Expression::If(then.lower(), otherwise.lower(), span.clone()));
+            },
+            NodeKind::Factor(node, span) => {
+                node.lower_into(program);
+                program.push(Expression::Factor(span.clone()));
+            },
+            NodeKind::List(items) => {
+                items.iter().for_each(|item| item.lower_into(program));
+                program.push(Expression::List(items.len(), self.span.clone()));
+            },
+            NodeKind::Index(list, i, span) => {
+                list.lower_into(program);
+                i.lower_into(program);
+                program.push(Expression::Index(span.clone()));
+            },
+            // the list is evaluated first, the body for each of its elements
+            NodeKind::Map(body, var, list, span) => {
+                list.lower_into(program);
+                program.push(Expression::Map(body.lower(), var.clone(), span.clone()));
+            },
+            NodeKind::Filter(body, var, list, span) => {
+                list.lower_into(program);
+                program.push(Expression::Filter(body.lower(), var.clone(), span.clone()));
+            }
+        }
+    }
+}
+
+// Binding strength used to put parentheses when printing
+const PREC_OR: u8 = 1;
+const PREC_AND: u8 = 2;
+const PREC_EQ: u8 = 3;
+const PREC_CMP: u8 = 4;
+const PREC_BIT_OR: u8 = 5;
+const PREC_XOR: u8 = 6;
+const PREC_BIT_AND: u8 = 7;
+const PREC_SHIFT: u8 = 8;
+const PREC_CONV: u8 = 9;
+const PREC_ADD: u8 = 10;
+const PREC_MUL: u8 = 11;
+const PREC_UNARY: u8 = 12;
+const PREC_POW: u8 = 13;
+const PREC_ATOM: u8 = 14;
+
+fn op_prec(op: Op) -> u8 {
+    match op {
+        Op::Or => PREC_OR,
+        Op::And => PREC_AND,
+        Op::Eq | Op::Ne => PREC_EQ,
+        Op::Lt | Op::Le | Op::Gt | Op::Ge => PREC_CMP,
+        Op::BitOr => PREC_BIT_OR,
+        Op::Xor => PREC_XOR,
+        Op::BitAnd => PREC_BIT_AND,
+        Op::Shl | Op::Shr | Op::Ushr => PREC_SHIFT,
+        Op::Add | Op::Sub => PREC_ADD,
+        Op::Mul | Op::Div | Op::Mod => PREC_MUL,
+        Op::Not | Op::BitNot => PREC_UNARY,
+        Op::Fact | Op::Percent | Op::Angle(_) => PREC_ATOM,
+        Op::Pow => PREC_POW
+    }
+}
+
+fn op_symbol(op: Op) -> &'static str {
+    match op {
+        Op::Add => " + ",
+        Op::Sub => " - ",
+        Op::Mul => "*",
+        Op::Div => "/",
+        Op::Mod => " % ",
+        Op::Pow => "^",
+        Op::Lt => " < ",
+        Op::Le => " <= ",
+        Op::Gt => " > ",
+        Op::Ge => " >= ",
+        Op::Eq => " == ",
+        Op::Ne => " != ",
+        Op::And => " && ",
+        Op::Or => " || ",
+        Op::Not => "!",
+        Op::BitAnd => " & ",
+        Op::BitOr => " | ",
+        Op::Xor => " xor ",
+        Op::BitNot => "~",
+        Op::Fact => "!",
+        Op::Percent => "%",
+        Op::Angle(Angle::Deg) => "°",
+        Op::Angle(Angle::Rad) => " rad",
+        Op::Angle(Angle::Grad) => " grad",
+        Op::Shl => " << ",
+        Op::Shr => " >> ",
+        Op::Ushr => " >>> "
+    }
+}
+
+impl Node {
+    fn prec(&self) -> u8 {
+        match &self.kind {
+            NodeKind::Num(Number::Rational(_)) => PREC_MUL,
+            NodeKind::Num(Number::Complex(c)) if c.re != 0.0 => PREC_ADD,
+            NodeKind::Num(n) if n.is_negative() => PREC_UNARY,
+            NodeKind::Num(_) | NodeKind::Str(_) | NodeKind::Date(_) | NodeKind::Duration(_) | NodeKind::Var(_) | NodeKind::Const(..)
+                | NodeKind::Func(..) | NodeKind::Call(..) | NodeKind::Diff(..) | NodeKind::Integrate(..)
+                | NodeKind::Solve(..) | NodeKind::If(..)
+                | NodeKind::Factor(..) | NodeKind::Unary(Op::Fact | Op::Percent | Op::Angle(_), ..) | NodeKind::List(_) | NodeKind::Index(..)
+                | NodeKind::Map(..) | NodeKind::Filter(..) => PREC_ATOM,
+            NodeKind::Unary(..) => PREC_UNARY,
+            NodeKind::Binary(op, ..) => op_prec(*op),
+            NodeKind::Convert(..) | NodeKind::Cast(..) => PREC_CONV,
+            _ => 0
+        }
+    }
+}
+
+// string in quotes, with quotes and backslashes escaped so that it reads back
+pub(crate) fn quote(s: &str) -> String {
+    let mut res = String::from('"');
+    for c in s.chars() {
+        match c {
+            '"' => res.push_str("\\\""),
+            '\\' => res.push_str("\\\\"),
+            '\n' => res.push_str("\\n"),
+            '\t' => res.push_str("\\t"),
+            c => res.push(c)
+        }
+    }
+    res.push('"');
+    res
+}
+
+fn fmt_args(args: &[Node]) -> String {
+    args.iter().map(Node::to_string).collect::<Vec<_>>().join(", ")
+}
+
+fn paren(node: &Node, needed: bool) -> String {
+    if needed { format!("({node})") } else { node.to_string() }
+}
+
+/// Prints expression with as few parentheses as needed to read it back,
+/// e.g. `2*x*sin(x) + x^2*cos(x)`
+impl Display for Node {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.kind {
+            NodeKind::Num(n) => write!(f, "{n}"),
+            NodeKind::Str(s) => write!(f, "{}", quote(s)),
+            NodeKind::Date(d) => write!(f, "{d}"),
+            NodeKind::Duration(d) => write!(f, "{d:#}"),
+            NodeKind::Var(id) => write!(f, "{id}"),
+            NodeKind::Const(name, _) => write!(f, "{name}"),
+            NodeKind::Unary(Op::Sub, _, arg) => write!(f, "-{}", paren(arg, arg.prec() < PREC_ATOM)),
+            NodeKind::Unary(Op::Not, _, arg) => write!(f, "!{}", paren(arg, arg.prec() < PREC_ATOM)),
+            NodeKind::Unary(Op::BitNot, _, arg) => write!(f, "~{}", paren(arg, arg.prec() < PREC_ATOM)),
+            NodeKind::Unary(Op::Fact, _, arg) => write!(f, "{}!", paren(arg, arg.prec() < PREC_ATOM)),
+            NodeKind::Unary(Op::Percent, _, arg) => write!(f, "{}%", paren(arg, arg.prec() < PREC_ATOM)),
+            NodeKind::Unary(op @ Op::Angle(_), _, arg) => write!(f, "{}{}", paren(arg, arg.prec() < PREC_ATOM), op_symbol(*op)),
+            NodeKind::Unary(_, _, arg) => write!(f, "+{}", paren(arg, arg.prec() < PREC_ATOM)),
+            NodeKind::Binary(op, _, lhs, rhs) => {
+                let prec = op_prec(*op);
+                // ^ is right associative, other operators are left associative,
+                // negation is kept in parentheses inside of an operation for clarity
+                let (l_paren, r_paren) = match op {
+                    Op::Pow => (lhs.prec() <= prec, rhs.prec() < prec || rhs.prec() == PREC_UNARY),
+                    // comparisons do not chain
+                    _ if op.is_comparison() => (lhs.prec() <= prec, rhs.prec() <= prec),
+                    _ => (lhs.prec() < prec, rhs.prec() <= prec || rhs.prec() == PREC_UNARY)
+                };
+                write!(f, "{}{}{}", paren(lhs, l_paren), op_symbol(*op), paren(rhs, r_paren))
+            },
+            NodeKind::Func(func, _, args) => write!(f, "{}({})", func.name, fmt_args(args)),
+            NodeKind::Call(id, _, args) => write!(f, "{id}({})", fmt_args(args)),
+            NodeKind::WithUnit(node, unit, _) => write!(f, "{} {unit}", paren(node, node.prec() < PREC_ATOM)),
+            NodeKind::Convert(node, unit, _) => write!(f, "{node} to {unit}"),
+            NodeKind::Cast(node, word, _) => write!(f, "{node} as {word}"),
+            NodeKind::Assign(id, _, value) => write!(f, "{id} = {value}"),
+            NodeKind::Define(id, params, body) => {
+                let params: Vec<_> = params.iter().map(Ident::to_string).collect();
+                write!(f, "{id}({}) = {body}", params.join(", "))
+            },
+            NodeKind::Diff(node, var, 1, _) => write!(f, "diff({node}, {var})"),
+            NodeKind::Diff(node, var, order, _) => write!(f, "diff({node}, {var}, {order})"),
+            NodeKind::Integrate(body, var, a, b, _) => write!(f, "integrate({body}, {var}, {a}, {b})"),
+            NodeKind::Solve(body, var, guess, _) => write!(f, "solve({body}, {var}, {guess})"),
+            NodeKind::If(cond, then, otherwise, _) => write!(f, "if({cond}, {then}, {otherwise})"),
+            NodeKind::Factor(node, _) => write!(f, "factor({node})"),
+            NodeKind::List(items) => write!(f, "[{}]", fmt_args(items)),
+            NodeKind::Index(list, i, _) => write!(f, "{}[{i}]", paren(list, list.prec() < PREC_ATOM)),
+            NodeKind::Map(body, var, list, _) => write!(f, "map({body}, {var}, {list})"),
+            NodeKind::Filter(cond, var, list, _) => write!(f, "filter({cond}, {var}, {list})")
+        }
+    }
+}
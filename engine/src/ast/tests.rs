@@ -0,0 +1,69 @@
+use super::*;
+use crate::Parser;
+
+fn parse(input: &str) -> Node {
+    let mut p = Parser::new(input);
+    p.parse().unwrap();
+    p.ast.unwrap()
+}
+
+#[test]
+fn test_tree() {
+    let node = parse("-x + 2 * (3 - y)");
+    let x = Node::new(NodeKind::Var(Ident::new("x")), Span::new(1, 1));
+    let neg = Node::new(NodeKind::Unary(Op::Sub, Span::new(0, 1), Box::new(x)), Span::new(0, 2));
+    let y = Node::new(NodeKind::Var(Ident::new("y")), Span::new(14, 1));
+    let three = Node::new(NodeKind::Num(Number::Int(3)), Span::new(10, 1));
+    let sub = Node::new(NodeKind::Binary(Op::Sub, Span::new(12, 1), Box::new(three), Box::new(y)), Span::new(9, 7));
+    let two = Node::new(NodeKind::Num(Number::Int(2)), Span::new(5, 1));
+    let mul = Node::new(NodeKind::Binary(Op::Mul, Span::new(7, 1), Box::new(two), Box::new(sub)), Span::new(5, 11));
+    let add = Node::new(NodeKind::Binary(Op::Add, Span::new(3, 1), Box::new(neg), Box::new(mul)), Span::new(0, 16));
+    assert_eq!(node, add);
+}
+
+#[test]
+fn test_spans() {
+    assert_eq!(parse("sqrt(2) + 1").span, Span::new(0, 11));
+    match parse("  z = sqrt( 2 ) ").kind {
+        NodeKind::Assign(_, span, value) => {
+            assert_eq!(span, Span::new(2, 1));
+            assert_eq!(value.span, Span::new(6, 9));
+        },
+        kind => panic!("Expected assignment, got {kind:?}")
+    }
+    match parse("5 km/h to m/s").kind {
+        NodeKind::Convert(node, _, span) => {
+            assert_eq!(span, Span::new(7, 2));
+            assert_eq!(node.span, Span::new(0, 6));
+        },
+        kind => panic!("Expected conversion, got {kind:?}")
+    }
+}
+
+#[test]
+fn test_lower() {
+    let node = parse("f(x) = 2 * x");
+    let body = vec![
+        Expression::Val(Number::Int(2)),
+        Expression::Ref(Ident::new("x"), Span::new(11, 1)),
+        Expression::BinaryOp(Op::Mul, Span::new(9, 1))
+    ];
+    match node.lower().as_slice() {
+        [Expression::Define(id, UserFunction { params, body: node })] => {
+            assert_eq!(id, &Ident::new("f"));
+            assert_eq!(params, &vec![Ident::new("x")]);
+            assert_eq!(node.lower(), body);
+        },
+        program => panic!("Expected definition, got {program:?}")
+    }
+
+    let node = parse("1 - 2 - 3");
+    let expected = vec![
+        Expression::Val(Number::Int(1)),
+        Expression::Val(Number::Int(2)),
+        Expression::BinaryOp(Op::Sub, Span::new(2, 1)),
+        Expression::Val(Number::Int(3)),
+        Expression::BinaryOp(Op::Sub, Span::new(6, 1))
+    ];
+    assert_eq!(node.lower(), expected);
+}
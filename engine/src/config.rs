@@ -0,0 +1,37 @@
+use crate::function::angle::Angle;
+use crate::number::radix::{self, Radix, Word};
+use crate::number::Number;
+use crate::unit::currency::Rates;
+use std::rc::Rc;
+
+/// Settings of a session, changed by commands like `precision 50` or
+/// `base hex`. Calculator owns them and parser reads literals with them.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Config {
+    /// Significant digits of decimal floats, None computes floats as f64
+    pub precision: Option<usize>,
+    /// Base in which integers are printed
+    pub radix: Radix,
+    /// Word that wraps results of integer arithmetic, None for unbounded integers
+    pub word: Option<Word>,
+    /// Separators between groups of digits, like `0xffff_ffff`
+    pub grouping: bool,
+    /// Unit of angles taken and returned by trigonometric functions
+    pub angle: Angle,
+    /// Exchange rates used by conversions of currencies
+    pub rates: Rc<Rates>
+}
+
+impl Config {
+    /// Wraps integer into the word of the session,
+    /// other numbers are returned unchanged
+    pub fn wrap(&self, n: Number) -> Number {
+        radix::wrap(n, self.word)
+    }
+
+    /// Prints integer in given radix with the word and grouping
+    /// of the session, None for numbers that are not integers
+    pub fn format(&self, n: &Number, radix: Radix) -> Option<String> {
+        radix::format(n, radix, self.word, self.grouping)
+    }
+}
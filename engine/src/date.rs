@@ -0,0 +1,200 @@
+use crate::number::Number;
+use std::fmt::Display;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+#[cfg(test)]
+mod tests;
+
+const SECS_PER_DAY: i64 = 86_400;
+
+// dates are limited to years printed with four digits
+const MIN_YEAR: i64 = 1;
+const MAX_YEAR: i64 = 9999;
+
+/// Date and time of day as seconds since 1970-01-01T00:00, without time zone
+#[derive(Clone, Debug, PartialEq)]
+pub struct Date(pub Number);
+
+/// Length of time in seconds, like 17:45 or the difference of two dates
+#[derive(Clone, Debug, PartialEq)]
+pub struct Duration(pub Number);
+
+impl Date {
+    /// None for dates before year 1 or after year 9999
+    pub fn new(secs: Number) -> Option<Date> {
+        let min = days_from_civil(MIN_YEAR, 1, 1) * SECS_PER_DAY;
+        let max = days_from_civil(MAX_YEAR + 1, 1, 1) * SECS_PER_DAY;
+        let in_range = secs.compare(&Number::Int(min as i128)).is_some_and(|ord| ord.is_ge())
+            && secs.compare(&Number::Int(max as i128)).is_some_and(|ord| ord.is_lt());
+        in_range.then_some(Date(secs))
+    }
+
+    /// Date like 2026-10-18, optionally with time like 2026-10-18T09:10 or 2026-10-18T09:10:30
+    pub fn parse(input: &str) -> Option<Date> {
+        let (date, time) = match input.split_once('T') {
+            Some((date, time)) => (date, Some(time)),
+            None => (input, None)
+        };
+        let mut parts = date.splitn(3, '-');
+        let year: i64 = parts.next()?.parse().ok()?;
+        let month: u32 = parts.next()?.parse().ok()?;
+        let day: u32 = parts.next()?.parse().ok()?;
+        if !(MIN_YEAR..=MAX_YEAR).contains(&year) || !(1..=12).contains(&month)
+            || day < 1 || day > days_in_month(year, month) {
+            return None;
+        }
+        let secs = match time.map(Duration::parse) {
+            Some(Some(Duration(Number::Int(secs)))) if secs < SECS_PER_DAY as i128 => secs,
+            Some(_) => return None,
+            None => 0
+        };
+        let secs = days_from_civil(year, month, day) as i128 * SECS_PER_DAY as i128 + secs;
+        Some(Date(Number::Int(secs)))
+    }
+
+    /// Current date and time in UTC, to a second
+    pub fn now() -> Date {
+        let secs = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs());
+        Date(Number::Int(secs as i128))
+    }
+
+    // whole seconds, rounded
+    fn secs(&self) -> i64 {
+        f64::from(self.0.clone()).round() as i64
+    }
+
+    fn days(&self) -> i64 {
+        self.secs().div_euclid(SECS_PER_DAY)
+    }
+
+    /// Day of the week from 1 for Monday to 7 for Sunday, like in ISO 8601
+    pub fn weekday(&self) -> i64 {
+        // 1970-01-01 was a Thursday
+        (self.days() + 3).rem_euclid(7) + 1
+    }
+
+    /// Number of days from Monday to Friday from this date up to the other,
+    /// without the other one. Negative when the other date is earlier.
+    pub fn workdays(&self, other: &Date) -> i64 {
+        let (start, end) = (self.days(), other.days());
+        if end < start {
+            return -other.workdays(self);
+        }
+        let weeks = (end - start) / 7;
+        let rest = (start + weeks * 7..end).filter(|day| (day + 3).rem_euclid(7) < 5).count();
+        weeks * 5 + rest as i64
+    }
+}
+
+impl Duration {
+    /// Time like 9:10 or 17:45:30, hours can be larger than 23
+    pub fn parse(input: &str) -> Option<Duration> {
+        let parts: Vec<_> = input.split(':').collect();
+        if !(2..=3).contains(&parts.len()) || parts[1..].iter().any(|p| p.len() != 2) {
+            return None;
+        }
+        let mut secs: i128 = 0;
+        for (i, part) in parts.iter().enumerate() {
+            let n: i128 = part.parse().ok()?;
+            if i > 0 && n >= 60 {
+                return None;
+            }
+            secs = secs * 60 + n;
+        }
+        if parts.len() == 2 {
+            secs *= 60;
+        }
+        Some(Duration(Number::Int(secs)))
+    }
+}
+
+// Days since 1970-01-01 of a date in the proleptic Gregorian calendar,
+// by the algorithm from http://howardhinnant.github.io/date_algorithms.html
+fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+    let y = if month <= 2 { year - 1 } else { year };
+    let era = y.div_euclid(400);
+    let yoe = y - era * 400;
+    let m = month as i64;
+    let doy = (153 * (if m > 2 { m - 3 } else { m + 9 }) + 2) / 5 + day as i64 - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146_097 + doe - 719_468
+}
+
+// Year, month and day of a day counted from 1970-01-01
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + (month <= 2) as i64;
+    (year, month, day)
+}
+
+fn days_in_month(year: i64, month: u32) -> u32 {
+    match month {
+        2 if year % 4 == 0 && (year % 100 != 0 || year % 400 == 0) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31
+    }
+}
+
+// 09:10 or 09:10:30, seconds only when there are any
+fn clock(secs: i64) -> String {
+    let (h, m, s) = (secs / 3600, secs / 60 % 60, secs % 60);
+    if s == 0 {
+        format!("{h:02}:{m:02}")
+    } else {
+        format!("{h:02}:{m:02}:{s:02}")
+    }
+}
+
+/// ISO 8601 date like 2026-10-18, with time like 2026-10-18T09:10
+/// when it is not midnight
+impl Display for Date {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let secs = self.secs();
+        let (year, month, day) = civil_from_days(secs.div_euclid(SECS_PER_DAY));
+        write!(f, "{year:04}-{month:02}-{day:02}")?;
+        match secs.rem_euclid(SECS_PER_DAY) {
+            0 => Ok(()),
+            time => write!(f, "T{}", clock(time))
+        }
+    }
+}
+
+/// ISO 8601 duration like PT8H35M, or P90D for whole days. Alternate
+/// flag prints it like a time literal instead, e.g. `{:#}` prints 08:35.
+impl Display for Duration {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let secs = f64::from(self.0.clone());
+        let sign = if secs < 0.0 { "-" } else { "" };
+        let secs = secs.abs();
+        let whole = secs.trunc() as i64;
+        if f.alternate() {
+            return write!(f, "{sign}{}", clock(whole));
+        }
+        if whole > 0 && whole % SECS_PER_DAY == 0 && secs.fract() == 0.0 {
+            return write!(f, "{sign}P{}D", whole / SECS_PER_DAY);
+        }
+        let (h, m) = (whole / 3600, whole / 60 % 60);
+        // seconds to a millisecond
+        let s = format!("{:.3}", secs - (h * 3600 + m * 60) as f64);
+        let s = s.trim_end_matches('0').trim_end_matches('.');
+        write!(f, "{sign}PT")?;
+        if h > 0 {
+            write!(f, "{h}H")?;
+        }
+        if m > 0 {
+            write!(f, "{m}M")?;
+        }
+        if s != "0" || (h == 0 && m == 0) {
+            write!(f, "{s}S")?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,54 @@
+use super::*;
+
+fn date(input: &str) -> Date {
+    Date::parse(input).unwrap()
+}
+
+#[test]
+fn test_parse() {
+    assert_eq!(date("1970-01-01"), Date(Number::Int(0)));
+    assert_eq!(date("1970-01-02T01:00"), Date(Number::Int(90_000)));
+    assert_eq!(date("2024-02-29").to_string(), "2024-02-29");
+    assert_eq!(date("2026-10-18T09:10:30").to_string(), "2026-10-18T09:10:30");
+    assert_eq!(date("0001-01-01").to_string(), "0001-01-01");
+    assert_eq!(Date::parse("2026-02-29"), None);
+    assert_eq!(Date::parse("2026-13-01"), None);
+    assert_eq!(Date::parse("2026-10-18T24:00"), None);
+    assert_eq!(Duration::parse("9:10"), Some(Duration(Number::Int(33_000))));
+    assert_eq!(Duration::parse("42:55:01"), Some(Duration(Number::Int(154_501))));
+    assert_eq!(Duration::parse("9:60"), None);
+    assert_eq!(Duration::parse("9:1"), None);
+}
+
+#[test]
+fn test_range() {
+    assert!(Date::new(Number::Int(0)).is_some());
+    assert!(Date::new(Number::Int(-62_135_596_800)).is_some());
+    assert!(Date::new(Number::Int(-62_135_596_801)).is_none());
+    assert!(Date::new(Number::Int(253_402_300_800)).is_none());
+    assert!(Date::new(Number::NaN).is_none());
+}
+
+#[test]
+fn test_format_duration() {
+    let fmt = |secs: i128| Duration(Number::Int(secs)).to_string();
+    assert_eq!(fmt(30_900), "PT8H35M");
+    assert_eq!(fmt(154_500), "PT42H55M");
+    assert_eq!(fmt(90 * 86_400), "P90D");
+    assert_eq!(fmt(-61), "-PT1M1S");
+    assert_eq!(fmt(0), "PT0S");
+    assert_eq!(Duration(Number::Float(1.5)).to_string(), "PT1.5S");
+    assert_eq!(format!("{:#}", Duration(Number::Int(33_000))), "09:10");
+}
+
+#[test]
+fn test_weekdays() {
+    assert_eq!(date("2026-10-18").weekday(), 7);
+    assert_eq!(date("2026-10-19").weekday(), 1);
+    assert_eq!(date("1970-01-01").weekday(), 4);
+    assert_eq!(date("2026-10-19").workdays(&date("2026-10-26")), 5);
+    assert_eq!(date("2026-10-17").workdays(&date("2026-10-20")), 1);
+    assert_eq!(date("2026-10-01").workdays(&date("2026-11-01")), 22);
+    assert_eq!(date("2026-11-01").workdays(&date("2026-10-01")), -22);
+    assert_eq!(date("2026-10-18").workdays(&date("2026-10-18")), 0);
+}
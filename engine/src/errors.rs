@@ -1,7 +1,7 @@
 use std::fmt::Display;
 
 #[allow(unused)]
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct Span {
     pub pos: usize,
     pub len: usize
@@ -14,6 +14,11 @@ impl Span {
             len
         }
     }
+
+    /// Span from the start of this span to the end of the other
+    pub fn to(&self, end: &Span) -> Span {
+        Span::new(self.pos, (end.pos + end.len).saturating_sub(self.pos))
+    }
 }
 
 #[allow(unused)]
@@ -1,6 +1,20 @@
+use crate::ast::{self, Node, NodeKind};
+use crate::config::Config;
+use crate::date::{Date, Duration};
 use crate::number::Number;
-use crate::function::Function;
+use crate::number::radix::{Radix, Word};
+use crate::function::{Arity, Function};
+use crate::function::integer;
+use crate::function::angle::Angle;
+use crate::matrix::Matrix;
+use crate::{numeric, symbolic};
+use crate::unit::{self, Quantity, Unit};
+use crate::errors::{Error, Span};
+use num_bigint::BigInt;
+use num_traits::{Signed, Zero};
 use std::collections::HashMap;
+use std::fmt::Display;
+use std::rc::Rc;
 
 #[cfg(test)]
 mod tests;
@@ -12,9 +26,44 @@ pub enum Op {
     Mul, // a * b
     Div, // a / b
     Mod, // a % b
-    Pow  // a ^ b
+    Pow, // a ^ b
+    Lt,  // a < b
+    Le,  // a <= b
+    Gt,  // a > b
+    Ge,  // a >= b
+    Eq,  // a == b
+    Ne,  // a != b
+    And, // a && b
+    Or,  // a || b
+    Not, // !a
+    BitAnd, // a & b
+    BitOr,  // a | b
+    Xor,    // a xor b
+    BitNot, // ~a
+    Fact,   // a!
+    Percent, // a%
+    Angle(Angle), // a°, a deg, a rad, a grad
+    Shl,    // a << b
+    Shr,    // a >> b
+    Ushr    // a >>> b
 }
 
+impl Op {
+    pub fn is_comparison(self) -> bool {
+        matches!(self, Op::Lt | Op::Le | Op::Gt | Op::Ge | Op::Eq | Op::Ne)
+    }
+
+    pub fn is_bitwise(self) -> bool {
+        matches!(self, Op::BitAnd | Op::BitOr | Op::Xor | Op::BitNot | Op::Shl | Op::Shr | Op::Ushr)
+    }
+}
+
+/// Number of bits in which >>> sees numbers when no word is set
+pub const WORD_BITS: u32 = 64;
+
+/// Longest list created by range, and most elements of a new matrix
+pub const MAX_LIST_LEN: usize = 1_000_000;
+
 #[derive(Clone, Debug, PartialEq, Eq, Hash)]
 pub struct Ident(String);
 
@@ -24,19 +73,705 @@ impl Ident {
     }
 }
 
+impl Display for Ident {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+/// Function defined in the expression language, e.g. f(x) = x^2 + 1
+#[derive(Clone, Debug, PartialEq)]
+pub struct UserFunction {
+    pub params: Vec<Ident>,
+    pub body: Node
+}
+
 #[derive(Clone, Debug, PartialEq)]
 pub enum Expression {
     Val(Number),
-    BinaryOp(Op),
-    UnaryOp(Op),
-    FuncCall(Function),
-    Assign(Ident),
-    Ref(Ident)
+    Str(String),
+    Date(Date),
+    Duration(Duration),
+    BinaryOp(Op, Span),
+    UnaryOp(Op, Span),
+    /// Calls function from the registry with given number of arguments
+    FuncCall(Function, usize, Span),
+    Assign(Ident, Span),
+    Ref(Ident, Span),
+    /// Attaches unit to the value on top of the stack, e.g. 5 km
+    WithUnit(Unit, Span),
+    /// Converts value on top of the stack to unit, e.g. x to km/h
+    Convert(Unit, Span),
+    /// Wraps integer on top of the stack into the word, e.g. x as u32
+    Cast(Word, Span),
+    /// Stores user function under the name
+    Define(Ident, UserFunction),
+    /// Calls user function with given number of arguments
+    Call(Ident, usize, Span),
+    /// Derivative of the expression of given order
+    Diff(Node, Ident, u32, Span),
+    /// Integral of the program over the variable, bounds are on the stack
+    Integrate(Vec<Expression>, Ident, Span),
+    /// Root of the program in the variable, guess is on the stack
+    Solve(Vec<Expression>, Ident, Span),
+    /// Runs the first program if condition on the stack is true, the second otherwise
+    If(Vec<Expression>, Vec<Expression>, Span),
+    /// && or || with the right side run only when the left one does not decide
+    Logic(Op, Vec<Expression>, Span),
+    /// Replaces integer on top of the stack with product of its prime factors
+    Factor(Span),
+    /// Makes list of given number of values from the top of the stack
+    List(usize, Span),
+    /// Element of the list below the index on top of the stack
+    Index(Span),
+    /// Runs the program for each element of the list on the stack,
+    /// with the variable bound to the element, and lists the results
+    Map(Vec<Expression>, Ident, Span),
+    /// Keeps elements of the list on the stack for which the program is true
+    Filter(Vec<Expression>, Ident, Span)
+}
+
+/// Result of evaluation: a plain number, a number with unit, a string, a date,
+/// a duration, a list or matrix of values or an expression with unknown
+/// variables, like a derivative
+#[derive(Clone, Debug, PartialEq)]
+pub enum Value {
+    Number(Number),
+    Quantity(Quantity),
+    Str(String),
+    Date(Date),
+    Duration(Duration),
+    /// Elements are any values except symbolic ones
+    List(Vec<Value>),
+    /// List of rows of numbers of the same length, like [[1, 2], [3, 4]]
+    Matrix(Matrix),
+    Symbolic(Node),
+    /// Result of a definition like f(x) = x^2, which has no value to print
+    Defined(Ident)
+}
+
+impl Default for Value {
+    fn default() -> Self {
+        Value::Number(Number::NaN)
+    }
+}
+
+impl From<Number> for Value {
+    fn from(value: Number) -> Self {
+        Value::Number(value)
+    }
+}
+
+impl Display for Value {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Value::Number(n) => n.fmt(f),
+            Value::Quantity(q) => q.fmt(f),
+            Value::Str(s) => write!(f, "{}", ast::quote(s)),
+            Value::Date(d) => d.fmt(f),
+            Value::Duration(d) => d.fmt(f),
+            Value::List(items) => {
+                write!(f, "[")?;
+                for (i, item) in items.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    item.fmt(f)?;
+                }
+                write!(f, "]")
+            },
+            Value::Matrix(m) => m.fmt(f),
+            Value::Symbolic(node) => node.fmt(f),
+            Value::Defined(id) => id.fmt(f)
+        }
+    }
+}
+
+impl Value {
+    // Dimensionless quantities are turned into plain numbers,
+    // which needs exchange rates for units like EUR/USD
+    fn from_quantity(value: Number, unit: Unit, config: &Config) -> Result<Value, String> {
+        if unit.is_dimensionless() {
+            Ok(Value::Number(value * unit.exchange_factor(&config.rates)?))
+        } else {
+            Ok(Value::Quantity(Quantity::new(value, unit)))
+        }
+    }
+
+    /// Prints integers in given radix with the word and grouping of the
+    /// config, like `0xff`, other values as usual
+    pub fn to_string_in(&self, radix: Radix, config: &Config) -> String {
+        match self {
+            Value::Number(n) => config.format(n, radix).unwrap_or_else(|| n.to_string()),
+            Value::List(items) => {
+                let items: Vec<_> = items.iter().map(|item| item.to_string_in(radix, config)).collect();
+                format!("[{}]", items.join(", "))
+            },
+            Value::Matrix(m) => Value::List(rows(m)).to_string_in(radix, config),
+            value => value.to_string()
+        }
+    }
+
+    // numeric part of the value
+    fn number(&self) -> &Number {
+        match self {
+            Value::Number(n) => n,
+            Value::Quantity(q) => &q.value,
+            Value::Str(_) | Value::Date(_) | Value::Duration(_) | Value::List(_) | Value::Matrix(_)
+                | Value::Symbolic(_) | Value::Defined(_) => &Number::NaN
+        }
+    }
+
+    pub(crate) fn into_quantity(self) -> Quantity {
+        match self {
+            Value::Number(n) => Quantity::new(n, Unit::dimensionless()),
+            Value::Quantity(q) => q,
+            Value::Duration(d) => Quantity::new(d.0, second()),
+            Value::Str(_) | Value::Date(_) | Value::List(_) | Value::Matrix(_) | Value::Symbolic(_) | Value::Defined(_) => {
+                Quantity::new(Number::NaN, Unit::dimensionless())
+            }
+        }
+    }
+}
+
+fn second() -> Unit {
+    unit::parse_unit("s").expect("second")
+}
+
+pub(crate) fn number_op(op: Op, l: Number, r: Number, config: &Config) -> Number {
+    match op {
+        Op::Add => l + r,
+        Op::Sub => l - r,
+        Op::Mul => l * r,
+        Op::Div => l / r,
+        Op::Mod => l % r,
+        Op::Pow => l ^ r,
+        Op::Eq => equal(&l, &r).into(),
+        Op::Ne => (!equal(&l, &r)).into(),
+        Op::And => (!l.is_zero() && !r.is_zero()).into(),
+        Op::Or => (!l.is_zero() || !r.is_zero()).into(),
+        Op::Not => l.is_zero().into(),
+        Op::BitAnd => l.bit_and(&r),
+        Op::BitOr => l.bit_or(&r),
+        Op::Xor => l.bit_xor(&r),
+        Op::BitNot => l.bit_not(),
+        Op::Fact => integer::factorial(&l),
+        Op::Percent => l / Number::Int(100),
+        Op::Shl => l.shl(&r),
+        Op::Shr => l.shr(&r),
+        Op::Ushr => l.ushr(&r, config.word.map_or(WORD_BITS, |w| w.bits)),
+        _ => match l.compare(&r) {
+            Some(ord) => match op {
+                Op::Lt => ord.is_lt(),
+                Op::Le => ord.is_le(),
+                Op::Gt => ord.is_gt(),
+                _ => ord.is_ge()
+            }.into(),
+            None => Number::NaN
+        }
+    }
+}
+
+// complex numbers can be equal but not ordered
+fn equal(l: &Number, r: &Number) -> bool {
+    match l.compare(r) {
+        Some(ord) => ord.is_eq(),
+        None => l.to_complex().is_some() && l.to_complex() == r.to_complex()
+    }
+}
+
+fn not_integer(n: &Number, span: &Span) -> Error {
+    Error::new(&format!("Bitwise operators need integers, got {n}"), span.clone())
+}
+
+// integers wrap around in the word set for the session
+fn wrap(value: Value, config: &Config) -> Value {
+    match value {
+        Value::Number(n) => Value::Number(config.wrap(n)),
+        Value::List(items) => Value::List(items.into_iter().map(|item| wrap(item, config)).collect()),
+        Value::Matrix(m) => Value::Matrix(m.map(|n| config.wrap(n))),
+        value => value
+    }
+}
+
+// any value except zero and empty string is true,
+// a list is true when all its elements are
+fn is_true(value: &Value) -> bool {
+    match value {
+        Value::Str(s) => !s.is_empty(),
+        Value::Duration(d) => !d.0.is_zero(),
+        Value::List(items) => items.iter().all(is_true),
+        Value::Matrix(m) => m.elements().iter().all(|n| !n.is_zero()),
+        value => !value.number().is_zero()
+    }
+}
+
+// list of rows of numbers of the same length is a matrix
+pub(crate) fn list(items: Vec<Value>) -> Value {
+    let cols = match items.first() {
+        Some(Value::List(row)) if !row.is_empty() => row.len(),
+        _ => return Value::List(items)
+    };
+    let is_row = |item: &Value| matches!(item, Value::List(row)
+        if row.len() == cols && row.iter().all(|n| matches!(n, Value::Number(_))));
+    if !items.iter().all(is_row) {
+        return Value::List(items);
+    }
+    let rows = items.len();
+    let data = items.into_iter().flat_map(|row| match row {
+        Value::List(row) => row.into_iter().map(|n| n.number().clone()).collect(),
+        _ => vec![]
+    }).collect();
+    Value::Matrix(Matrix::new(rows, cols, data).expect("rows of the same length"))
+}
+
+pub(crate) fn rows(m: &Matrix) -> Vec<Value> {
+    (0..m.rows).map(|i| Value::List(m.row(i).iter().cloned().map(Value::Number).collect())).collect()
 }
 
+// matrix as the list of its rows, so that it is mapped like other lists
+pub(crate) fn unpack(value: Value) -> Value {
+    match value {
+        Value::Matrix(m) => Value::List(rows(&m)),
+        value => value
+    }
+}
+
+// list of numbers as a matrix of one column
+pub(crate) fn column(items: &[Value]) -> Option<Matrix> {
+    let data = items.iter().map(|item| match item {
+        Value::Number(n) => Some(n.clone()),
+        _ => None
+    }).collect::<Option<Vec<_>>>()?;
+    Matrix::new(data.len(), 1, data)
+}
+
+pub(crate) fn map_list<F, E>(items: Vec<Value>, f: F) -> Result<Value, E>
+    where F: FnMut(Value) -> Result<Value, E>
+{
+    items.into_iter().map(f).collect::<Result<_, _>>().map(list)
+}
+
+fn same_length(l: usize, r: usize, span: &Span) -> Result<(), Error> {
+    if l != r {
+        return Err(Error::new(&format!("Lists have different lengths: {l} and {r}"), span.clone()));
+    }
+    Ok(())
+}
+
+// rows of a matrix are its elements
+pub(crate) fn expect_list(value: Value) -> Result<Vec<Value>, String> {
+    match value {
+        Value::List(items) => Ok(items),
+        Value::Matrix(m) => Ok(rows(&m)),
+        value => Err(format!("Expected list, got {value}"))
+    }
+}
+
+// NaN elements come from numbers too large to represent
+pub(crate) fn checked(res: Result<Matrix, String>) -> Result<Matrix, String> {
+    match res {
+        Ok(m) if m.elements().contains(&Number::NaN) => Err(String::from("Overflow")),
+        res => res
+    }
+}
+
+// elements of a matrix of one row or column
+pub(crate) fn vector(m: Matrix) -> Value {
+    Value::List(m.elements().iter().cloned().map(Value::Number).collect())
+}
+
+pub(crate) fn units_not_accepted(unit: &Unit) -> String {
+    format!("Function does not accept units: {unit}")
+}
+
+// numeric methods work with real values only
+fn real(n: Number, span: &Span) -> Result<f64, Error> {
+    let x = f64::from(n.clone());
+    if x.is_nan() {
+        return Err(Error::new(&format!("Expected real number, got {n}"), span.clone()));
+    }
+    Ok(x)
+}
+
+// real value of a number, for numeric methods and ranges
+fn to_real(value: Value, span: &Span) -> Result<f64, Error> {
+    match value {
+        Value::Number(n) => real(n, span),
+        Value::Quantity(q) => Err(Error::new(&units_not_accepted(&q.unit), span.clone())),
+        value => Err(Error::new(&format!("Expected real number, got {value}"), span.clone()))
+    }
+}
+
+// lists are combined element by element, and a single value
+// is combined with each element of a list
+fn binary_op(op: Op, l: Value, r: Value, config: &Config, span: &Span) -> Result<Value, Error> {
+    match (l, r) {
+        (l @ Value::Matrix(_), r) | (l, r @ Value::Matrix(_)) => matrix_op(op, l, r, config, span),
+        (Value::List(l), Value::List(r)) => {
+            same_length(l.len(), r.len(), span)?;
+            l.into_iter().zip(r).map(|(l, r)| binary_op(op, l, r, config, span)).collect::<Result<_, _>>().map(Value::List)
+        },
+        (Value::List(l), r) => map_list(l, |l| binary_op(op, l, r.clone(), config, span)),
+        (l, Value::List(r)) => map_list(r, |r| binary_op(op, l.clone(), r, config, span)),
+        (l @ Value::Str(_), r) | (l, r @ Value::Str(_)) => string_op(op, l, r, config, span),
+        (l @ (Value::Date(_) | Value::Duration(_)), r) | (l, r @ (Value::Date(_) | Value::Duration(_))) => {
+            time_op(op, l, r, config, span)
+        },
+        (l, r) => scalar_op(op, l, r, config, span)
+    }
+}
+
+// * multiplies matrices, a list of numbers is a column on the right side
+// of a matrix and a row on the left side, other operators work element by element
+fn matrix_op(op: Op, l: Value, r: Value, config: &Config, span: &Span) -> Result<Value, Error> {
+    let err = |msg: String| Err(Error::new(&msg, span.clone()));
+    let checked = |res| checked(res).map_err(|msg| Error::new(&msg, span.clone()));
+    match (op, l, r) {
+        (Op::Mul, Value::Matrix(a), Value::Matrix(b)) => Ok(Value::Matrix(checked(a.mul(&b))?)),
+        (Op::Mul, Value::Matrix(a), Value::List(v)) => match column(&v) {
+            Some(b) if a.cols == b.rows => Ok(vector(checked(a.mul(&b))?)),
+            Some(_) => err(format!("Cannot multiply {} matrix by list of length {}", a.size(), v.len())),
+            None => err(format!("Cannot multiply matrix by {}", Value::List(v)))
+        },
+        (Op::Mul, Value::List(v), Value::Matrix(b)) => match column(&v) {
+            Some(a) if a.rows == b.rows => Ok(vector(checked(a.transpose().mul(&b))?)),
+            Some(_) => err(format!("Cannot multiply list of length {} by {} matrix", v.len(), b.size())),
+            None => err(format!("Cannot multiply {} by matrix", Value::List(v)))
+        },
+        (Op::Div, _, Value::Matrix(_)) => err(String::from("Cannot divide by matrix, multiply by its inverse")),
+        (Op::Pow, Value::Matrix(a), Value::Number(Number::Int(n))) => Ok(Value::Matrix(checked(a.pow(n))?)),
+        (Op::Pow, ..) => err(String::from("Matrices can only be raised to integer powers")),
+        (_, Value::Matrix(a), Value::Matrix(b)) if a.rows != b.rows || a.cols != b.cols => {
+            err(format!("Matrices have different sizes: {} and {}", a.size(), b.size()))
+        },
+        (_, Value::Matrix(a), Value::List(v)) | (_, Value::List(v), Value::Matrix(a)) => {
+            err(format!("Cannot combine {} matrix with list of length {}", a.size(), v.len()))
+        },
+        (op, l, r) => binary_op(op, unpack(l), unpack(r), config, span)
+    }
+}
+
+// + joins strings, a value joined with a string is printed as usual.
+// Strings compare in the order of their characters.
+fn string_op(op: Op, l: Value, r: Value, config: &Config, span: &Span) -> Result<Value, Error> {
+    let text = |value: Value| match value {
+        Value::Str(s) => s,
+        value => value.to_string()
+    };
+    match (op, l, r) {
+        (Op::Add, l, r) => Ok(Value::Str(text(l) + &text(r))),
+        (op, Value::Str(l), Value::Str(r)) if op.is_comparison() => {
+            Ok(Value::Number(number_op(op, Number::Int(l.cmp(&r) as i128), Number::Int(0), config)))
+        },
+        (Op::Eq, ..) => Ok(Value::Number(Number::Int(0))),
+        (Op::Ne, ..) => Ok(Value::Number(Number::Int(1))),
+        (op, ..) if op.is_comparison() => Err(Error::new("Cannot compare strings and numbers", span.clone())),
+        _ => Err(Error::new("Strings can only be joined with + or compared", span.clone()))
+    }
+}
+
+// Dates and durations count seconds. Durations are added to dates and
+// combined with each other and with quantities of time, like 90 days.
+fn time_op(op: Op, l: Value, r: Value, config: &Config, span: &Span) -> Result<Value, Error> {
+    let secs = |value: &Value| match value {
+        Value::Duration(d) => Some(d.0.clone()),
+        Value::Quantity(q) => q.convert(&second(), &config.rates).ok().map(|q| q.value),
+        _ => None
+    };
+    let (ls, rs) = (secs(&l), secs(&r));
+    let res = match (op, &l, &r, ls, rs) {
+        (Op::Add, Value::Date(d), _, _, Some(s)) | (Op::Add, _, Value::Date(d), Some(s), _) => {
+            Value::Date(date(d.0.clone() + s, span)?)
+        },
+        (Op::Sub, Value::Date(d), _, _, Some(s)) => Value::Date(date(d.0.clone() - s, span)?),
+        (Op::Sub, Value::Date(a), Value::Date(b), ..) => Value::Duration(Duration(a.0.clone() - b.0.clone())),
+        (op, Value::Date(a), Value::Date(b), ..) if op.is_comparison() => {
+            Value::Number(number_op(op, a.0.clone(), b.0.clone(), config))
+        },
+        (Op::Add | Op::Sub | Op::Mod, .., Some(l), Some(r)) => Value::Duration(Duration(number_op(op, l, r, config))),
+        (op, .., Some(l), Some(r)) if op.is_comparison() => Value::Number(number_op(op, l, r, config)),
+        (Op::Div, .., Some(l), Some(r)) => Value::Number(l / r),
+        (Op::Mul, Value::Duration(d), Value::Number(n), ..) | (Op::Mul, Value::Number(n), Value::Duration(d), ..) => {
+            Value::Duration(Duration(d.0.clone() * n.clone()))
+        },
+        (Op::Div, Value::Duration(d), Value::Number(n), ..) => Value::Duration(Duration(d.0.clone() / n.clone())),
+        _ => {
+            let msg = format!("Invalid operation on {} and {}", describe(&l), describe(&r));
+            return Err(Error::new(&msg, span.clone()));
+        }
+    };
+    match res {
+        Value::Number(Number::NaN) | Value::Duration(Duration(Number::NaN)) if matches!(op, Op::Div | Op::Mod) => {
+            Err(Error::new("Division by zero", span.clone()))
+        },
+        Value::Number(Number::NaN) | Value::Duration(Duration(Number::NaN)) => Err(Error::new("Overflow", span.clone())),
+        res => Ok(res)
+    }
+}
+
+fn date(secs: Number, span: &Span) -> Result<Date, Error> {
+    Date::new(secs).ok_or_else(|| Error::new("Date is out of range", span.clone()))
+}
+
+// kind of value for error messages
+fn describe(value: &Value) -> String {
+    match value {
+        Value::Number(_) => String::from("number"),
+        Value::Quantity(q) => unit::describe(&q.unit),
+        Value::Str(_) => String::from("string"),
+        Value::Date(_) => String::from("date"),
+        Value::Duration(_) => String::from("duration"),
+        Value::List(_) | Value::Matrix(_) => String::from("list"),
+        Value::Symbolic(_) => String::from("expression"),
+        Value::Defined(_) => String::from("definition")
+    }
+}
+
+// NaN result of an operation on valid numbers means either
+// division by zero or a number too large to represent
+fn scalar_op(op: Op, l: Value, r: Value, config: &Config, span: &Span) -> Result<Value, Error> {
+    let div_by_zero = match op {
+        Op::Div | Op::Mod => r.number().is_zero(),
+        Op::Pow => l.number().is_zero() && r.number().is_negative(),
+        _ => false
+    };
+    // bitwise operators fail either on a number that is not an integer or on the shift
+    let not_int = [l.number(), r.number()].into_iter().find(|n| n.to_bigint().is_none()).cloned();
+    let res = match (l, r) {
+        (Value::Number(l), Value::Number(r)) => Value::Number(number_op(op, l, r, config)),
+        (l, r) => quantity_op(op, l.into_quantity(), r.into_quantity(), config)
+            .map_err(|msg| Error::new(&msg, span.clone()))?
+    };
+    match (res.number(), not_int) {
+        (Number::NaN, _) if div_by_zero => Err(Error::new("Division by zero", span.clone())),
+        (Number::NaN, _) if op.is_comparison() => Err(Error::new("Cannot compare complex numbers", span.clone())),
+        (Number::NaN, Some(n)) if op.is_bitwise() => Err(not_integer(&n, span)),
+        (Number::NaN, _) if op.is_bitwise() => Err(Error::new("Invalid shift amount", span.clone())),
+        (Number::NaN, _) => Err(Error::new("Overflow", span.clone())),
+        _ => Ok(wrap(res, config))
+    }
+}
+
+fn unary_op(op: Op, arg: Value, config: &Config, span: &Span) -> Result<Value, Error> {
+    let res = match (op, unpack(arg)) {
+        (op, Value::List(items)) => return map_list(items, |item| unary_op(op, item, config, span)),
+        (Op::Add, arg) => arg,
+        (Op::Sub, Value::Number(n)) => Value::Number(-n),
+        (Op::Sub, Value::Quantity(q)) => Value::Quantity(Quantity::new(-q.value, q.unit)),
+        (Op::Sub, Value::Duration(d)) => Value::Duration(Duration(-d.0)),
+        (Op::Not, arg) => Value::Number((!is_true(&arg)).into()),
+        (Op::BitNot, Value::Number(n)) => match n.bit_not() {
+            Number::NaN => return Err(not_integer(&n, span)),
+            res => Value::Number(res)
+        },
+        (Op::BitNot, Value::Quantity(_)) => return Err(Error::new("Bitwise operators do not accept units", span.clone())),
+        (Op::Fact, Value::Number(n)) => match integer::factorial(&n) {
+            Number::NaN if n.to_bigint().is_some() && !n.is_negative() => return Err(Error::new("Overflow", span.clone())),
+            Number::NaN => return Err(Error::new(&format!("Factorial is not defined for {n}"), span.clone())),
+            res => Value::Number(res)
+        },
+        (Op::Fact, Value::Quantity(_)) => return Err(Error::new("Factorial does not accept units", span.clone())),
+        (Op::Percent, Value::Number(n)) => Value::Number(n / Number::Int(100)),
+        (Op::Percent, Value::Quantity(q)) => Value::Quantity(Quantity::new(q.value / Number::Int(100), q.unit)),
+        (Op::Angle(unit), Value::Number(n)) => Value::Number(unit.convert(n, config.angle, config.precision)),
+        (Op::Angle(_), Value::Quantity(_)) => return Err(Error::new("Angles do not accept units", span.clone())),
+        _ => return Err(Error::new("Invalid unary operator", span.clone()))
+    };
+    Ok(wrap(res, config))
+}
+
+// Functions of a fixed number of arguments are applied to each element
+// of list arguments, functions of any number of arguments get elements
+// of the lists as separate arguments, so sum([1, 2]) is sum(1, 2).
+// Functions of values like len get the lists as they are.
+fn call_function(f: &Function, mut args: Vec<Value>, config: &Config, span: &Span) -> Result<Value, Error> {
+    if f.takes_values() {
+        if let Some(Value::Symbolic(node)) = args.iter().find(|arg| matches!(arg, Value::Symbolic(_))) {
+            let msg = format!("Cannot compute with symbolic expression {node}");
+            return Err(Error::new(&msg, span.clone()));
+        }
+        return f.exec_values(&mut args, config).map_err(|msg| Error::new(&msg, span.clone()));
+    }
+    let args: Vec<_> = args.into_iter().map(unpack).collect();
+    let len = args.iter().find_map(|arg| match arg {
+        Value::List(items) => Some(items.len()),
+        _ => None
+    });
+    match (f.arity, len) {
+        (Arity::Variadic(_), _) | (_, None) => {
+            let mut numbers = vec![];
+            for arg in args {
+                flatten(arg, &mut numbers, span)?;
+            }
+            match f.exec(&mut numbers, config) {
+                Number::NaN => Err(Error::new(&format!("Invalid argument for {}", f.name), span.clone())),
+                res => Ok(Value::Number(config.wrap(res)))
+            }
+        },
+        (_, Some(len)) => {
+            let mut columns = vec![];
+            for arg in args {
+                columns.push(match arg {
+                    Value::List(items) => {
+                        same_length(len, items.len(), span)?;
+                        items
+                    },
+                    arg => vec![arg; len]
+                });
+            }
+            let mut res = Vec::with_capacity(len);
+            for i in 0..len {
+                let args = columns.iter_mut().map(|column| std::mem::take(&mut column[i])).collect();
+                res.push(call_function(f, args, config, span)?);
+            }
+            Ok(list(res))
+        }
+    }
+}
+
+// numbers of the value, including those in nested lists
+fn flatten(value: Value, numbers: &mut Vec<Number>, span: &Span) -> Result<(), Error> {
+    match value {
+        Value::Number(n) => numbers.push(n),
+        Value::Quantity(q) => return Err(Error::new(&units_not_accepted(&q.unit), span.clone())),
+        Value::List(items) => {
+            for item in items {
+                flatten(item, numbers, span)?;
+            }
+        },
+        Value::Matrix(m) => numbers.extend_from_slice(m.elements()),
+        Value::Str(_) | Value::Date(_) | Value::Duration(_) | Value::Defined(_) => {
+            return Err(Error::new(&format!("Expected number, got {value}"), span.clone()));
+        },
+        Value::Symbolic(node) => {
+            let msg = format!("Cannot compute with symbolic expression {node}");
+            return Err(Error::new(&msg, span.clone()));
+        }
+    }
+    Ok(())
+}
+
+fn with_unit(value: Value, unit: &Unit, config: &Config, span: &Span) -> Result<Value, Error> {
+    match unpack(value) {
+        Value::List(items) => map_list(items, |item| with_unit(item, unit, config, span)),
+        value @ (Value::Str(_) | Value::Date(_) | Value::Duration(_)) => {
+            Err(Error::new(&format!("Cannot attach unit to {value}"), span.clone()))
+        },
+        value => {
+            let q = value.into_quantity();
+            Value::from_quantity(q.value, q.unit.mul(unit), config).map_err(|msg| Error::new(&msg, span.clone()))
+        }
+    }
+}
+
+fn convert(value: Value, unit: &Unit, config: &Config, span: &Span) -> Result<Value, Error> {
+    let q = match unpack(value) {
+        Value::List(items) => return map_list(items, |item| convert(item, unit, config, span)),
+        value @ (Value::Str(_) | Value::Date(_)) => {
+            return Err(Error::new(&format!("Cannot convert {value} to {}", unit::describe(unit)), span.clone()));
+        },
+        value => value.into_quantity()
+    };
+    if !q.unit.same_dimension(unit) {
+        let msg = format!("Cannot convert {} to {}", unit::describe(&q.unit), unit::describe(unit));
+        return Err(Error::new(&msg, span.clone()));
+    }
+    match q.exchange(unit, &config.rates) {
+        Ok(q) => Ok(Value::Quantity(q)),
+        Err(msg) => Err(Error::new(&msg, span.clone()))
+    }
+}
+
+fn cast(value: Value, word: &Word, span: &Span) -> Result<Value, Error> {
+    let res = match unpack(value) {
+        Value::Number(n) => word.wrap(&n).ok_or_else(|| format!("Cannot convert {n} to {word}")),
+        Value::Quantity(q) => Err(format!("Cannot convert {} to {word}", unit::describe(&q.unit))),
+        Value::List(items) => return map_list(items, |item| cast(item, word, span)),
+        value => Err(format!("Cannot convert {value} to {word}"))
+    };
+    match res {
+        Ok(n) => Ok(Value::Number(n)),
+        Err(msg) => Err(Error::new(&msg, span.clone()))
+    }
+}
+
+// element at the index, negative indices count from the end,
+// elements of a string are its characters
+fn index(list: Value, i: Value, span: &Span) -> Result<Value, Error> {
+    let mut items = match unpack(list) {
+        Value::List(items) => items,
+        Value::Str(s) => s.chars().map(|c| Value::Str(c.to_string())).collect(),
+        value => return Err(Error::new(&format!("Cannot index {value}, it is not a list"), span.clone()))
+    };
+    let len = items.len();
+    let n = match i {
+        Value::Number(n) => n,
+        i => return Err(Error::new(&format!("Index must be an integer, got {i}"), span.clone()))
+    };
+    let pos = match n.to_bigint() {
+        Some(i) if i.is_negative() => BigInt::from(len) + i,
+        Some(i) => i,
+        None => return Err(Error::new(&format!("Index must be an integer, got {n}"), span.clone()))
+    };
+    match usize::try_from(pos) {
+        Ok(pos) if pos < len => Ok(items.swap_remove(pos)),
+        _ => Err(Error::new(&format!("Index {n} is out of range for list of length {len}"), span.clone()))
+    }
+}
+
+fn quantity_op(op: Op, l: Quantity, r: Quantity, config: &Config) -> Result<Value, String> {
+    match op {
+        _ if op.is_comparison() => {
+            if !l.unit.same_dimension(&r.unit) {
+                return Err(format!("Cannot compare {} and {}", unit::describe(&l.unit), unit::describe(&r.unit)));
+            }
+            let converted = r.convert(&l.unit, &config.rates)?;
+            Ok(Value::Number(number_op(op, l.value, converted.value, config)))
+        },
+        Op::Add | Op::Sub | Op::Mod => {
+            if !l.unit.same_dimension(&r.unit) {
+                return Err(format!("Incompatible units: {} and {}", unit::describe(&l.unit), unit::describe(&r.unit)));
+            }
+            let converted = r.convert(&l.unit, &config.rates)?;
+            Value::from_quantity(number_op(op, l.value, converted.value, config), l.unit, config)
+        },
+        Op::Mul => Value::from_quantity(l.value * r.value, l.unit.mul(&r.unit), config),
+        Op::Div => Value::from_quantity(l.value / r.value, l.unit.div(&r.unit), config),
+        Op::Pow => {
+            if !r.unit.is_dimensionless() {
+                return Err(String::from("Exponent cannot have units"));
+            }
+            match r.base_value() {
+                Number::Int(n) if i32::try_from(n).is_ok() => {
+                    Value::from_quantity(l.value ^ Number::Int(n), l.unit.powi(n as i32), config)
+                },
+                _ => Err(String::from("Units can only be raised to integer powers"))
+            }
+        },
+        _ if op.is_bitwise() => Err(String::from("Bitwise operators do not accept units")),
+        // logical operators look only at the values
+        _ => Ok(Value::Number(number_op(op, l.value, r.value, config)))
+    }
+}
+
+/// Limit of nested user function calls, reached by runaway recursion
+pub const MAX_CALL_DEPTH: usize = 256;
+
 pub struct Calculator {
-    stack: Vec<Number>,
-    vars: HashMap<Ident, Number>
+    stack: Vec<Value>,
+    vars: HashMap<Ident, Value>,
+    // user functions with their bodies lowered when defined
+    funcs: HashMap<Ident, Rc<(UserFunction, Vec<Expression>)>>,
+    // arguments of user functions being evaluated, innermost call last
+    frames: Vec<HashMap<Ident, Value>>,
+    config: Config
+}
+
+impl Default for Calculator {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Calculator {
@@ -44,66 +779,474 @@ impl Calculator {
     pub fn new() -> Self {
         Calculator {
             stack: vec![],
-            vars: HashMap::new()
+            vars: HashMap::new(),
+            funcs: HashMap::new(),
+            frames: vec![],
+            config: Config::default()
         }
     }
 
-    pub fn eval(&mut self, program: &[Expression]) -> Number {
+    /// Settings of the session, like precision of floats or the word
+    /// integers wrap around in
+    pub fn config(&self) -> &Config {
+        &self.config
+    }
+
+    pub fn config_mut(&mut self) -> &mut Config {
+        &mut self.config
+    }
+
+    /// Angle mode, unit of angles taken and returned by sin, asin and others
+    pub fn angle(&self) -> Angle {
+        self.config.angle
+    }
+
+    pub fn set_angle(&mut self, angle: Angle) {
+        self.config.angle = angle;
+    }
+
+    pub fn eval(&mut self, program: &[Expression]) -> Result<Value, Error> {
         self.stack.clear();
+        self.frames.clear();
+        self.run(program)?;
+        Ok(self.stack.pop().unwrap_or_default())
+    }
+
+    fn run(&mut self, program: &[Expression]) -> Result<(), Error> {
         for ex in program {
             match ex {
-                Expression::Val(n) => self.stack.push(*n),
-                Expression::BinaryOp(op) => self.eval_binary_op(*op),
-                Expression::UnaryOp(op) => self.eval_unary_op(*op),
-                Expression::FuncCall(f) => self.eval_func_call(f),
-                Expression::Assign(id) => self.eval_assign(id),
-                Expression::Ref(id) => self.eval_ref(id)
+                Expression::Val(n) => self.stack.push(Value::Number(self.config.wrap(n.clone()))),
+                Expression::Str(s) => self.stack.push(Value::Str(s.clone())),
+                Expression::Date(d) => self.stack.push(Value::Date(d.clone())),
+                Expression::Duration(d) => self.stack.push(Value::Duration(d.clone())),
+                Expression::BinaryOp(op, span) => self.eval_binary_op(*op, span)?,
+                Expression::UnaryOp(op, span) => self.eval_unary_op(*op, span)?,
+                Expression::FuncCall(f, arg_count, span) => self.eval_func_call(f, *arg_count, span)?,
+                Expression::Assign(id, span) => self.eval_assign(id, span)?,
+                Expression::Ref(id, span) => self.eval_ref(id, span)?,
+                Expression::WithUnit(unit, span) => self.eval_with_unit(unit, span)?,
+                Expression::Convert(unit, span) => self.eval_convert(unit, span)?,
+                Expression::Cast(word, span) => self.eval_cast(word, span)?,
+                Expression::Factor(span) => self.eval_factor(span)?,
+                Expression::Define(id, f) => self.eval_define(id, f),
+                Expression::Call(id, arg_count, span) => self.eval_call(id, *arg_count, span)?,
+                Expression::Diff(node, var, order, span) => self.eval_diff(node, var, *order, span)?,
+                Expression::Integrate(body, var, span) => self.eval_integrate(body, var, span)?,
+                Expression::Solve(body, var, span) => self.eval_solve(body, var, span)?,
+                Expression::If(then, otherwise, span) => self.eval_if(then, otherwise, span)?,
+                Expression::Logic(op, rhs, span) => self.eval_logic(*op, rhs, span)?,
+                Expression::List(count, span) => self.eval_list(*count, span)?,
+                Expression::Index(span) => self.eval_index(span)?,
+                Expression::Map(body, var, span) => self.eval_map(body, var, span)?,
+                Expression::Filter(body, var, span) => self.eval_filter(body, var, span)?
             }
         }
-        self.stack.pop().unwrap_or_default()
+        Ok(())
     }
 
-    fn eval_binary_op(&mut self, op: Op) {
-        let r = self.stack.pop().unwrap_or_default();
-        let l = self.stack.pop().unwrap_or_default();
-        let res = match op {
-            Op::Add => l + r,
-            Op::Sub => l - r,
-            Op::Mul => l * r,
-            Op::Div => l / r,
-            Op::Mod => l % r,
-            Op::Pow => l ^ r
-        };
+    fn pop(&mut self, span: &Span) -> Result<Value, Error> {
+        self.stack.pop().ok_or_else(|| Error::new("Stack underflow", span.clone()))
+    }
+
+    // symbolic values can be stored in variables but not computed with
+    fn pop_numeric(&mut self, span: &Span) -> Result<Value, Error> {
+        let val = self.pop(span)?;
+        self.numeric(val, span)
+    }
+
+    // Expressions without variables, like the product from factor(360),
+    // are evaluated, so factor(360) + 1 is 361.
+    fn numeric(&mut self, val: Value, span: &Span) -> Result<Value, Error> {
+        match val {
+            Value::Symbolic(node) if symbolic::free_vars(&node).is_empty() => {
+                self.run(&node.lower())?;
+                self.pop(span)
+            },
+            Value::Symbolic(node) => {
+                let msg = format!("Cannot compute with symbolic expression {node}");
+                Err(Error::new(&msg, span.clone()))
+            },
+            val => Ok(val)
+        }
+    }
+
+    fn pop_args(&mut self, arg_count: usize, span: &Span) -> Result<Vec<Value>, Error> {
+        if self.stack.len() < arg_count {
+            return Err(Error::new("Stack underflow", span.clone()));
+        }
+        Ok(self.stack.split_off(self.stack.len() - arg_count))
+    }
+
+    fn eval_binary_op(&mut self, op: Op, span: &Span) -> Result<(), Error> {
+        let r = self.pop_numeric(span)?;
+        let l = self.pop_numeric(span)?;
+        let res = binary_op(op, l, r, &self.config, span)?;
         self.stack.push(res);
+        Ok(())
     }
 
-    fn eval_unary_op(&mut self, op: Op) {
-        let arg = self.stack.pop().unwrap_or_default();
-        let res = match op {
-            Op::Add => arg,
-            Op::Sub => -arg,
-            _ => Number::NaN
-        };
+    fn eval_unary_op(&mut self, op: Op, span: &Span) -> Result<(), Error> {
+        let arg = self.pop_numeric(span)?;
+        let res = unary_op(op, arg, &self.config, span)?;
         self.stack.push(res);
+        Ok(())
     }
 
-    fn eval_func_call(&mut self, f: &Function) {
-        let res = f.exec(&mut self.stack);
+    fn eval_func_call(&mut self, f: &Function, arg_count: usize, span: &Span) -> Result<(), Error> {
+        let args = self.pop_args(arg_count, span)?.into_iter()
+            .map(|arg| self.numeric(arg, span))
+            .collect::<Result<Vec<_>, _>>()?;
+        let res = call_function(f, args, &self.config, span)?;
         self.stack.push(res);
+        Ok(())
     }
 
-    fn eval_assign(&mut self, id: &Ident) {
-        let val = self.stack.pop().unwrap_or_default();
-        self.vars.insert(id.clone(), val);
+    fn eval_assign(&mut self, id: &Ident, span: &Span) -> Result<(), Error> {
+        let val = self.pop(span)?;
+        self.vars.insert(id.clone(), val.clone());
         self.stack.push(val);
+        Ok(())
     }
 
-    fn eval_ref(&mut self, id: &Ident) {
-        let val = match self.vars.get(id) {
-            Some(n) => n.clone(),
-            None => Number::NaN
+    // function arguments shadow variables
+    fn eval_ref(&mut self, id: &Ident, span: &Span) -> Result<(), Error> {
+        let arg = self.frames.last().and_then(|frame| frame.get(id));
+        match arg.or_else(|| self.vars.get(id)) {
+            Some(val) => self.stack.push(val.clone()),
+            None => return Err(Error::new(&format!("Unknown variable {}", id.0), span.clone()))
+        }
+        Ok(())
+    }
+
+    // a variable named like the unit wins over it, after g = 9.81
+    // 2g is 2*g and not 2 grams
+    // Variables shadow unit symbols of the same name in every term of the
+    // unit, after s = 4 8 m/s is 2 m
+    fn eval_with_unit(&mut self, unit: &Unit, span: &Span) -> Result<(), Error> {
+        let mut res = self.pop_numeric(span)?;
+        let vars: Vec<_> = unit.terms().iter()
+            .filter_map(|(symbol, pow)| Some((self.lookup(&Ident::new(symbol))?.clone(), *pow)))
+            .collect();
+        if vars.is_empty() {
+            let res = with_unit(res, unit, &self.config, span)?;
+            self.stack.push(res);
+            return Ok(());
+        }
+        for (var, pow) in vars {
+            let var = match pow {
+                1 => var,
+                _ => binary_op(Op::Pow, var, Value::Number(Number::Int(pow as i128)), &self.config, span)?
+            };
+            res = binary_op(Op::Mul, res, var, &self.config, span)?;
+        }
+        let rest = unit.retain(|symbol| self.lookup(&Ident::new(symbol)).is_none());
+        if !rest.terms().is_empty() {
+            res = with_unit(res, &rest, &self.config, span)?;
+        }
+        self.stack.push(res);
+        Ok(())
+    }
+
+    fn eval_convert(&mut self, unit: &Unit, span: &Span) -> Result<(), Error> {
+        let value = self.pop_numeric(span)?;
+        let res = convert(value, unit, &self.config, span)?;
+        self.stack.push(res);
+        Ok(())
+    }
+
+    fn eval_cast(&mut self, word: &Word, span: &Span) -> Result<(), Error> {
+        let value = self.pop_numeric(span)?;
+        let res = cast(value, word, span)?;
+        self.stack.push(res);
+        Ok(())
+    }
+
+    // the result is kept as an expression like 2^3*3^2*5,
+    // so that it is printed factored
+    fn eval_factor(&mut self, span: &Span) -> Result<(), Error> {
+        let n = match self.pop_numeric(span)? {
+            Value::Number(n) => n,
+            Value::Quantity(q) => return Err(Error::new(&units_not_accepted(&q.unit), span.clone())),
+            value => return Err(Error::new(&format!("Cannot factor {value}"), span.clone()))
         };
-        self.stack.push(val);
+        let int = match n.to_bigint() {
+            Some(int) if !int.is_zero() => int,
+            _ => return Err(Error::new(&format!("Cannot factor {n}"), span.clone()))
+        };
+        let factors = match integer::factor(&int.abs()) {
+            Some(factors) => factors,
+            None => return Err(Error::new("Factors are too large to find", span.clone()))
+        };
+        let num = |n: BigInt| Node::new(NodeKind::Num(n.into()), span.clone());
+        let mut terms: Vec<_> = factors.into_iter().map(|(p, power)| match power {
+            1 => num(p),
+            _ => Node::new(NodeKind::Binary(Op::Pow, span.clone(), Box::new(num(p)), Box::new(num(power.into()))), span.clone())
+        }).collect();
+        if int.is_negative() || terms.is_empty() {
+            terms.insert(0, num(int.signum()));
+        }
+        let product = terms.into_iter()
+            .reduce(|acc, t| Node::new(NodeKind::Binary(Op::Mul, span.clone(), Box::new(acc), Box::new(t)), span.clone()));
+        self.stack.push(Value::Symbolic(product.unwrap_or_else(|| num(int))));
+        Ok(())
+    }
+
+    fn eval_define(&mut self, id: &Ident, f: &UserFunction) {
+        let program = f.body.lower();
+        self.funcs.insert(id.clone(), Rc::new((f.clone(), program)));
+        self.stack.push(Value::Defined(id.clone()));
+    }
+
+    // errors in the function body are reported at the call,
+    // the body comes from a different line of input
+    fn eval_call(&mut self, id: &Ident, arg_count: usize, span: &Span) -> Result<(), Error> {
+        let def = match self.funcs.get(id) {
+            Some(def) => Rc::clone(def),
+            None => return Err(Error::new(&format!("Unknown function {}", id.0), span.clone()))
+        };
+        let (f, program) = &*def;
+        if f.params.len() != arg_count {
+            let msg = format!("Function {} expects {}, got {arg_count}", id.0, Arity::Exact(f.params.len()));
+            return Err(Error::new(&msg, span.clone()));
+        }
+        if self.frames.len() >= MAX_CALL_DEPTH {
+            return Err(Error::new("Maximum recursion depth exceeded", span.clone()));
+        }
+        let args = self.pop_args(arg_count, span)?;
+        self.frames.push(f.params.iter().cloned().zip(args).collect());
+        let res = self.run(program);
+        self.frames.pop();
+        res.map_err(|e| Error::new(&e.message, span.clone()))
+    }
+
+    // Derivative is evaluated when its variables have values, e.g. inside
+    // a user function, otherwise the result is the symbolic expression.
+    fn eval_diff(&mut self, node: &Node, var: &Ident, order: u32, span: &Span) -> Result<(), Error> {
+        // diff(f, x) is the derivative of user function f of one argument
+        let node = match &node.kind {
+            NodeKind::Var(id) if self.lookup(id).is_none() && self.funcs.contains_key(id) => {
+                let arg = Node::new(NodeKind::Var(var.clone()), node.span.clone());
+                Node::new(NodeKind::Call(id.clone(), node.span.clone(), vec![arg]), node.span.clone())
+            },
+            _ => node.clone()
+        };
+        let node = self.inline(&node, &mut vec![]).map_err(|msg| Error::new(&msg, span.clone()))?;
+        let res = symbolic::diff(&node, var, order, &self.config).map_err(|msg| Error::new(&msg, span.clone()))?;
+        let res = symbolic::simplify(self.bind_numbers(res), &self.config);
+        if symbolic::free_vars(&res).iter().all(|id| self.lookup(id).is_some()) {
+            self.run(&res.lower()).map_err(|e| Error::new(&e.message, span.clone()))
+        } else {
+            self.stack.push(Value::Symbolic(res));
+            Ok(())
+        }
+    }
+
+    fn eval_integrate(&mut self, body: &[Expression], var: &Ident, span: &Span) -> Result<(), Error> {
+        let b = self.pop_real(span)?;
+        let a = self.pop_real(span)?;
+        let res = numeric::integrate(|x| self.eval_at(body, var, x, span), a, b);
+        self.push_real(res, span)
+    }
+
+    fn eval_solve(&mut self, body: &[Expression], var: &Ident, span: &Span) -> Result<(), Error> {
+        let guess = self.pop_real(span)?;
+        let res = numeric::solve(|x| self.eval_at(body, var, x, span), guess);
+        self.push_real(res, span)
+    }
+
+    // only the chosen branch is evaluated
+    fn eval_if(&mut self, then: &[Expression], otherwise: &[Expression], span: &Span) -> Result<(), Error> {
+        if is_true(&self.pop_numeric(span)?) {
+            self.run(then)
+        } else {
+            self.run(otherwise)
+        }
+    }
+
+    fn eval_logic(&mut self, op: Op, rhs: &[Expression], span: &Span) -> Result<(), Error> {
+        let lhs = is_true(&self.pop_numeric(span)?);
+        let res = match (op, lhs) {
+            (Op::And, false) => false,
+            (Op::Or, true) => true,
+            _ => {
+                self.run(rhs)?;
+                is_true(&self.pop_numeric(span)?)
+            }
+        };
+        self.stack.push(Value::Number(res.into()));
+        Ok(())
+    }
+
+    fn pop_real(&mut self, span: &Span) -> Result<f64, Error> {
+        let value = self.pop_numeric(span)?;
+        to_real(value, span)
+    }
+
+    // errors of the numeric method have no span and are reported at the name
+    fn push_real(&mut self, res: Result<f64, Error>, span: &Span) -> Result<(), Error> {
+        match res {
+            Ok(x) => {
+                self.stack.push(Value::Number(Number::from(x)));
+                Ok(())
+            },
+            Err(e) if e.span.len == 0 => Err(Error::new(&e.message, span.clone())),
+            Err(e) => Err(e)
+        }
+    }
+
+    fn eval_list(&mut self, count: usize, span: &Span) -> Result<(), Error> {
+        let items = self.pop_args(count, span)?;
+        if let Some(Value::Symbolic(node)) = items.iter().find(|item| matches!(item, Value::Symbolic(_))) {
+            let msg = format!("Lists cannot hold symbolic expression {node}");
+            return Err(Error::new(&msg, span.clone()));
+        }
+        // a literal of rows is a matrix, all of its rows must have the same length
+        let lens: Vec<_> = items.iter().map_while(|item| match item {
+            Value::List(row) => Some(row.len()),
+            _ => None
+        }).collect();
+        if lens.len() == items.len() {
+            if let Some(len) = lens.iter().find(|len| **len != lens[0]) {
+                let msg = format!("Rows have different lengths: {} and {len}", lens[0]);
+                return Err(Error::new(&msg, span.clone()));
+            }
+        }
+        self.stack.push(list(items));
+        Ok(())
+    }
+
+    fn eval_index(&mut self, span: &Span) -> Result<(), Error> {
+        let i = self.pop_numeric(span)?;
+        let list = self.pop_numeric(span)?;
+        let res = index(list, i, span)?;
+        self.stack.push(res);
+        Ok(())
     }
-}
 
+    fn eval_map(&mut self, body: &[Expression], var: &Ident, span: &Span) -> Result<(), Error> {
+        let items = expect_list(self.pop_numeric(span)?).map_err(|msg| Error::new(&msg, span.clone()))?;
+        let mut res = Vec::with_capacity(items.len());
+        for item in items {
+            res.push(self.eval_with(body, var, item, span)?);
+        }
+        self.stack.push(list(res));
+        Ok(())
+    }
+
+    fn eval_filter(&mut self, body: &[Expression], var: &Ident, span: &Span) -> Result<(), Error> {
+        let items = expect_list(self.pop_numeric(span)?).map_err(|msg| Error::new(&msg, span.clone()))?;
+        let mut res = vec![];
+        for item in items {
+            if is_true(&self.eval_with(body, var, item.clone(), span)?) {
+                res.push(item);
+            }
+        }
+        self.stack.push(list(res));
+        Ok(())
+    }
+
+    // evaluates the program with the variable bound to x
+    fn eval_at(&mut self, program: &[Expression], var: &Ident, x: f64, span: &Span) -> Result<f64, Error> {
+        let value = self.eval_with(program, var, Value::Number(Number::from(x)), span)?;
+        to_real(value, span)
+    }
+
+    // evaluates the program with the variable bound to the value, next to
+    // arguments of the function being called
+    fn eval_with(&mut self, program: &[Expression], var: &Ident, value: Value, span: &Span) -> Result<Value, Error> {
+        if self.frames.len() >= MAX_CALL_DEPTH {
+            return Err(Error::new("Maximum recursion depth exceeded", span.clone()));
+        }
+        let mut frame = self.frames.last().cloned().unwrap_or_default();
+        frame.insert(var.clone(), value);
+        self.frames.push(frame);
+        let res = self.run(program);
+        self.frames.pop();
+        res?;
+        self.pop_numeric(span)
+    }
+
+    fn lookup(&self, id: &Ident) -> Option<&Value> {
+        let arg = self.frames.last().and_then(|frame| frame.get(id));
+        arg.or_else(|| self.vars.get(id))
+    }
+
+    // replaces calls of user functions with their bodies, `inlined` holds
+    // functions being expanded so that recursion is reported
+    fn inline(&self, node: &Node, inlined: &mut Vec<Ident>) -> Result<Node, String> {
+        let kind = match &node.kind {
+            NodeKind::Call(id, _, args) => {
+                let (f, _) = match self.funcs.get(id) {
+                    Some(def) => &**def,
+                    None => return Err(format!("Unknown function {id}"))
+                };
+                if f.params.len() != args.len() {
+                    return Err(format!("Function {id} expects {}, got {}", Arity::Exact(f.params.len()), args.len()));
+                }
+                if inlined.contains(id) {
+                    return Err(format!("Cannot differentiate recursive function {id}"));
+                }
+                let mut vars = vec![];
+                for (param, arg) in f.params.iter().zip(args) {
+                    vars.push((param.clone(), self.inline(arg, inlined)?));
+                }
+                inlined.push(id.clone());
+                let body = self.inline(&f.body, inlined)?;
+                inlined.pop();
+                return Ok(symbolic::substitute(&body, &vars));
+            },
+            NodeKind::Unary(op, span, arg) => NodeKind::Unary(*op, span.clone(), Box::new(self.inline(arg, inlined)?)),
+            NodeKind::Binary(op, span, lhs, rhs) => {
+                NodeKind::Binary(*op, span.clone(), Box::new(self.inline(lhs, inlined)?), Box::new(self.inline(rhs, inlined)?))
+            },
+            NodeKind::Func(f, span, args) => {
+                let args = args.iter().map(|a| self.inline(a, inlined)).collect::<Result<_, _>>()?;
+                NodeKind::Func(f.clone(), span.clone(), args)
+            },
+            NodeKind::Diff(inner, var, order, span) => {
+                NodeKind::Diff(Box::new(self.inline(inner, inlined)?), var.clone(), *order, span.clone())
+            },
+            NodeKind::Integrate(body, var, a, b, span) => {
+                let (a, b) = (self.inline(a, inlined)?, self.inline(b, inlined)?);
+                NodeKind::Integrate(Box::new(self.inline(body, inlined)?), var.clone(), Box::new(a), Box::new(b), span.clone())
+            },
+            NodeKind::Solve(body, var, guess, span) => {
+                let guess = Box::new(self.inline(guess, inlined)?);
+                NodeKind::Solve(Box::new(self.inline(body, inlined)?), var.clone(), guess, span.clone())
+            },
+            NodeKind::If(cond, then, otherwise, span) => {
+                let cond = Box::new(self.inline(cond, inlined)?);
+                let (then, otherwise) = (Box::new(self.inline(then, inlined)?), Box::new(self.inline(otherwise, inlined)?));
+                NodeKind::If(cond, then, otherwise, span.clone())
+            },
+            NodeKind::List(items) => NodeKind::List(items.iter().map(|i| self.inline(i, inlined)).collect::<Result<_, _>>()?),
+            NodeKind::Index(list, i, span) => {
+                NodeKind::Index(Box::new(self.inline(list, inlined)?), Box::new(self.inline(i, inlined)?), span.clone())
+            },
+            NodeKind::Map(body, var, list, span) => {
+                let list = Box::new(self.inline(list, inlined)?);
+                NodeKind::Map(Box::new(self.inline(body, inlined)?), var.clone(), list, span.clone())
+            },
+            NodeKind::Filter(body, var, list, span) => {
+                let list = Box::new(self.inline(list, inlined)?);
+                NodeKind::Filter(Box::new(self.inline(body, inlined)?), var.clone(), list, span.clone())
+            },
+            kind => kind.clone()
+        };
+        Ok(Node::new(kind, node.span.clone()))
+    }
+
+    // replaces variables that hold plain numbers with their values
+    fn bind_numbers(&self, node: Node) -> Node {
+        let vars: Vec<_> = symbolic::free_vars(&node).into_iter()
+            .filter_map(|id| match self.lookup(&id) {
+                Some(Value::Number(n)) => {
+                    let value = Node::new(NodeKind::Num(n.clone()), node.span.clone());
+                    Some((id, value))
+                },
+                _ => None
+            })
+            .collect();
+        symbolic::substitute(&node, &vars)
+    }
+}
@@ -1,9 +1,13 @@
 use super::*;
 use crate::function;
+use crate::unit::currency::Rates;
 
 fn eval(prog: & [Expression]) -> Number {
     let mut c = Calculator::new();
-    c.eval(prog)
+    match c.eval(prog).unwrap() {
+        Value::Number(n) => n,
+        v => panic!("Expected number, got {v}")
+    }
 }
 
 #[test]
@@ -17,7 +21,7 @@ fn test_binary_op_int() {
     let program = [
         Expression::Val(2.into()),
         Expression::Val(3.into()),
-        Expression::BinaryOp(Op::Add)
+        Expression::BinaryOp(Op::Add, Span::new(0, 0))
     ];
     assert_eq!(eval(&program), 5.into());
 }
@@ -27,27 +31,35 @@ fn test_div_ints() {
     let program = [
         Expression::Val(4.into()),
         Expression::Val(2.into()),
-        Expression::BinaryOp(Op::Div)
+        Expression::BinaryOp(Op::Div, Span::new(0, 0))
     ];
     assert_eq!(eval(&program), 2.into());
 
     let program = [
         Expression::Val(2.into()),
         Expression::Val(3.into()),
-        Expression::BinaryOp(Op::Div)
+        Expression::BinaryOp(Op::Div, Span::new(0, 0))
     ];
-    if let Number::Float(f) = eval(&program) {
-        assert!((f - 2.0/3.0).abs() < 0.01)
-    } else {
-        assert!(false)
-    }
+    assert_eq!(eval(&program).to_string(), "2/3");
+}
+
+#[test]
+fn test_rational_stays_exact() {
+    let program = [
+        Expression::Val(1.into()),
+        Expression::Val(3.into()),
+        Expression::BinaryOp(Op::Div, Span::new(0, 0)),
+        Expression::Val(3.into()),
+        Expression::BinaryOp(Op::Mul, Span::new(0, 0))
+    ];
+    assert_eq!(eval(&program), Number::Int(1));
 }
 
 #[test]
 fn test_unary_minus() {
     let program = [
         Expression::Val(2.into()),
-        Expression::UnaryOp(Op::Sub)
+        Expression::UnaryOp(Op::Sub, Span::new(0, 0))
     ];
     assert_eq!(Number::Int(-2), eval(&program));
 }
@@ -57,8 +69,8 @@ fn test_function_eval() {
     let program = vec![
         Expression::Val(1.into()),
         Expression::Val(1.into()),
-        Expression::BinaryOp(Op::Sub),
-        Expression::FuncCall(function::parse_function("sin").unwrap())
+        Expression::BinaryOp(Op::Sub, Span::new(0, 0)),
+        Expression::FuncCall(function::parse_function("sin").unwrap(), 1, Span::new(0, 0))
     ];
     match eval(&program) {
         Number::Float(f) => assert!(f.abs() < 1e-10),
@@ -70,15 +82,590 @@ fn test_function_eval() {
 fn test_assign() {
     let p1 = vec![
         Expression::Val(1.into()),
-        Expression::Assign(Ident::new("x"))
+        Expression::Assign(Ident::new("x"), Span::new(0, 0))
     ];
     let p2 = vec![
         Expression::Val(2.into()),
-        Expression::Ref(Ident::new("x")),
-        Expression::BinaryOp(Op::Add)
+        Expression::Ref(Ident::new("x"), Span::new(0, 0)),
+        Expression::BinaryOp(Op::Add, Span::new(0, 0))
     ];
     let mut c = Calculator::new();
-    c.eval(&p1);
-    let res = c.eval(&p2);
-    assert_eq!(Number::Int(3), res);
-}
\ No newline at end of file
+    c.eval(&p1).unwrap();
+    let res = c.eval(&p2).unwrap();
+    assert_eq!(Value::Number(Number::Int(3)), res);
+}
+fn calc(input: &str) -> Result<Value, crate::Error> {
+    calc_with(input, Config::default())
+}
+
+// evaluates the input in a session with the given settings
+fn calc_with(input: &str, config: Config) -> Result<Value, crate::Error> {
+    let mut p = crate::Parser::with_config(input, &config);
+    p.parse()?;
+    let mut c = Calculator::new();
+    *c.config_mut() = config;
+    c.eval(&p.program)
+}
+
+#[test]
+fn test_units() {
+    assert_eq!(calc("5 km + 300 m").unwrap().to_string(), "5.3 km");
+    assert_eq!(calc("3 m * 2 s^-1").unwrap().to_string(), "6 m/s");
+    assert_eq!(calc("60 mph to km/h").unwrap().to_string(), "96.56064 km/h");
+    assert_eq!(calc("(2 m)^2").unwrap().to_string(), "4 m^2");
+    assert_eq!(calc("-(1 + 2) h to min").unwrap().to_string(), "-180 min");
+    assert_eq!(calc("1 km / 1 m").unwrap(), Value::Number(Number::Int(1000)));
+}
+
+#[test]
+fn test_precision() {
+    let config = Config { precision: Some(30), ..Default::default() };
+    assert_eq!(calc_with("sqrt(2.0)", config.clone()).unwrap().to_string(), "1.41421356237309504880168872421");
+    assert_eq!(calc_with("pi", config.clone()).unwrap().to_string(), "3.14159265358979323846264338328");
+    assert_eq!(calc_with("1/3.0 + 1", config).unwrap().to_string(), "1.33333333333333333333333333333");
+    assert_eq!(calc("1/3.0 + 1").unwrap().to_string(), "1.3333333333333333");
+}
+
+#[test]
+fn test_variable_named_like_unit() {
+    assert_eq!(calc_lines(&["g = 9.81", "2g"]).unwrap().to_string(), "19.62");
+    assert_eq!(calc_lines(&["m = 3", "2 m + m"]).unwrap().to_string(), "9");
+    assert_eq!(calc_lines(&["f(m) = 2m", "f(4)"]).unwrap().to_string(), "8");
+    assert_eq!(calc_lines(&["g = 2", "5 kg to g"]).unwrap().to_string(), "5000 g");
+    assert_eq!(calc_lines(&["g = 2", "2 g^2"]).unwrap().to_string(), "8");
+    assert_eq!(calc_lines(&["s = 4", "8 m/s"]).unwrap().to_string(), "2 m");
+    assert_eq!(calc_lines(&["m = 3", "2 kg*m/s^2"]).unwrap().to_string(), "6 kg/s^2");
+    assert_eq!(calc_lines(&["m = 3", "s = 2", "12 m/s"]).unwrap().to_string(), "18");
+    assert_eq!(calc("2g").unwrap().to_string(), "2 g");
+}
+
+#[test]
+fn test_incompatible_units() {
+    let err = calc("5 km + 3 s").unwrap_err();
+    assert_eq!(err.message, "Incompatible units: km and s");
+    assert_eq!(err.span, Span::new(5, 1));
+
+    let err = calc("1 + 2 * 3 m").unwrap_err();
+    assert_eq!(err.message, "Incompatible units: number and m");
+    assert_eq!(err.span, Span::new(2, 1));
+
+    let err = calc("5 km to s").unwrap_err();
+    assert_eq!(err.message, "Cannot convert km to s");
+    assert_eq!(err.span, Span::new(5, 2));
+}
+
+fn calc_lines(lines: &[&str]) -> Result<Value, crate::Error> {
+    let mut c = Calculator::new();
+    let mut res = Value::default();
+    for line in lines {
+        let mut p = crate::Parser::new(line);
+        p.parse()?;
+        res = c.eval(&p.program)?;
+    }
+    Ok(res)
+}
+
+#[test]
+fn test_user_function() {
+    let res = calc_lines(&["f(x) = x^2 + 1", "f(3)"]).unwrap();
+    assert_eq!(res, Value::Number(Number::Int(10)));
+
+    let res = calc_lines(&["x = 10", "add(x, y) = x + y", "add(1, 2) + x"]).unwrap();
+    assert_eq!(res, Value::Number(Number::Int(13)));
+
+    let res = calc_lines(&["k = 2", "g(x) = k * x", "h(x) = g(x) + g(1)", "h(4)"]).unwrap();
+    assert_eq!(res, Value::Number(Number::Int(10)));
+
+    assert_eq!(calc("f(x) = x^2").unwrap(), Value::Defined(Ident::new("f")));
+}
+
+#[test]
+fn test_user_function_errors() {
+    let err = calc_lines(&["f(x) = x", "1 + f(1, 2)"]).unwrap_err();
+    assert_eq!(err.message, "Function f expects 1 argument, got 2");
+    assert_eq!(err.span, Span::new(4, 1));
+
+    let err = calc_lines(&["g(2)"]).unwrap_err();
+    assert_eq!(err.message, "Unknown function g");
+
+    let err = calc_lines(&["f(x) = f(x + 1)", "f(1)"]).unwrap_err();
+    assert_eq!(err.message, "Maximum recursion depth exceeded");
+
+    // frames of failed calls are dropped
+    let mut c = Calculator::new();
+    for line in ["f(x) = 1 / x", "f(0)"] {
+        let mut p = crate::Parser::new(line);
+        p.parse().unwrap();
+        let _ = c.eval(&p.program);
+    }
+    assert!(c.frames.is_empty());
+}
+
+#[test]
+fn test_eval_errors() {
+    let check = |input: &str, message: &str, span: Span| {
+        let err = calc(input).unwrap_err();
+        assert_eq!((err.message.as_str(), err.span), (message, span), "{input}");
+    };
+    check("1 + 2 / 0", "Division by zero", Span::new(6, 1));
+    check("5 % (1 - 1)", "Division by zero", Span::new(2, 1));
+    check("0 ^ -1", "Division by zero", Span::new(2, 1));
+    check("1.5e300 * 1e300", "Overflow", Span::new(8, 1));
+    check("2 ^ 2 ^ 30", "Overflow", Span::new(2, 1));
+    check("1 + y", "Unknown variable y", Span::new(4, 1));
+    check("2 * ln(0)", "Invalid argument for ln", Span::new(4, 2));
+    check("5 km / 0 h", "Division by zero", Span::new(5, 1));
+
+    let program = [Expression::BinaryOp(Op::Add, Span::new(3, 1))];
+    let err = Calculator::new().eval(&program).unwrap_err();
+    assert_eq!((err.message.as_str(), err.span), ("Stack underflow", Span::new(3, 1)));
+
+    let err = calc_lines(&["f(x) = 1 / x", "2 + f(0)"]).unwrap_err();
+    assert_eq!((err.message.as_str(), err.span), ("Division by zero", Span::new(4, 1)));
+}
+
+#[test]
+fn test_diff() {
+    assert_eq!(calc("diff(x^2*sin(x), x)").unwrap().to_string(), "2*x*sin(x) + x^2*cos(x)");
+    assert_eq!(calc("diff(x^3, x, 2)").unwrap().to_string(), "6*x");
+    assert_eq!(calc_lines(&["x = 3", "diff(x^3, x, 2)"]).unwrap(), Value::Number(Number::Int(18)));
+    assert_eq!(calc_lines(&["a = 2", "diff(a*x^2, x)"]).unwrap().to_string(), "4*x");
+    assert_eq!(calc_lines(&["g(x) = diff(x^3, x)", "g(2)"]).unwrap(), Value::Number(Number::Int(12)));
+    assert_eq!(calc_lines(&["f(x) = x^4", "diff(f, x, 2)"]).unwrap().to_string(), "12*x^2");
+    assert_eq!(calc_lines(&["f(t) = t^2", "diff(f(2*x), x)"]).unwrap().to_string(), "8*x");
+    assert_eq!(calc_lines(&["f(t) = t^2", "d(t) = diff(f, t)", "d(5)"]).unwrap(), Value::Number(Number::Int(10)));
+
+    let err = calc("diff(x^2, x) * 2").unwrap_err();
+    assert_eq!(err.message, "Cannot compute with symbolic expression 2*x");
+    let err = calc("1 + diff(f(x), x)").unwrap_err();
+    assert_eq!(err.span, Span::new(4, 4));
+    let err = calc_lines(&["f(x) = f(x) + 1", "diff(f(x), x)"]).unwrap_err();
+    assert_eq!(err.message, "Cannot differentiate recursive function f");
+}
+
+#[test]
+fn test_integrate_solve() {
+    let number = |input: &[&str]| f64::from(calc_lines(input).unwrap().number().clone());
+    assert!((number(&["integrate(x^2, x, 0, 3)"]) - 9.0).abs() < 1e-9);
+    assert!((number(&["integrate(sin(t), t, 0, pi)"]) - 2.0).abs() < 1e-9);
+    assert!((number(&["a = 2", "integrate(a*x, x, 0, a)"]) - 4.0).abs() < 1e-9);
+    assert!((number(&["f(a) = integrate(a*x, x, 0, 1)", "f(6)"]) - 3.0).abs() < 1e-9);
+    assert!((number(&["x = 5", "integrate(x, x, 0, 1) + x"]) - 5.5).abs() < 1e-9);
+    assert!((number(&["solve(x^2 - 2, x, 1)"]) - 2f64.sqrt()).abs() < 1e-9);
+    assert!((number(&["f(x) = x^3 - x - 1", "solve(f(x), x, 1)"]) - 1.324717957244746).abs() < 1e-9);
+    assert!((number(&["solve(diff(x^3 - 3*x, x), x, 2)"]) - 1.0).abs() < 1e-9);
+    assert!((number(&["solve(integrate(t, t, 0, x) - 2, x, 1)"]) - 2.0).abs() < 1e-6);
+    assert!((number(&["solve(ln(x), x, 5)"]) - 1.0).abs() < 1e-9);
+    assert!((number(&["solve(sqrt(x) - 1, x, 9)"]) - 1.0).abs() < 1e-9);
+
+    let err = calc("integrate(1/x, x, 0, 1)").unwrap_err();
+    assert_eq!(err.span, Span::new(11, 1));
+    let err = calc("solve(x^2 + 1, x, 1)").unwrap_err();
+    assert_eq!((err.message.as_str(), err.span), ("No solution found, try another guess", Span::new(0, 5)));
+    let err = calc("integrate(y, x, 0, 1)").unwrap_err();
+    assert_eq!((err.message.as_str(), err.span), ("Unknown variable y", Span::new(10, 1)));
+    let err = calc("integrate(x, x, 0, 1 km)").unwrap_err();
+    assert_eq!(err.span, Span::new(0, 9));
+}
+
+#[test]
+fn test_comparison() {
+    let num = |input: &str| calc(input).unwrap();
+    assert_eq!(num("1 < 2"), Value::Number(Number::Int(1)));
+    assert_eq!(num("2 <= 1"), Value::Number(Number::Int(0)));
+    assert_eq!(num("1/2 == 0.5"), Value::Number(Number::Int(1)));
+    assert_eq!(num("1 + 2 != 3"), Value::Number(Number::Int(0)));
+    assert_eq!(num("2i == 2i"), Value::Number(Number::Int(1)));
+    assert_eq!(num("5 km > 300 m"), Value::Number(Number::Int(1)));
+    assert_eq!(num("1 h >= 60 min"), Value::Number(Number::Int(1)));
+    assert_eq!(num("1 < 2 && 3 < 2 || !0"), Value::Number(Number::Int(1)));
+    assert_eq!(num("!(2 > 1)"), Value::Number(Number::Int(0)));
+
+    let err = calc("1i < 2").unwrap_err();
+    assert_eq!((err.message.as_str(), err.span), ("Cannot compare complex numbers", Span::new(3, 1)));
+    let err = calc("1 km < 2 s").unwrap_err();
+    assert_eq!(err.message, "Cannot compare km and s");
+}
+
+#[test]
+fn test_if() {
+    let prices = ["price(x) = if(x > 10, x*0.9, x)", "price(20) + price(5)"];
+    assert_eq!(calc_lines(&prices).unwrap().to_string(), "23");
+    assert_eq!(calc("if(1 < 2, 1 km, 2 km)").unwrap().to_string(), "1 km");
+    // only the chosen branch and the needed side of && and || are evaluated
+    assert_eq!(calc("if(0, 1/0, 2)").unwrap(), Value::Number(Number::Int(2)));
+    assert_eq!(calc("0 && 1/0").unwrap(), Value::Number(Number::Int(0)));
+    assert_eq!(calc("2 || y").unwrap(), Value::Number(Number::Int(1)));
+    let fact = ["fact(n) = if(n <= 1, 1, n * fact(n - 1))", "fact(20)"];
+    assert_eq!(calc_lines(&fact).unwrap(), Value::Number(Number::Int(2432902008176640000)));
+
+    assert_eq!(calc("1 && 1/0").unwrap_err().message, "Division by zero");
+    assert_eq!(calc("diff(if(x > 0, x^2, -x), x)").unwrap().to_string(), "if(x > 0, 2*x, -1)");
+}
+
+#[test]
+fn test_bitwise() {
+    let int = |input: &str| match calc(input).unwrap() {
+        Value::Number(n) => n,
+        v => panic!("Expected number, got {v}")
+    };
+    assert_eq!(int("0xf0 & 0b1010_0101"), Number::Int(0xa0));
+    assert_eq!(int("0xf0 | 0x0f"), Number::Int(0xff));
+    assert_eq!(int("6 xor 3"), Number::Int(5));
+    assert_eq!(int("~0"), Number::Int(-1));
+    assert_eq!(int("1 << 4 + 1"), Number::Int(32));
+    assert_eq!(int("-17 >> 2"), Number::Int(-5));
+    assert_eq!(int("-1 >>> 60"), Number::Int(15));
+    assert_eq!(int("1 | 2 xor 3 & 4"), Number::Int(3));
+    assert_eq!(int("1 << 130 >> 129"), Number::Int(2));
+    assert_eq!(int("~(2^100) + 2^100"), Number::Int(-1));
+    assert_eq!(int("(1 << 127) - 1"), Number::Int(i128::MAX));
+
+    let err = calc("2.5 & 1").unwrap_err();
+    assert_eq!((err.message.as_str(), err.span), ("Bitwise operators need integers, got 2.5", Span::new(4, 1)));
+    let err = calc("~(1/2)").unwrap_err();
+    assert_eq!(err.message, "Bitwise operators need integers, got 1/2");
+    assert_eq!(calc("1 << -1").unwrap_err().message, "Invalid shift amount");
+    assert_eq!(calc("1 km | 1").unwrap_err().message, "Bitwise operators do not accept units");
+}
+
+#[test]
+fn test_cast() {
+    assert_eq!(calc("-1 as u32").unwrap().to_string(), "4294967295");
+    assert_eq!(calc("300 as u8").unwrap().to_string(), "44");
+    assert_eq!(calc("(200 as i8) + 1").unwrap().to_string(), "-55");
+    assert_eq!(calc("-1 as u8").unwrap().to_string_in(Radix::Hex, &Config::default()), "0xff");
+    let err = calc("2.5 as u8").unwrap_err();
+    assert_eq!((err.message.as_str(), err.span), ("Cannot convert 2.5 to u8", Span::new(4, 2)));
+    assert_eq!(calc("1 km as u8").unwrap_err().message, "Cannot convert km to u8");
+    assert!(calc("diff(x as u8, x)").is_err());
+
+    let u8 = || Config { word: Word::parse("u8"), ..Default::default() };
+    assert_eq!(calc_with("255 + 1", u8()).unwrap().to_string(), "0");
+    assert_eq!(calc_with("-1 >>> 4", u8()).unwrap().to_string(), "15");
+    assert_eq!(calc("-1 >>> 60").unwrap().to_string(), "15");
+    let grouped = Config { word: Word::parse("u32"), grouping: true, ..Default::default() };
+    assert_eq!(calc_with("-1", grouped.clone()).unwrap().to_string_in(Radix::Hex, &grouped), "0xffff_ffff");
+}
+
+#[test]
+fn test_factorial() {
+    assert_eq!(calc("5!").unwrap().to_string(), "120");
+    assert_eq!(calc("-3! + 2^3!").unwrap().to_string(), "58");
+    assert_eq!(calc("(2 + 1)!!").unwrap().to_string(), "720");
+    assert_eq!(calc("nCr(6, 2) / nPr(6, 2)").unwrap().to_string(), "1/2");
+    assert_eq!(calc("30!").unwrap().to_string(), "265252859812191058636308480000000");
+    assert_eq!(calc("diff(3! * x, x)").unwrap().to_string(), "6");
+    assert_eq!(calc("3 != 3").unwrap().to_string(), "0");
+
+    let err = calc("(-2)!").unwrap_err();
+    assert_eq!((err.message.as_str(), err.span), ("Factorial is not defined for -2", Span::new(4, 1)));
+    assert_eq!(calc("100000!").unwrap_err().message, "Overflow");
+    assert_eq!(calc("(2 m)!").unwrap_err().message, "Factorial does not accept units");
+    assert_eq!(calc("diff(x!, x)").unwrap_err().message, "Cannot differentiate factorial");
+}
+
+#[test]
+fn test_factor() {
+    assert_eq!(calc("factor(360)").unwrap().to_string(), "2^3*3^2*5");
+    assert_eq!(calc("factor(-7)").unwrap().to_string(), "-1*7");
+    assert_eq!(calc("factor(1)").unwrap().to_string(), "1");
+    assert_eq!(calc("factor(2^64 + 1)").unwrap().to_string(), "274177*67280421310721");
+    assert_eq!(calc("factor(360) + 1").unwrap().to_string(), "361");
+    assert_eq!(calc("sqrt(factor(-7) * -7)").unwrap().to_string(), "7");
+    assert_eq!(calc_lines(&["f = factor(12)", "f / 4"]).unwrap().to_string(), "3");
+    let err = calc("factor(1/2)").unwrap_err();
+    assert_eq!((err.message.as_str(), err.span), ("Cannot factor 1/2", Span::new(0, 6)));
+}
+
+#[test]
+fn test_statistics() {
+    assert_eq!(calc("variance(1, 2, 3, 4)").unwrap().to_string(), "5/3");
+    assert_eq!(calc("percentile(1, 2, 3, 4, 50) == median(1, 2, 3, 4)").unwrap().to_string(), "1");
+    assert_eq!(calc("percentile([1, 2, 3, 4], 50)").unwrap().to_string(), "5/2");
+    assert_eq!(calc("percentile([15, 20, 35, 40, 50], 90)").unwrap().to_string(), "46");
+    assert_eq!(calc("binomcdf(3, 1/2, 3)").unwrap().to_string(), "1");
+    let err = calc("normcdf(1, 2, 3, 4)").unwrap_err();
+    assert_eq!((err.message.as_str(), err.span), ("Function normcdf expects 1 to 3 arguments, got 4", Span::new(0, 7)));
+    assert_eq!(calc("norminv(2)").unwrap_err().message, "Invalid argument for norminv");
+}
+
+#[test]
+fn test_list() {
+    assert_eq!(calc("[1, 2, 3] * 2").unwrap().to_string(), "[2, 4, 6]");
+    assert_eq!(calc("1 / [2, 4] + [1, 1]").unwrap().to_string(), "[3/2, 5/4]");
+    assert_eq!(calc("[[1, 2], 3] * 2").unwrap().to_string(), "[[2, 4], 6]");
+    assert_eq!(calc("[1, 2] km to m").unwrap().to_string(), "[1000 m, 2000 m]");
+    assert_eq!(calc("-[1, 2] == [-1, 2]").unwrap().to_string(), "[1, 0]");
+    assert_eq!(calc("if([1, 2] > 0, 1, 0)").unwrap().to_string(), "1");
+    assert_eq!(calc("[]").unwrap().to_string(), "[]");
+    assert_eq!(calc("[10, 255] as u8").unwrap().to_string_in(Radix::Hex, &Config::default()), "[0xa, 0xff]");
+
+    let err = calc("[1, 2] + [1, 2, 3]").unwrap_err();
+    assert_eq!((err.message.as_str(), err.span), ("Lists have different lengths: 2 and 3", Span::new(7, 1)));
+    assert_eq!(calc("[1, 0] / [1, 0]").unwrap_err().message, "Division by zero");
+    assert_eq!(calc("[1 m, 2] + 1 m").unwrap_err().message, "Incompatible units: number and m");
+    assert_eq!(calc("[diff(x^2, x)]").unwrap_err().message, "Lists cannot hold symbolic expression 2*x");
+}
+
+#[test]
+fn test_index() {
+    assert_eq!(calc("[4, 5, 6][0]").unwrap().to_string(), "4");
+    assert_eq!(calc("[4, 5, 6][-1]").unwrap().to_string(), "6");
+    assert_eq!(calc("[[1, 2], [3, 4]][1][0]").unwrap().to_string(), "3");
+    let err = calc("[4, 5, 6][3]").unwrap_err();
+    assert_eq!((err.message.as_str(), err.span), ("Index 3 is out of range for list of length 3", Span::new(9, 3)));
+    assert_eq!(calc("[1][1/2]").unwrap_err().message, "Index must be an integer, got 1/2");
+    assert_eq!(calc("5[0]").unwrap_err().message, "Cannot index 5, it is not a list");
+}
+
+#[test]
+fn test_matrix() {
+    assert_eq!(calc("[[1, 2], [3, 4]] * [[5], [6]]").unwrap().to_string(), "[[17], [39]]");
+    assert_eq!(calc("[[1, 2], [3, 4]] * [10, 100]").unwrap().to_string(), "[210, 430]");
+    assert_eq!(calc("[1, 1] * [[1, 2], [3, 4]]").unwrap().to_string(), "[4, 6]");
+    assert_eq!(calc("[[1, 2], [3, 4]] + 1").unwrap().to_string(), "[[2, 3], [4, 5]]");
+    assert_eq!(calc("[[1, 2], [3, 4]] ^ -1").unwrap().to_string(), "[[-2, 1], [3/2, -1/2]]");
+    assert_eq!(calc("[[1, 2], [3, 4]][1][1]").unwrap().to_string(), "4");
+    assert_eq!(calc("det([[1, 2], [3, 4]])").unwrap().to_string(), "-2");
+    assert_eq!(calc("inv([[2, 1], [1, 3]])").unwrap().to_string(), "[[3/5, -1/5], [-1/5, 2/5]]");
+    assert_eq!(calc("transpose([1, 2])").unwrap().to_string(), "[[1], [2]]");
+    assert_eq!(calc("transpose([[1, 2]])").unwrap().to_string(), "[[1], [2]]");
+    assert_eq!(calc("rank([[1, 2], [2, 4]])").unwrap().to_string(), "1");
+    assert_eq!(calc("solve([[2, 1], [1, 3]], [3, 5])").unwrap().to_string(), "[4/5, 7/5]");
+    assert_eq!(calc("eig([[2, 1], [1, 2]])").unwrap().to_string(), "[1, 3]");
+    assert_eq!(calc("identity(2) * 3").unwrap().to_string(), "[[3, 0], [0, 3]]");
+    assert_eq!(calc("zeros(1, 3)").unwrap().to_string(), "[[0, 0, 0]]");
+    assert_eq!(calc("len(zeros(2, 3))").unwrap().to_string(), "2");
+
+    let err = calc("[[1, 2], [3, 4]] * [[1, 2, 3]]").unwrap_err();
+    assert_eq!((err.message.as_str(), err.span), ("Cannot multiply 2x2 matrix by 1x3 matrix", Span::new(17, 1)));
+    let err = calc("[[1, 2]] + [[1], [2]]").unwrap_err();
+    assert_eq!((err.message.as_str(), err.span), ("Matrices have different sizes: 1x2 and 2x1", Span::new(9, 1)));
+    let err = calc("1 + [[1, 2], [3]]").unwrap_err();
+    assert_eq!((err.message.as_str(), err.span), ("Rows have different lengths: 2 and 1", Span::new(4, 13)));
+    let err = calc("inv([[1, 2], [2, 4]])").unwrap_err();
+    assert_eq!((err.message.as_str(), err.span), ("Matrix is singular", Span::new(0, 3)));
+    assert_eq!(calc("det([[1, 2]])").unwrap_err().message, "Matrix must be square, got 1x2");
+    assert_eq!(calc("det([1, 2])").unwrap_err().message, "Expected matrix, got [1, 2]");
+    assert_eq!(calc("[[1, 2], [3, 4]] * [1, 2, 3]").unwrap_err().message, "Cannot multiply 2x2 matrix by list of length 3");
+    assert_eq!(calc("1 / [[1]]").unwrap_err().message, "Cannot divide by matrix, multiply by its inverse");
+    assert_eq!(calc("[[1]] ^ 0.5").unwrap_err().message, "Matrices can only be raised to integer powers");
+    assert_eq!(calc("solve([[1, 2], [3, 4]], [1])").unwrap_err().message, "Cannot solve 2x2 system with 1x1 right side");
+    assert_eq!(calc("zeros(0)").unwrap_err().message, "Expected size of matrix, got 0");
+    assert_eq!(calc("identity(2000)").unwrap_err().message, "Matrix is too large");
+}
+
+#[test]
+fn test_string() {
+    assert_eq!(calc("\"abc\"").unwrap(), Value::Str(String::from("abc")));
+    assert_eq!(calc("\"ab\" + \"cd\"").unwrap().to_string(), "\"abcd\"");
+    assert_eq!(calc("\"Total: \" + 5 km").unwrap().to_string(), "\"Total: 5 km\"");
+    assert_eq!(calc("len(\"żółw\")").unwrap().to_string(), "4");
+    assert_eq!(calc("upper(\"abc\")").unwrap().to_string(), "\"ABC\"");
+    assert_eq!(calc("lower([\"A\", \"B\"])").unwrap().to_string(), "[\"a\", \"b\"]");
+    assert_eq!(calc("\"abc\"[-1]").unwrap().to_string(), "\"c\"");
+    assert_eq!(calc("\"a\" < \"b\"").unwrap().to_string(), "1");
+    assert_eq!(calc("\"a\" == 1").unwrap().to_string(), "0");
+    assert_eq!(calc("sort([\"b\", \"c\", \"a\"])").unwrap().to_string(), "[\"a\", \"b\", \"c\"]");
+    assert_eq!(calc("if(\"\", 1, 2)").unwrap().to_string(), "2");
+    assert_eq!(calc("format(pi, \"0.00\")").unwrap().to_string(), "\"3.14\"");
+    assert_eq!(calc("format((1/3) km, \"0.0\")").unwrap().to_string(), "\"0.3 km\"");
+    assert_eq!(calc("format([1, 2], \"0.0\")").unwrap().to_string(), "[\"1.0\", \"2.0\"]");
+
+    let err = calc("\"a\" * 2").unwrap_err();
+    assert_eq!((err.message.as_str(), err.span), ("Strings can only be joined with + or compared", Span::new(4, 1)));
+    assert_eq!(calc("\"a\" < 1").unwrap_err().message, "Cannot compare strings and numbers");
+    assert_eq!(calc("sqrt(\"a\")").unwrap_err().message, "Expected number, got \"a\"");
+    assert_eq!(calc("upper(1)").unwrap_err().message, "Expected string, got 1");
+    assert_eq!(calc_lines(&["x = \"a\"", "(x) km"]).unwrap_err().message, "Cannot attach unit to \"a\"");
+    assert_eq!(calc("\"a\" as u8").unwrap_err().message, "Cannot convert \"a\" to u8");
+    assert_eq!(calc("format(2, 3)").unwrap_err().message, "Expected format pattern, got 3");
+    assert_eq!(calc("format(2i, \"0\")").unwrap_err().message, "Expected real number, got 2i");
+    assert_eq!(calc("format(2, \"x\")").unwrap_err().message, "Invalid format pattern: x");
+    assert_eq!(calc("diff(\"a\", x)").unwrap_err().message, "Cannot differentiate strings");
+}
+
+#[test]
+fn test_dates() {
+    assert_eq!(calc("2026-10-18 + 90 days").unwrap().to_string(), "2027-01-16");
+    assert_eq!(calc("(17:45 - 09:10) * 5").unwrap().to_string(), "PT42H55M");
+    assert_eq!(calc("2026-10-18 + 09:10").unwrap().to_string(), "2026-10-18T09:10");
+    assert_eq!(calc("2027-01-01 - 2026-10-18").unwrap().to_string(), "P75D");
+    assert_eq!(calc("2026-10-18T12:00 - 1 week").unwrap().to_string(), "2026-10-11T12:00");
+    assert_eq!(calc("(10:00 - 10:30) / 2").unwrap().to_string(), "-PT15M");
+    assert_eq!(calc("08:00 / 30 min").unwrap().to_string(), "16");
+    assert_eq!(calc("(01:30) to min").unwrap().to_string(), "90 min");
+    assert_eq!(calc("2026-10-18 < 2026-10-19").unwrap().to_string(), "1");
+    assert_eq!(calc("sort([2026-10-19, 2026-10-18])").unwrap().to_string(), "[2026-10-18, 2026-10-19]");
+    assert_eq!(calc("weekday(2026-10-18)").unwrap().to_string(), "7");
+    assert_eq!(calc("weekday([2026-10-19, 2026-10-24])").unwrap().to_string(), "[1, 6]");
+    assert_eq!(calc("workdays(2026-10-16, 2026-10-26)").unwrap().to_string(), "6");
+    assert_eq!(calc("workdays(2026-10-26, 2026-10-16)").unwrap().to_string(), "-6");
+    assert!(matches!(calc("now()").unwrap(), Value::Date(_)));
+
+    let err = calc("2026-10-18 + 2026-10-18").unwrap_err();
+    assert_eq!((err.message.as_str(), err.span), ("Invalid operation on date and date", Span::new(11, 1)));
+    assert_eq!(calc("2026-10-18 * 2").unwrap_err().message, "Invalid operation on date and number");
+    assert_eq!(calc("09:10 + 1 km").unwrap_err().message, "Invalid operation on duration and km");
+    assert_eq!(calc("9999-12-31 + 1 day").unwrap_err().message, "Date is out of range");
+    assert_eq!(calc("10:00 / 0").unwrap_err().message, "Division by zero");
+    assert_eq!(calc("sort([2026-10-18, 1])").unwrap_err().message, "Cannot compare dates and numbers");
+    assert_eq!(calc("weekday(1)").unwrap_err().message, "Expected date, got 1");
+    assert_eq!(calc("sqrt(09:10)").unwrap_err().message, "Expected number, got PT9H10M");
+}
+
+#[test]
+fn test_currency() {
+    assert_eq!(calc("5 EUR + 3 EUR").unwrap().to_string(), "8.00 EUR");
+    assert_eq!(calc("5 EUR + 3 USD").unwrap_err().message, "Missing exchange rate for USD");
+    let rates = Rc::new(Rates::parse_csv("EUR,1\nUSD,1.08\nPLN,4.25\nJPY,162").unwrap());
+    let calc = |input| calc_with(input, Config { rates: Rc::clone(&rates), ..Default::default() });
+    assert_eq!(calc("120 EUR + 35 USD in PLN").unwrap().to_string(), "647.73 PLN");
+    assert_eq!(calc("120 EUR + 35 USD to PLN").unwrap().to_string(), "647.73 PLN");
+    assert_eq!(calc("35 USD in EUR").unwrap().to_string(), "32.41 EUR");
+    assert_eq!(calc("100 USD in JPY").unwrap().to_string(), "15000 JPY");
+    assert_eq!(calc("(1/3) EUR").unwrap().to_string(), "0.33 EUR");
+    assert_eq!(calc("10 EUR / 1 USD").unwrap().to_string(), "54/5");
+    assert_eq!(calc("12 EUR/h * 8 h").unwrap().to_string(), "96.00 EUR");
+    assert_eq!(calc("1 EUR > 1 USD").unwrap().to_string(), "1");
+    assert_eq!(calc("0 EUR < 0.004 USD").unwrap().to_string(), "1");
+    assert_eq!(calc("0.004 USD > 0 EUR").unwrap().to_string(), "1");
+    assert_eq!(calc("1000 JPY == 6.17 EUR").unwrap().to_string(), "0");
+    assert_eq!(calc("1000 JPY == (500/81) EUR").unwrap().to_string(), "1");
+    assert_eq!(calc("255 in hex").unwrap().to_string(), "255");
+
+    let err = calc("5 EUR + 3 GBP").unwrap_err();
+    assert_eq!((err.message.as_str(), err.span), ("Missing exchange rate for GBP", Span::new(6, 1)));
+    assert_eq!(calc("5 GBP in EUR").unwrap_err().message, "Missing exchange rate for GBP");
+    assert_eq!(calc("5 EUR + 3 km").unwrap_err().message, "Incompatible units: EUR and km");
+    assert_eq!(calc("5 EUR to km").unwrap_err().message, "Cannot convert EUR to km");
+}
+
+#[test]
+fn test_percent() {
+    assert_eq!(calc("200 + 15%").unwrap().to_string(), "230");
+    assert_eq!(calc("200 - 15%").unwrap().to_string(), "170");
+    assert_eq!(calc("(200 + 15%) * 2").unwrap().to_string(), "460");
+    assert_eq!(calc("50% of 80").unwrap().to_string(), "40");
+    assert_eq!(calc("50% of 80 + 1").unwrap().to_string(), "41");
+    assert_eq!(calc("200 * 15%").unwrap().to_string(), "30");
+    assert_eq!(calc("15%").unwrap().to_string(), "3/20");
+    assert_eq!(calc("20 as % of 80").unwrap().to_string(), "25");
+    assert_eq!(calc_lines(&["x = 30", "x as % of 120"]).unwrap().to_string(), "25");
+    assert_eq!(calc("7 % 3").unwrap().to_string(), "1");
+    assert_eq!(calc("7 % (-2)").unwrap().to_string(), "1");
+    assert_eq!(calc("200 + 15% + 10").unwrap().to_string(), "240");
+    assert_eq!(calc("200 + 15% - 10").unwrap().to_string(), "220");
+    assert_eq!(calc("200 - 15% * 2").unwrap().to_string(), "1997/10");
+    assert_eq!(calc("100 EUR + 10%").unwrap().to_string(), "110.00 EUR");
+    assert_eq!(calc("[100, 200] - 10%").unwrap().to_string(), "[90, 180]");
+    assert_eq!(calc("(5 km)%").unwrap().to_string(), "0.05 km");
+
+    assert_eq!(calc("\"a\"%").unwrap_err().message, "Invalid unary operator");
+    let err = calc("20 as % of 0").unwrap_err();
+    assert_eq!((err.message.as_str(), err.span), ("Division by zero", Span::new(3, 2)));
+}
+
+#[test]
+fn test_list_functions() {
+    assert_eq!(calc("sum([4, 5, 6])").unwrap().to_string(), "15");
+    assert_eq!(calc("max([1, 7], 3, [[2]])").unwrap().to_string(), "7");
+    assert_eq!(calc("variance([2, 4, 4, 4, 5, 5, 7, 9])").unwrap().to_string(), "32/7");
+    assert_eq!(calc("abs([-1, 2])").unwrap().to_string(), "[1, 2]");
+    assert_eq!(calc("nCr([4, 5], 2)").unwrap().to_string(), "[6, 10]");
+    assert_eq!(calc("len([1, [2, 3]])").unwrap().to_string(), "2");
+    assert_eq!(calc("sort([3, -1, 2.5])").unwrap().to_string(), "[-1, 2.5, 3]");
+    assert_eq!(calc("sort([1 km, 20 m])").unwrap().to_string(), "[20 m, 1 km]");
+    assert_eq!(calc("range(4)").unwrap().to_string(), "[0, 1, 2, 3]");
+    assert_eq!(calc("range(1, 2, 1/4)").unwrap().to_string(), "[1, 5/4, 3/2, 7/4]");
+    assert_eq!(calc("range(10, 0, -3)").unwrap().to_string(), "[10, 7, 4, 1]");
+    assert_eq!(calc("range(5, 1)").unwrap().to_string(), "[]");
+    assert_eq!(calc("map(x^2, x, range(1, 4))").unwrap().to_string(), "[1, 4, 9]");
+    assert_eq!(calc("filter(isprime(n), n, range(10))").unwrap().to_string(), "[2, 3, 5, 7]");
+    assert_eq!(calc_lines(&["k = 10", "map(x + k, x, [1, 2])"]).unwrap().to_string(), "[11, 12]");
+    assert_eq!(calc_lines(&["f(n) = sum(map(x^2, x, range(n + 1)))", "f(3)"]).unwrap().to_string(), "14");
+
+    assert_eq!(calc("nCr([1, 2], [1, 2, 3])").unwrap_err().message, "Lists have different lengths: 2 and 3");
+    assert_eq!(calc("max([])").unwrap_err().message, "Invalid argument for max");
+    assert_eq!(calc("len(5)").unwrap_err().message, "Expected list, got 5");
+    assert_eq!(calc("sort([1, 1 m])").unwrap_err().message, "Cannot compare number and m");
+    assert_eq!(calc("sort([1, [2]])").unwrap_err().message, "Cannot sort nested lists");
+    let err = calc("range(0, 1, 0)").unwrap_err();
+    assert_eq!((err.message.as_str(), err.span), ("Step of range cannot be zero", Span::new(0, 5)));
+    assert_eq!(calc("range(10^7)").unwrap_err().message, "Range is too long");
+    assert_eq!(calc("map(1/x, x, [1, 0])").unwrap_err().message, "Division by zero");
+}
+
+#[test]
+fn test_registry_list_functions() {
+    let mut functions = function::FunctionRegistry::new();
+    functions.register_values("len", function::Arity::Exact(1), |_, _| Ok(Value::Number(Number::Int(0))));
+    functions.register_values("rev", function::Arity::Exact(1), |args, _| match args.pop() {
+        Some(Value::List(items)) => Ok(Value::List(items.into_iter().rev().collect())),
+        _ => Err(String::from("Expected list"))
+    });
+    let functions = Rc::new(functions);
+    let calc = |input| {
+        let mut p = crate::Parser::with_functions(input, &Config::default(), Rc::clone(&functions));
+        p.parse()?;
+        Calculator::new().eval(&p.program)
+    };
+    assert_eq!(calc("len([1, 2])").unwrap().to_string(), "0");
+    assert_eq!(calc("rev(range(3))").unwrap().to_string(), "[2, 1, 0]");
+    assert_eq!(calc("sum(rev([1, 2]))").unwrap().to_string(), "3");
+    let err = calc("rev(5)").unwrap_err();
+    assert_eq!((err.message.as_str(), err.span), ("Expected list", Span::new(0, 3)));
+}
+
+#[test]
+fn test_registry_config() {
+    let mut functions = function::FunctionRegistry::new();
+    functions.register("third", function::Arity::Exact(1), |args| args[0].clone() / Number::Int(3));
+    let config = Config { precision: Some(30), angle: Angle::Deg, ..Default::default() };
+    let mut p = crate::Parser::with_functions("third(1.0) + sin(third(90))", &config, Rc::new(functions));
+    p.parse().unwrap();
+    let mut c = Calculator::new();
+    *c.config_mut() = config;
+    assert_eq!(c.eval(&p.program).unwrap().to_string(), "0.833333333333333333333333333333");
+}
+
+#[test]
+fn test_angle_mode() {
+    let calc_in = |angle: Angle, input: &str| {
+        let mut p = crate::Parser::new(input);
+        p.parse().unwrap();
+        let mut c = Calculator::new();
+        c.set_angle(angle);
+        c.eval(&p.program).map(|res| res.to_string())
+    };
+    assert_eq!(calc_in(Angle::Deg, "sin(30)").unwrap(), "1/2");
+    assert_eq!(calc_in(Angle::Deg, "sin(30°) + cos(60 deg)").unwrap(), "1");
+    assert_eq!(calc_in(Angle::Deg, "asin(1)").unwrap(), "90");
+    assert_eq!(calc_in(Angle::Deg, "sin([0, 90, 270])").unwrap(), "[0, 1, -1]");
+    assert_eq!(calc_in(Angle::Deg, "(pi/2) rad").unwrap(), "90");
+    assert_eq!(calc_in(Angle::Grad, "90°").unwrap(), "100");
+    assert_eq!(calc_in(Angle::Rad, "180°").unwrap(), std::f64::consts::PI.to_string());
+    assert_eq!(calc_in(Angle::Rad, "sin(200 grad)").unwrap(), calc("sin(pi)").unwrap().to_string());
+    assert_eq!(calc_in(Angle::Rad, "sin(30°)").unwrap(), "1/2");
+    assert_eq!(calc_in(Angle::Rad, "cos(100 grad)").unwrap(), "0");
+    assert_eq!(calc_with("sin(30°)", Config { precision: Some(30), ..Default::default() }).unwrap().to_string(), "1/2");
+    assert_eq!(calc_in(Angle::Deg, "deg(pi) + rad(180)").unwrap(), calc("180 + pi").unwrap().to_string());
+    assert_eq!(calc_in(Angle::Deg, "integrate(cos(t), t, 0, 90)").map(|s| s.parse::<f64>().unwrap().round()).unwrap(), 57.0);
+    assert_eq!(calc_in(Angle::Deg, "diff(sin(x), x)").unwrap(), format!("{}*cos(x)", std::f64::consts::PI / 180.0));
+    assert_eq!(calc_in(Angle::Deg, "diff(x°, x)").unwrap(), "1");
+    assert_eq!(calc_in(Angle::Rad, "diff(x°, x)").unwrap(), (std::f64::consts::PI / 180.0).to_string());
+
+    let err = calc_in(Angle::Deg, "tan(90)").unwrap_err();
+    assert_eq!((err.message.as_str(), err.span), ("Invalid argument for tan", Span::new(0, 3)));
+    assert_eq!(calc("(5 km)°").unwrap_err().message, "Angles do not accept units");
+
+    // the mode belongs to the calculator, a new one starts in radians
+    let mut c = Calculator::new();
+    assert_eq!(c.angle(), Angle::Rad);
+    c.set_angle(Angle::Deg);
+    let mut p = crate::Parser::new("cos(180)");
+    p.parse().unwrap();
+    assert_eq!(c.eval(&p.program).unwrap(), Value::Number(Number::Int(-1)));
+    assert_ne!(Calculator::new().eval(&p.program).unwrap(), Value::Number(Number::Int(-1)));
+}
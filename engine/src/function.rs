@@ -1,37 +1,319 @@
 use crate::number::Number;
+use angle::{Angle, InverseTrig, Trig};
+use crate::config::Config;
 use crate::errors::Error;
+use crate::eval::Value;
+use num_bigint::BigInt;
+use num_traits::Signed;
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::f64::consts::PI;
+use std::fmt::Display;
+use std::rc::Rc;
 
 #[cfg(test)]
 mod tests;
 
-pub type FnPtr = fn(&mut Vec<Number>) -> Number;
+pub mod angle;
+pub mod integer;
+pub mod list;
+pub mod stats;
 
-#[derive(Debug, Clone, PartialEq)]
+pub type FnPtr = Rc<dyn Fn(&mut Vec<Number>, &Config) -> Number>;
+pub type ValueFnPtr = Rc<dyn Fn(&mut Vec<Value>, &Config) -> Result<Value, String>>;
+
+/// Number of arguments accepted by a function
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Arity {
+    Exact(usize),
+    /// At least given number of arguments, like max(1, 2, 3)
+    Variadic(usize),
+    /// From min to max arguments, the rest have default values
+    Range(usize, usize)
+}
+
+impl Arity {
+    pub fn accepts(&self, arg_count: usize) -> bool {
+        match self {
+            Arity::Exact(n) => arg_count == *n,
+            Arity::Variadic(n) => arg_count >= *n,
+            Arity::Range(min, max) => arg_count >= *min && arg_count <= *max
+        }
+    }
+}
+
+impl Display for Arity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Arity::Exact(1) => write!(f, "1 argument"),
+            Arity::Exact(n) => write!(f, "{n} arguments"),
+            Arity::Variadic(1) => write!(f, "at least 1 argument"),
+            Arity::Variadic(n) => write!(f, "at least {n} arguments"),
+            Arity::Range(min, max) => write!(f, "{min} to {max} arguments")
+        }
+    }
+}
+
+// Functions of numbers are applied to each element of list arguments,
+// functions of values get lists, strings and dates as they are
+#[derive(Clone)]
+enum Body {
+    Number(FnPtr),
+    Value(ValueFnPtr)
+}
+
+#[derive(Clone)]
 pub struct Function {
-    pub ptr: FnPtr,
-    pub arg_count: usize
+    pub name: String,
+    pub arity: Arity,
+    body: Body
 }
 
 impl Function {
-    fn new(ptr: FnPtr, arg_count: usize) -> Function {
+    pub fn new<F>(name: &str, arity: Arity, f: F) -> Function
+        where F: Fn(&mut Vec<Number>, &Config) -> Number + 'static
+    {
+        Function {
+            name: String::from(name),
+            arity,
+            body: Body::Number(Rc::new(f))
+        }
+    }
+
+    /// Function of values like lists, matrices, strings and dates
+    pub fn with_values<F>(name: &str, arity: Arity, f: F) -> Function
+        where F: Fn(&mut Vec<Value>, &Config) -> Result<Value, String> + 'static
+    {
         Function {
-            ptr,
-            arg_count
+            name: String::from(name),
+            arity,
+            body: Body::Value(Rc::new(f))
+        }
+    }
+
+    pub fn takes_values(&self) -> bool {
+        matches!(self.body, Body::Value(_))
+    }
+
+    /// Calls the function, args hold exactly the arguments of the call.
+    /// A function of values is NaN unless it returns a number.
+    pub fn exec(&self, args: &mut Vec<Number>, config: &Config) -> Number {
+        match &self.body {
+            Body::Number(f) => f(args, config),
+            Body::Value(f) => match f(&mut args.drain(..).map(Value::Number).collect(), config) {
+                Ok(Value::Number(n)) => n,
+                _ => Number::NaN
+            }
+        }
+    }
+
+    /// Calls the function with values, a function of numbers
+    /// accepts only numbers
+    pub fn exec_values(&self, args: &mut Vec<Value>, config: &Config) -> Result<Value, String> {
+        match &self.body {
+            Body::Number(_) => {
+                let mut numbers = args.drain(..).map(|arg| match arg {
+                    Value::Number(n) => Ok(n),
+                    arg => Err(format!("Expected number, got {arg}"))
+                }).collect::<Result<_, _>>()?;
+                match self.exec(&mut numbers, config) {
+                    Number::NaN => Err(format!("Invalid argument for {}", self.name)),
+                    res => Ok(Value::Number(res))
+                }
+            },
+            Body::Value(f) => f(args, config)
         }
     }
+}
+
+impl PartialEq for Function {
+    fn eq(&self, other: &Self) -> bool {
+        let same_body = match (&self.body, &other.body) {
+            (Body::Number(f), Body::Number(g)) => Rc::ptr_eq(f, g),
+            (Body::Value(f), Body::Value(g)) => Rc::ptr_eq(f, g),
+            _ => false
+        };
+        self.name == other.name && self.arity == other.arity && same_body
+    }
+}
+
+impl std::fmt::Debug for Function {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Function")
+            .field("name", &self.name)
+            .field("arity", &self.arity)
+            .finish()
+    }
+}
+
+/// Functions available to the parser by name. Applications embedding
+/// the engine can register their own functions next to the built-in ones:
+///
+/// ```
+/// use engine::function::{Arity, FunctionRegistry};
+/// use engine::number::Number;
+/// use engine::{Config, Parser};
+/// use std::rc::Rc;
+///
+/// let mut functions = FunctionRegistry::new();
+/// functions.register("double", Arity::Exact(1), |args| args[0].clone() * Number::Int(2));
+/// let mut parser = Parser::with_functions("double(21)", &Config::default(), Rc::new(functions));
+/// assert!(parser.parse().is_ok());
+/// ```
+#[derive(Clone)]
+pub struct FunctionRegistry {
+    functions: HashMap<String, Function>
+}
 
-    pub fn exec(&self, args: &mut Vec<Number>) -> Number {
-        (self.ptr)(args)
+impl Default for FunctionRegistry {
+    fn default() -> Self {
+        Self::new()
     }
 }
 
+impl FunctionRegistry {
+    /// Registry with built-in functions
+    pub fn new() -> Self {
+        let mut registry = Self::empty();
+        registry.register_builtins();
+        registry
+    }
+
+    pub fn empty() -> Self {
+        FunctionRegistry {
+            functions: HashMap::new()
+        }
+    }
+
+    /// Adds function, replacing the one registered under the same name
+    pub fn register<F>(&mut self, name: &str, arity: Arity, f: F)
+        where F: Fn(&mut Vec<Number>) -> Number + 'static
+    {
+        self.register_with_config(name, arity, move |args, _| f(args));
+    }
+
+    /// Adds function that depends on settings of the session, like sqrt
+    /// that computes decimals when precision is set
+    pub fn register_with_config<F>(&mut self, name: &str, arity: Arity, f: F)
+        where F: Fn(&mut Vec<Number>, &Config) -> Number + 'static
+    {
+        self.functions.insert(String::from(name), Function::new(name, arity, f));
+    }
+
+    /// Adds function of values like lists and strings, which gets
+    /// its arguments as they are, like len
+    pub fn register_values<F>(&mut self, name: &str, arity: Arity, f: F)
+        where F: Fn(&mut Vec<Value>, &Config) -> Result<Value, String> + 'static
+    {
+        self.functions.insert(String::from(name), Function::with_values(name, arity, f));
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Function> {
+        self.functions.get(name)
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.functions.contains_key(name)
+    }
 
+    fn register_builtins(&mut self) {
+        // angles are in the unit of the angle mode
+        self.register_with_config("sin", Arity::Exact(1), |args, config| angle::trig(Trig::Sin, args.pop().unwrap_or_default(), config, sin));
+        self.register_with_config("cos", Arity::Exact(1), |args, config| angle::trig(Trig::Cos, args.pop().unwrap_or_default(), config, cos));
+        self.register_with_config("tan", Arity::Exact(1), |args, config| angle::trig(Trig::Tan, args.pop().unwrap_or_default(), config, tan));
+        self.register_with_config("asin", Arity::Exact(1), |args, config| angle::inverse_trig(InverseTrig::Asin, args.pop().unwrap_or_default(), config, asin));
+        self.register_with_config("acos", Arity::Exact(1), |args, config| angle::inverse_trig(InverseTrig::Acos, args.pop().unwrap_or_default(), config, acos));
+        self.register_with_config("atan", Arity::Exact(1), |args, config| angle::inverse_trig(InverseTrig::Atan, args.pop().unwrap_or_default(), config, atan));
+        self.register_with_config("deg", Arity::Exact(1), |args, config| {
+            Angle::Rad.convert(args.pop().unwrap_or_default(), Angle::Deg, config.precision)
+        });
+        self.register_with_config("rad", Arity::Exact(1), |args, config| {
+            Angle::Deg.convert(args.pop().unwrap_or_default(), Angle::Rad, config.precision)
+        });
+        self.register_with_config("ln", Arity::Exact(1), ln);
+        self.register_with_config("log10", Arity::Exact(1), log10);
+        self.register_with_config("sqrt", Arity::Exact(1), sqrt);
+        self.register("root", Arity::Exact(2), root);
+        self.register("abs", Arity::Exact(1), abs);
+        self.register_with_config("log", Arity::Exact(2), log);
+        self.register("re", Arity::Exact(1), re);
+        self.register("im", Arity::Exact(1), im);
+        self.register("arg", Arity::Exact(1), arg);
+        self.register("conj", Arity::Exact(1), conj);
+        self.register("min", Arity::Variadic(1), |args| extreme(args, Ordering::Less));
+        self.register("max", Arity::Variadic(1), |args| extreme(args, Ordering::Greater));
+        self.register("sum", Arity::Variadic(1), sum);
+        self.register("avg", Arity::Variadic(1), stats::mean);
+        self.register("mean", Arity::Variadic(1), stats::mean);
+        self.register("median", Arity::Variadic(1), stats::median);
+        self.register_with_config("variance", Arity::Variadic(1), stats::variance);
+        self.register_with_config("stdev", Arity::Variadic(1), stats::stdev);
+        self.register("percentile", Arity::Variadic(2), stats::percentile);
+        self.register("normpdf", Arity::Range(1, 3), stats::normpdf);
+        self.register("normcdf", Arity::Range(1, 3), stats::normcdf);
+        self.register("norminv", Arity::Range(1, 3), stats::norminv);
+        self.register("binompdf", Arity::Exact(3), stats::binompdf);
+        self.register("binomcdf", Arity::Exact(3), stats::binomcdf);
+        self.register("poissonpdf", Arity::Exact(2), stats::poissonpdf);
+        self.register("poissoncdf", Arity::Exact(2), stats::poissoncdf);
+        self.register("gcd", Arity::Variadic(1), |args| integer::gcd(args));
+        self.register("lcm", Arity::Variadic(1), |args| integer::lcm(args));
+        self.register("nCr", Arity::Exact(2), integer::ncr);
+        self.register("nPr", Arity::Exact(2), integer::npr);
+        self.register("isprime", Arity::Exact(1), integer::isprime);
+        self.register("mod_pow", Arity::Exact(3), integer::mod_pow);
+        self.register("gamma", Arity::Exact(1), real_gamma);
+        // lists, matrices, strings and dates
+        self.register_values("len", Arity::Exact(1), |args, _| list::len(args));
+        self.register_values("sort", Arity::Exact(1), list::sort);
+        self.register_values("range", Arity::Range(1, 3), |args, _| list::range(args));
+        self.register_values("det", Arity::Exact(1), list::det);
+        self.register_values("inv", Arity::Exact(1), |args, _| list::inv(args));
+        self.register_values("transpose", Arity::Exact(1), |args, _| list::transpose(args));
+        self.register_values("rank", Arity::Exact(1), |args, _| list::rank(args));
+        self.register_values("eig", Arity::Exact(1), |args, _| list::eig(args));
+        self.register_values("identity", Arity::Exact(1), |args, _| list::identity(args));
+        self.register_values("zeros", Arity::Range(1, 2), |args, _| list::zeros(args));
+        self.register_values("solve", Arity::Exact(2), |args, _| list::solve(args));
+        self.register_values("upper", Arity::Exact(1), |args, _| list::upper(args));
+        self.register_values("lower", Arity::Exact(1), |args, _| list::lower(args));
+        self.register_values("format", Arity::Exact(2), |args, _| list::format(args));
+        self.register_values("now", Arity::Exact(0), |args, _| list::now(args));
+        self.register_values("weekday", Arity::Exact(1), |args, _| list::weekday(args));
+        self.register_values("workdays", Arity::Exact(2), |args, _| list::workdays(args));
+    }
+}
+
+thread_local! {
+    static BUILTINS: Rc<FunctionRegistry> = Rc::new(FunctionRegistry::new());
+}
+
+/// Shared registry of built-in functions
+pub fn builtins() -> Rc<FunctionRegistry> {
+    BUILTINS.with(Rc::clone)
+}
+
+// When decimal precision is set the function is computed
+// with Decimal method of the same name instead of f64.
+// Complex arguments and real arguments outside of function domain,
+// like sqrt(-1), are computed with Complex64 method.
 macro_rules! f64_function {
     ($n:ident) => {
-        fn $n(stack: &mut Vec<Number>) -> Number {
-            let arg: f64 = stack.pop().unwrap_or_default().into();
-            arg.$n().into()
-        }        
+        fn $n(stack: &mut Vec<Number>, config: &Config) -> Number {
+            let arg = stack.pop().unwrap_or_default();
+            let res = match (&arg, config.precision) {
+                (Number::NaN | Number::Complex(_), _) => Number::NaN,
+                (_, Some(prec)) => arg.to_decimal(prec).and_then(|d| d.$n(prec)).into(),
+                (_, None) => {
+                    let arg: f64 = arg.clone().into();
+                    arg.$n().into()
+                }
+            };
+            match (res, arg.to_complex()) {
+                (Number::NaN, Some(c)) => c.$n().into(),
+                (res, _) => res
+            }
+        }
     }
 }
 
@@ -50,48 +332,148 @@ fn abs(stack: &mut Vec<Number>) -> Number {
     match n {
         Number::NaN => Number::NaN,
         Number::Float(f) => f.abs().into(),
-        Number::Int(i) => i.abs().into()
+        Number::Int(i) => BigInt::from(i.unsigned_abs()).into(),
+        Number::BigInt(b) => BigInt::from(b.magnitude().clone()).into(),
+        Number::Rational(r) => r.abs().into(),
+        Number::Decimal(d) => Number::Decimal(d.abs()),
+        Number::Complex(c) => c.norm().into()
+    }
+}
+
+fn re(stack: &mut Vec<Number>) -> Number {
+    match stack.pop().unwrap_or_default() {
+        Number::Complex(c) => c.re.into(),
+        n => n
+    }
+}
+
+fn im(stack: &mut Vec<Number>) -> Number {
+    match stack.pop().unwrap_or_default() {
+        Number::Complex(c) => c.im.into(),
+        Number::NaN => Number::NaN,
+        _ => Number::Int(0)
+    }
+}
+
+fn arg(stack: &mut Vec<Number>) -> Number {
+    match stack.pop().unwrap_or_default().to_complex() {
+        Some(c) => c.arg().into(),
+        None => Number::NaN
     }
 }
 
-fn log(stack: &mut Vec<Number>) -> Number {
+fn conj(stack: &mut Vec<Number>) -> Number {
+    match stack.pop().unwrap_or_default() {
+        Number::Complex(c) => c.conj().into(),
+        n => n
+    }
+}
+
+fn log(stack: &mut Vec<Number>, config: &Config) -> Number {
     let rhs = stack.pop().unwrap_or_default();
     let lhs = stack.pop().unwrap_or_default();
     if rhs == Number::NaN || lhs == Number::NaN {
         return Number::NaN
     }
-    let l: f64 = lhs.into();
-    let r: f64 = rhs.into();
-    l.log(r).into()
+    let (l, r) = (lhs.to_complex(), rhs.to_complex());
+    let res: Number = match config.precision {
+        Some(prec) => {
+            let wp = prec + 2;
+            let (l, r) = (lhs.to_decimal(wp), rhs.to_decimal(wp));
+            l.and_then(|l| l.ln(wp))
+                .zip(r.and_then(|r| r.ln(wp)))
+                .and_then(|(l, r)| l.div(&r, prec))
+                .into()
+        },
+        None => {
+            let l: f64 = lhs.into();
+            let r: f64 = rhs.into();
+            l.log(r).into()
+        }
+    };
+    match (res, l, r) {
+        (Number::NaN, Some(l), Some(r)) => (l.ln() / r.ln()).into(),
+        (res, _, _) => res
+    }
 }
 
 fn root(stack: &mut Vec<Number>) -> Number {
     let rhs = stack.pop().unwrap_or_default();
     let lhs = stack.pop().unwrap_or_default();
-    if rhs == Number::NaN || lhs == Number::NaN {
-        return Number::NaN
+    lhs ^ (Number::Int(1) / rhs)
+}
+
+// smallest or largest of the arguments, NaN if any of them cannot be compared
+fn extreme(args: &[Number], order: Ordering) -> Number {
+    let mut res = match args.first() {
+        Some(n) => n.clone(),
+        None => return Number::NaN
+    };
+    for n in args.iter() {
+        match n.compare(&res) {
+            Some(o) if o == order => res = n.clone(),
+            Some(_) => (),
+            None => return Number::NaN
+        }
+    }
+    res
+}
+
+fn sum(args: &mut Vec<Number>) -> Number {
+    args.drain(..).fold(Number::Int(0), |acc, n| acc + n)
+}
+
+// coefficients of Lanczos approximation with g = 7
+const LANCZOS: [f64; 9] = [
+    0.999_999_999_999_809_9, 676.520_368_121_885_1, -1_259.139_216_722_402_8,
+    771.323_428_777_653_1, -176.615_029_162_140_6, 12.507_343_278_686_905,
+    -0.138_571_095_265_720_12, 9.984_369_578_019_572e-6, 1.505_632_735_149_311_6e-7
+];
+
+/// Gamma function, (x - 1)! for positive integers. NaN at its poles
+/// 0, -1, -2, ... and infinity for x above 171.
+pub fn gamma(x: f64) -> f64 {
+    if x <= 0.0 && x.fract() == 0.0 {
+        return f64::NAN;
+    }
+    if x < 0.5 {
+        // reflection formula
+        return PI / ((PI * x).sin() * gamma(1.0 - x));
+    }
+    let (t, sum) = lanczos(x);
+    // t^(x - 0.5) is split in halves so that it does not overflow before e^-t
+    let half = t.powf((x - 0.5) / 2.0);
+    (2.0 * PI).sqrt() * half * ((-t).exp() * half) * sum
+}
+
+/// Logarithm of gamma(x) for x > 0, also where gamma(x) overflows
+pub fn ln_gamma(x: f64) -> f64 {
+    if x < 0.5 {
+        return (PI / (PI * x).sin()).ln() - ln_gamma(1.0 - x);
+    }
+    let (t, sum) = lanczos(x);
+    (2.0 * PI).sqrt().ln() + (x - 0.5) * t.ln() - t + sum.ln()
+}
+
+// gamma(x) == sqrt(2 pi) t^(x - 0.5) e^-t sum, for x >= 0.5
+fn lanczos(x: f64) -> (f64, f64) {
+    let t = x + 6.5;
+    let sum = LANCZOS[1..].iter().enumerate()
+        .fold(LANCZOS[0], |acc, (i, c)| acc + c / (x + i as f64));
+    (t, sum)
+}
+
+fn real_gamma(stack: &mut Vec<Number>) -> Number {
+    match stack.pop().unwrap_or_default() {
+        Number::NaN | Number::Complex(_) => Number::NaN,
+        n => gamma(n.into()).into()
     }
-    let l: f64 = lhs.into();
-    let r: f64 = rhs.into();
-    l.powf(1.0/r).into()
 }
 
 pub fn parse_function(input: &str) -> Result<Function, Error> {
     let name = input.trim();
-    let f = match name {
-        "sin" => Function::new(sin, 1),
-        "cos" => Function::new(cos, 1),
-        "tan" => Function::new(tan, 1),
-        "asin" => Function::new(asin, 1),
-        "acos" => Function::new(acos, 1),
-        "atan" => Function::new(atan, 1),
-        "ln" => Function::new(ln, 1),
-        "log10" => Function::new(log10, 1),
-        "sqrt" => Function::new(sqrt, 1),
-        "root" => Function::new(root, 2),
-        "abs" => Function::new(abs, 1),
-        "log" => Function::new(log, 2),
-        _ => return Err(format!("Unknown function {name}").into())
-    };
-    Ok(f)
+    match builtins().get(name) {
+        Some(f) => Ok(f.clone()),
+        None => Err(format!("Unknown function {name}").into())
+    }
 }
@@ -0,0 +1,146 @@
+use crate::config::Config;
+use crate::number::Number;
+use crate::number::parse;
+use num_bigint::BigInt;
+use num_integer::Integer;
+use num_traits::ToPrimitive;
+use std::cmp::Ordering;
+use std::fmt::Display;
+
+/// Unit of angles taken and returned by trigonometric functions
+#[derive(Clone, Copy, Debug, PartialEq, Default)]
+pub enum Angle {
+    #[default]
+    Rad,
+    Deg,
+    Grad
+}
+
+impl Angle {
+    pub fn parse(name: &str) -> Option<Angle> {
+        match name {
+            "rad" => Some(Angle::Rad),
+            "deg" | "°" => Some(Angle::Deg),
+            "grad" => Some(Angle::Grad),
+            _ => None
+        }
+    }
+
+    // full turn in the unit, 2pi has the given decimal precision
+    fn turn(self, precision: Option<usize>) -> Number {
+        match self {
+            Angle::Rad => Number::Int(2) * parse::parse_const("pi", precision).unwrap_or_default(),
+            Angle::Deg => Number::Int(360),
+            Angle::Grad => Number::Int(400)
+        }
+    }
+
+    /// Converts angle given in this unit to the other unit, pi in radians
+    /// has the given decimal precision. Degrees and gradians convert
+    /// exactly, 90 deg is 100 grad.
+    pub fn convert(self, n: Number, to: Angle, precision: Option<usize>) -> Number {
+        if self == to {
+            return n;
+        }
+        n / self.turn(precision) * to.turn(precision)
+    }
+}
+
+impl Display for Angle {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            Angle::Rad => "rad",
+            Angle::Deg => "deg",
+            Angle::Grad => "grad"
+        };
+        write!(f, "{name}")
+    }
+}
+
+// sin of multiples of 30 degrees in halves, None where it is irrational
+const SINES: [Option<i128>; 12] = [
+    Some(0), Some(1), None, Some(2), None, Some(1), Some(0), Some(-1), None, Some(-2), None, Some(-1)
+];
+// tan of multiples of 45 degrees, None where it is not defined
+const TANGENTS: [Option<i128>; 4] = [Some(0), Some(1), None, Some(-1)];
+
+fn is_exact(n: &Number) -> bool {
+    matches!(n, Number::Int(_) | Number::BigInt(_) | Number::Rational(_))
+}
+
+// sin of k twelfths of a turn when it is rational
+fn sine(k: i128) -> Option<Number> {
+    SINES[k.rem_euclid(12) as usize].map(|halves| Number::Int(halves) / Number::Int(2))
+}
+
+// how many of `parts` equal parts of a turn the angle is, when it falls
+// on one of them. Degrees and gradians are reduced exactly, radians fall on
+// a part only when they are the same number as its degrees converted, so
+// 30° in radians is a twelfth of a turn.
+fn part_of_turn(n: &Number, angle: Angle, parts: i128, precision: Option<usize>) -> Option<i128> {
+    let k = match angle {
+        Angle::Rad if !is_exact(n) => {
+            let k = (f64::from(n.clone()) * parts as f64 / std::f64::consts::TAU).round();
+            if !k.is_finite() || k.abs() > 1e15 {
+                return None;
+            }
+            let degrees = Number::Int(k as i128 * 360 / parts);
+            if Angle::Deg.convert(degrees, Angle::Rad, precision) != *n {
+                return None;
+            }
+            BigInt::from(k as i128)
+        },
+        Angle::Deg | Angle::Grad if is_exact(n) => (n.clone() * Number::Int(parts) / angle.turn(None)).to_bigint()?,
+        _ => return None
+    };
+    k.mod_floor(&BigInt::from(parts)).to_i128()
+}
+
+/// Trigonometric functions, exact at multiples of 30 and 45 degrees
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Trig {
+    Sin,
+    Cos,
+    Tan
+}
+
+/// Inverse trigonometric functions, exact where their trigonometric
+/// function is
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum InverseTrig {
+    Asin,
+    Acos,
+    Atan
+}
+
+/// Trigonometric function of an angle in the unit of the angle mode. Desk
+/// calculator values like sin(30) = 0.5 in degrees are exact, others go
+/// through radians.
+pub fn trig(func: Trig, arg: Number, config: &Config, f: fn(&mut Vec<Number>, &Config) -> Number) -> Number {
+    let (angle, precision) = (config.angle, config.precision);
+    let exact = match func {
+        Trig::Sin => part_of_turn(&arg, angle, 12, precision).and_then(sine),
+        Trig::Cos => part_of_turn(&arg, angle, 12, precision).and_then(|k| sine(k + 3)),
+        // tan of 90 degrees is not defined
+        Trig::Tan => part_of_turn(&arg, angle, 8, precision).map(|k| TANGENTS[k as usize % 4].map_or(Number::NaN, Number::Int))
+    };
+    exact.unwrap_or_else(|| f(&mut vec![angle.convert(arg, Angle::Rad, config.precision)], config))
+}
+
+/// Inverse trigonometric function returning an angle in the unit of the angle mode
+pub fn inverse_trig(func: InverseTrig, arg: Number, config: &Config, f: fn(&mut Vec<Number>, &Config) -> Number) -> Number {
+    let angle = config.angle;
+    let equal = |n: Option<Number>| n.and_then(|n| n.compare(&arg)) == Some(Ordering::Equal);
+    // parts of a turn, twelfths for asin and acos and eighths for atan,
+    // floats count too, asin(0.5) is 30 degrees
+    let exact = match func {
+        _ if angle == Angle::Rad => None,
+        InverseTrig::Asin => (-3..=3).find(|k| equal(sine(*k))).map(|k| (k, 12)),
+        InverseTrig::Acos => (-3..=3).find(|k| equal(sine(*k))).map(|k| (3 - k, 12)),
+        InverseTrig::Atan => (-1..=1).find(|k| equal(Some(Number::Int(*k)))).map(|k| (k, 8))
+    };
+    match exact {
+        Some((k, parts)) => Number::Int(k) * angle.turn(config.precision) / Number::Int(parts),
+        None => Angle::Rad.convert(f(&mut vec![arg], config), angle, config.precision)
+    }
+}
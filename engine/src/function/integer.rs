@@ -0,0 +1,227 @@
+use crate::number::Number;
+use num_bigint::BigInt;
+use num_integer::Integer;
+use num_traits::{One, Signed, ToPrimitive, Zero};
+
+// larger factorials would not fit into the size limit of integers
+const MAX_FACTORIAL: u64 = 50_000;
+
+// steps of Pollard's rho method before factor gives up
+const MAX_RHO_STEPS: usize = 300_000;
+
+// bases of Miller-Rabin test, the first 13 of them give exact
+// answers below 3.3 * 10^24, all 20 are used for larger numbers
+const PRIMES: [u32; 20] = [2, 3, 5, 7, 11, 13, 17, 19, 23, 29, 31, 37, 41, 43, 47, 53, 59, 61, 67, 71];
+
+/// n! for non-negative integers, exact however large it is.
+/// Other real numbers get gamma(n + 1). NaN for negative integers,
+/// complex numbers and results too large to compute.
+pub fn factorial(n: &Number) -> Number {
+    match n.to_bigint() {
+        Some(n) if n.is_negative() => Number::NaN,
+        Some(n) => match n.to_u64() {
+            Some(n) if n <= MAX_FACTORIAL => product(&BigInt::one(), n).into(),
+            _ => Number::NaN
+        },
+        None if matches!(n, Number::Complex(_) | Number::NaN) => Number::NaN,
+        None => {
+            let x: f64 = n.clone().into();
+            match super::gamma(x + 1.0) {
+                res if res.is_finite() => res.into(),
+                _ => Number::NaN
+            }
+        }
+    }
+}
+
+// product of count consecutive integers starting at lo, split in halves
+// so that big numbers are multiplied by numbers of similar size
+fn product(lo: &BigInt, count: u64) -> BigInt {
+    if count <= 16 {
+        return (0..count).fold(BigInt::one(), |acc, i| acc * (lo + i));
+    }
+    let half = count / 2;
+    product(lo, half) * product(&(lo + half), count - half)
+}
+
+// n and k of nCr and nPr, None unless 0 <= k <= n
+fn choose_args(args: &mut Vec<Number>) -> Option<(BigInt, u64)> {
+    let k = args.pop()?.to_bigint()?;
+    let n = args.pop()?.to_bigint()?;
+    if n.is_negative() || k.is_negative() || k > n {
+        return None;
+    }
+    Some((n, k.to_u64().filter(|k| *k <= MAX_FACTORIAL)?))
+}
+
+/// Number of ways to choose k of n items
+pub fn ncr(args: &mut Vec<Number>) -> Number {
+    match choose_args(args) {
+        Some((n, k)) => {
+            // C(n, k) == C(n, n - k), the smaller k needs fewer steps
+            let k = (&n - k).to_u64().map_or(k, |rest| rest.min(k));
+            (product(&(&n - k + 1), k) / product(&BigInt::one(), k)).into()
+        },
+        None => Number::NaN
+    }
+}
+
+/// Number of ordered arrangements of k of n items
+pub fn npr(args: &mut Vec<Number>) -> Number {
+    match choose_args(args) {
+        Some((n, k)) => product(&(n - k + 1), k).into(),
+        None => Number::NaN
+    }
+}
+
+// folds integer arguments, NaN if any of them is not an integer
+fn int_fold<F>(args: &[Number], f: F) -> Number
+    where F: Fn(BigInt, BigInt) -> BigInt
+{
+    let mut res: Option<BigInt> = None;
+    for n in args.iter() {
+        let n = match n.to_bigint() {
+            Some(n) => n,
+            None => return Number::NaN
+        };
+        res = Some(match res {
+            Some(r) => f(r, n),
+            None => n.abs()
+        });
+    }
+    res.map_or(Number::NaN, Number::from)
+}
+
+pub fn gcd(args: &[Number]) -> Number {
+    int_fold(args, |a, b| a.gcd(&b))
+}
+
+pub fn lcm(args: &[Number]) -> Number {
+    int_fold(args, |a, b| a.lcm(&b))
+}
+
+/// b^e mod m without computing b^e, negative e uses the inverse of b
+pub fn mod_pow(args: &mut Vec<Number>) -> Number {
+    let ints: Option<Vec<_>> = args.drain(..).map(|n| n.to_bigint()).collect();
+    let (b, e, m) = match ints.as_deref() {
+        Some([b, e, m]) if m.is_positive() => (b.mod_floor(m), e, m),
+        _ => return Number::NaN
+    };
+    if !e.is_negative() {
+        return b.modpow(e, m).into();
+    }
+    match inverse(&b, m) {
+        Some(inv) => inv.modpow(&-e, m).into(),
+        None => Number::NaN
+    }
+}
+
+// x with a * x == 1 (mod m), None if a and m have common divisors
+fn inverse(a: &BigInt, m: &BigInt) -> Option<BigInt> {
+    let gcd = a.extended_gcd(m);
+    if !gcd.gcd.is_one() {
+        return None;
+    }
+    Some(gcd.x.mod_floor(m))
+}
+
+/// 1 for primes, 0 for other integers
+pub fn isprime(args: &mut Vec<Number>) -> Number {
+    match args.pop().and_then(|n| n.to_bigint()) {
+        Some(n) => is_prime(&n).into(),
+        None => Number::NaN
+    }
+}
+
+pub fn is_prime(n: &BigInt) -> bool {
+    if n < &BigInt::from(2) {
+        return false;
+    }
+    for p in PRIMES {
+        if n == &BigInt::from(p) {
+            return true;
+        }
+        if (n % p).is_zero() {
+            return false;
+        }
+    }
+    // n - 1 == d * 2^s with odd d
+    let n1: BigInt = n - 1;
+    let s = n1.trailing_zeros().unwrap_or(0);
+    let d = &n1 >> s;
+    'bases: for a in PRIMES {
+        let mut x = BigInt::from(a).modpow(&d, n);
+        if x.is_one() || x == n1 {
+            continue;
+        }
+        for _ in 1..s {
+            x = &x * &x % n;
+            if x == n1 {
+                continue 'bases;
+            }
+        }
+        return false;
+    }
+    true
+}
+
+/// Prime factors of n > 0 with their powers, in ascending order.
+/// None if a factor is too large to be found.
+pub fn factor(n: &BigInt) -> Option<Vec<(BigInt, u32)>> {
+    let mut factors = vec![];
+    let mut rest = n.clone();
+    let mut p = BigInt::from(2);
+    // small factors are found faster by division
+    while &p * &p <= rest && p < BigInt::from(1000) {
+        while (&rest % &p).is_zero() {
+            rest /= &p;
+            factors.push(p.clone());
+        }
+        p += 1;
+    }
+    let mut composite = vec![];
+    if rest > BigInt::one() {
+        composite.push(rest);
+    }
+    while let Some(m) = composite.pop() {
+        if is_prime(&m) {
+            factors.push(m);
+            continue;
+        }
+        let d = rho(&m)?;
+        composite.push(&m / &d);
+        composite.push(d);
+    }
+    factors.sort();
+    let mut res: Vec<(BigInt, u32)> = vec![];
+    for f in factors {
+        match res.last_mut() {
+            Some((last, power)) if *last == f => *power += 1,
+            _ => res.push((f, 1))
+        }
+    }
+    Some(res)
+}
+
+// nontrivial divisor of odd composite n by Pollard's rho method
+fn rho(n: &BigInt) -> Option<BigInt> {
+    let mut steps = 0;
+    for c in 1u32.. {
+        let f = |x: &BigInt| (x * x + c) % n;
+        let (mut x, mut y, mut d) = (BigInt::from(2), BigInt::from(2), BigInt::one());
+        while d.is_one() {
+            steps += 1;
+            if steps > MAX_RHO_STEPS {
+                return None;
+            }
+            x = f(&x);
+            y = f(&f(&y));
+            d = (&x - &y).abs().gcd(n);
+        }
+        // the sequence met itself before finding a divisor, try another one
+        if &d != n {
+            return Some(d);
+        }
+    }
+    None
+}
@@ -0,0 +1,241 @@
+use crate::config::Config;
+use crate::date::Date;
+use crate::eval::{self, Value, MAX_LIST_LEN};
+use crate::matrix::Matrix;
+use crate::number::{pattern, Number};
+use crate::unit;
+use std::cmp::Ordering;
+
+// Functions of lists, matrices, strings and dates. They get their
+// arguments as they are, a list is not split into its elements.
+
+/// len(list) or len(str), a matrix has as many elements as rows
+pub fn len(args: &mut Vec<Value>) -> Result<Value, String> {
+    match args.pop().unwrap_or_default() {
+        Value::Str(s) => Ok(Value::Number(Number::Int(s.chars().count() as i128))),
+        value => Ok(Value::Number(Number::Int(eval::expect_list(value)?.len() as i128)))
+    }
+}
+
+/// sort(list) of numbers or quantities of the same dimension, or of strings
+pub fn sort(args: &mut Vec<Value>, config: &Config) -> Result<Value, String> {
+    let mut items = eval::expect_list(args.pop().unwrap_or_default())?;
+    // every element must compare with the first one, then all of them compare
+    if let Some(first) = items.first() {
+        for item in &items {
+            compare(first, item, config)?;
+        }
+    }
+    items.sort_by(|a, b| compare(a, b, config).unwrap_or(Ordering::Equal));
+    Ok(Value::List(items))
+}
+
+// order of numbers or quantities of the same dimension, or of strings
+fn compare(l: &Value, r: &Value, config: &Config) -> Result<Ordering, String> {
+    if matches!(l, Value::List(_) | Value::Matrix(_)) || matches!(r, Value::List(_) | Value::Matrix(_)) {
+        return Err(String::from("Cannot sort nested lists"));
+    }
+    match (l, r) {
+        (Value::Str(l), Value::Str(r)) => return Ok(l.cmp(r)),
+        (Value::Str(_), _) | (_, Value::Str(_)) => return Err(String::from("Cannot compare strings and numbers")),
+        (Value::Date(l), Value::Date(r)) => return Ok(l.0.compare(&r.0).unwrap_or(Ordering::Equal)),
+        (Value::Date(_), _) | (_, Value::Date(_)) => return Err(String::from("Cannot compare dates and numbers")),
+        _ => ()
+    }
+    let (l, r) = (l.clone().into_quantity(), r.clone().into_quantity());
+    if !l.unit.same_dimension(&r.unit) {
+        return Err(format!("Cannot compare {} and {}", unit::describe(&l.unit), unit::describe(&r.unit)));
+    }
+    let converted = r.convert(&l.unit, &config.rates)?;
+    l.value.compare(&converted.value).ok_or_else(|| String::from("Cannot compare complex numbers"))
+}
+
+/// range(b), range(a, b) or range(a, b, step) from a up to b, without b
+pub fn range(args: &mut Vec<Value>) -> Result<Value, String> {
+    let mut numbers = vec![];
+    for arg in args.drain(..) {
+        match arg {
+            Value::Number(n) if n.compare(&n).is_some() => numbers.push(n),
+            Value::Quantity(q) => return Err(eval::units_not_accepted(&q.unit)),
+            arg => return Err(format!("Expected real number, got {arg}"))
+        }
+    }
+    let (start, end, step) = match <[Number; 3]>::try_from(numbers) {
+        Ok([start, end, step]) => (start, end, step),
+        Err(mut numbers) => {
+            let end = numbers.pop().unwrap_or_default();
+            (numbers.pop().unwrap_or(Number::Int(0)), end, Number::Int(1))
+        }
+    };
+    if step.is_zero() {
+        return Err(String::from("Step of range cannot be zero"));
+    }
+    let count = f64::from((end - start.clone()) / step.clone()).ceil().max(0.0);
+    if count > MAX_LIST_LEN as f64 {
+        return Err(String::from("Range is too long"));
+    }
+    let items = (0..count as usize)
+        .map(|i| Value::Number(start.clone() + step.clone() * Number::Int(i as i128)))
+        .collect();
+    Ok(Value::List(items))
+}
+
+fn expect_matrix(value: Value) -> Result<Matrix, String> {
+    match value {
+        Value::Matrix(m) => Ok(m),
+        value => Err(format!("Expected matrix, got {value}"))
+    }
+}
+
+/// det(A), integers wrap around in the word of the session
+pub fn det(args: &mut Vec<Value>, config: &Config) -> Result<Value, String> {
+    match expect_matrix(args.pop().unwrap_or_default())?.det()? {
+        Number::NaN => Err(String::from("Overflow")),
+        det => Ok(Value::Number(config.wrap(det)))
+    }
+}
+
+/// inv(A)
+pub fn inv(args: &mut Vec<Value>) -> Result<Value, String> {
+    let m = expect_matrix(args.pop().unwrap_or_default())?;
+    Ok(Value::Matrix(eval::checked(m.inv())?))
+}
+
+/// transpose(A), a list of numbers is a row, it becomes a column
+pub fn transpose(args: &mut Vec<Value>) -> Result<Value, String> {
+    match args.pop().unwrap_or_default() {
+        Value::List(items) if eval::column(&items).is_some() => {
+            Ok(eval::list(items.into_iter().map(|n| Value::List(vec![n])).collect()))
+        },
+        value => Ok(Value::Matrix(expect_matrix(value)?.transpose()))
+    }
+}
+
+/// rank(A)
+pub fn rank(args: &mut Vec<Value>) -> Result<Value, String> {
+    let m = expect_matrix(args.pop().unwrap_or_default())?;
+    Ok(Value::Number(Number::Int(m.rank() as i128)))
+}
+
+/// eig(A), list of eigenvalues
+pub fn eig(args: &mut Vec<Value>) -> Result<Value, String> {
+    let values = expect_matrix(args.pop().unwrap_or_default())?.eigenvalues()?;
+    Ok(Value::List(values.into_iter().map(Value::Number).collect()))
+}
+
+// number of rows or columns of a new matrix
+fn matrix_size(value: Value) -> Result<usize, String> {
+    let size = match &value {
+        Value::Number(n) => n.to_bigint().and_then(|n| usize::try_from(n).ok()),
+        _ => None
+    };
+    match size {
+        Some(size) if size > 0 && size <= MAX_LIST_LEN => Ok(size),
+        _ => Err(format!("Expected size of matrix, got {value}"))
+    }
+}
+
+// rows and columns of a new matrix, zeros(n) is square
+fn matrix_sizes(args: &mut Vec<Value>) -> Result<(usize, usize), String> {
+    let sizes = args.drain(..).map(matrix_size).collect::<Result<Vec<_>, _>>()?;
+    let (rows, cols) = (sizes[0], *sizes.get(1).unwrap_or(&sizes[0]));
+    if rows * cols > MAX_LIST_LEN {
+        return Err(String::from("Matrix is too large"));
+    }
+    Ok((rows, cols))
+}
+
+/// identity(n)
+pub fn identity(args: &mut Vec<Value>) -> Result<Value, String> {
+    let (n, _) = matrix_sizes(args)?;
+    Ok(Value::Matrix(Matrix::identity(n)))
+}
+
+/// zeros(n) or zeros(rows, cols)
+pub fn zeros(args: &mut Vec<Value>) -> Result<Value, String> {
+    let (rows, cols) = matrix_sizes(args)?;
+    Ok(Value::Matrix(Matrix::zeros(rows, cols)))
+}
+
+/// solve(A, b) of a linear system, b is a list of numbers
+/// or a matrix with a column for each system
+pub fn solve(args: &mut Vec<Value>) -> Result<Value, String> {
+    let b = args.pop().unwrap_or_default();
+    let a = expect_matrix(args.pop().unwrap_or_default())?;
+    match b {
+        Value::List(items) => match eval::column(&items) {
+            Some(b) => Ok(eval::vector(eval::checked(a.solve(&b))?)),
+            None => Err(format!("Expected list of numbers, got {}", Value::List(items)))
+        },
+        b => Ok(Value::Matrix(eval::checked(a.solve(&expect_matrix(b)?))?))
+    }
+}
+
+// string or strings in a list changed by the function
+fn change_case(value: Value, f: fn(&str) -> String) -> Result<Value, String> {
+    match value {
+        Value::Str(s) => Ok(Value::Str(f(&s))),
+        Value::List(items) => eval::map_list(items, |item| change_case(item, f)),
+        value => Err(format!("Expected string, got {value}"))
+    }
+}
+
+/// upper(str)
+pub fn upper(args: &mut Vec<Value>) -> Result<Value, String> {
+    change_case(args.pop().unwrap_or_default(), str::to_uppercase)
+}
+
+/// lower(str)
+pub fn lower(args: &mut Vec<Value>) -> Result<Value, String> {
+    change_case(args.pop().unwrap_or_default(), str::to_lowercase)
+}
+
+// number as a string by the pattern, with its unit after it
+fn format_value(value: Value, pattern: &str) -> Result<Value, String> {
+    match eval::unpack(value) {
+        Value::Number(n) => pattern::format(&n, pattern).map(Value::Str),
+        Value::Quantity(q) => pattern::format(&q.value, pattern).map(|n| Value::Str(format!("{n} {}", q.unit))),
+        Value::List(items) => eval::map_list(items, |item| format_value(item, pattern)),
+        value => Err(format!("Expected real number, got {value}"))
+    }
+}
+
+/// format(x, pattern)
+pub fn format(args: &mut Vec<Value>) -> Result<Value, String> {
+    let pattern = match args.pop().unwrap_or_default() {
+        Value::Str(pattern) => pattern,
+        value => return Err(format!("Expected format pattern, got {value}"))
+    };
+    format_value(args.pop().unwrap_or_default(), &pattern)
+}
+
+fn expect_date(value: Value) -> Result<Date, String> {
+    match value {
+        Value::Date(d) => Ok(d),
+        value => Err(format!("Expected date, got {value}"))
+    }
+}
+
+/// now()
+pub fn now(_: &mut Vec<Value>) -> Result<Value, String> {
+    Ok(Value::Date(Date::now()))
+}
+
+fn weekday_of(value: Value) -> Result<Value, String> {
+    match value {
+        Value::List(items) => eval::map_list(items, weekday_of),
+        value => Ok(Value::Number(Number::Int(expect_date(value)?.weekday() as i128)))
+    }
+}
+
+/// weekday(date) or weekdays of a list of dates
+pub fn weekday(args: &mut Vec<Value>) -> Result<Value, String> {
+    weekday_of(args.pop().unwrap_or_default())
+}
+
+/// workdays(a, b) between two dates
+pub fn workdays(args: &mut Vec<Value>) -> Result<Value, String> {
+    let end = expect_date(args.pop().unwrap_or_default())?;
+    let start = expect_date(args.pop().unwrap_or_default())?;
+    Ok(Value::Number(Number::Int(start.workdays(&end) as i128)))
+}
@@ -0,0 +1,273 @@
+use super::{integer, ln_gamma};
+use crate::config::Config;
+use crate::number::Number;
+use num_traits::ToPrimitive;
+use std::cmp::Ordering;
+use std::f64::consts::{PI, SQRT_2};
+
+// binomial probabilities are computed directly up to this many trials,
+// exactly when p is exact, above it through logarithms
+const MAX_DIRECT_TRIALS: u64 = 1000;
+
+// upper limit of trials and counts summed by cumulative distributions
+const MAX_COUNT: u64 = 10_000_000;
+
+// depth of the continued fraction of erfc, enough for x >= 2
+const ERFC_TERMS: u32 = 100;
+
+// coefficients of Acklam's approximation of the inverse normal distribution
+const ACKLAM_A: [f64; 6] = [
+    -3.969683028665376e1, 2.209460984245205e2, -2.759285104469687e2,
+    1.38357751867269e2, -3.066479806614716e1, 2.506628277459239
+];
+const ACKLAM_B: [f64; 5] = [
+    -5.447609879822406e1, 1.615858368580409e2, -1.556989798598866e2,
+    6.680131188771972e1, -1.328068155288572e1
+];
+const ACKLAM_C: [f64; 6] = [
+    -7.784894002430293e-3, -3.223964580411365e-1, -2.400758277161838,
+    -2.549732539343734, 4.374664141464968, 2.938163982698783
+];
+const ACKLAM_D: [f64; 4] = [
+    7.784695709041462e-3, 3.224671290700398e-1, 2.445134137142996, 3.754408661907416
+];
+
+pub fn mean(args: &mut Vec<Number>) -> Number {
+    let count = Number::Int(args.len() as i128);
+    super::sum(args) / count
+}
+
+// arguments in ascending order, None if some of them cannot be compared
+fn sorted(args: &mut Vec<Number>) -> Option<Vec<Number>> {
+    let mut sorted = Vec::with_capacity(args.len());
+    for n in args.drain(..) {
+        n.compare(&n)?;
+        sorted.push(n);
+    }
+    sorted.sort_by(|a, b| a.compare(b).unwrap_or(Ordering::Equal));
+    Some(sorted)
+}
+
+pub fn median(args: &mut Vec<Number>) -> Number {
+    let mut sorted = match sorted(args) {
+        Some(sorted) => sorted,
+        None => return Number::NaN
+    };
+    let mid = sorted.len() / 2;
+    match sorted.len() {
+        0 => Number::NaN,
+        len if len % 2 == 1 => sorted.swap_remove(mid),
+        _ => (sorted[mid - 1].clone() + sorted[mid].clone()) / Number::Int(2)
+    }
+}
+
+/// Sample variance, divided by n - 1. Exact for exact arguments, so the
+/// config is not used.
+pub fn variance(args: &mut Vec<Number>, _: &Config) -> Number {
+    if args.len() < 2 || args.iter().any(|n| n.compare(n).is_none()) {
+        return Number::NaN;
+    }
+    let count = Number::Int(args.len() as i128 - 1);
+    let mean = mean(&mut args.clone());
+    let squares = args.drain(..).fold(Number::Int(0), |acc, x| {
+        let d = x - mean.clone();
+        acc + d.clone() * d
+    });
+    squares / count
+}
+
+/// Sample standard deviation
+pub fn stdev(args: &mut Vec<Number>, config: &Config) -> Number {
+    super::sqrt(&mut vec![variance(args, config)], config)
+}
+
+/// percentile(x, ..., p) for 0 <= p <= 100, interpolated between
+/// the closest values like PERCENTILE.INC of spreadsheets
+pub fn percentile(args: &mut Vec<Number>) -> Number {
+    let p = args.pop().unwrap_or_default();
+    let sorted = match sorted(args) {
+        Some(sorted) if !sorted.is_empty() && in_range(&p, 0, 100) => sorted,
+        _ => return Number::NaN
+    };
+    let rank = p * Number::Int(sorted.len() as i128 - 1) / Number::Int(100);
+    let lower = f64::from(rank.clone()).floor() as usize;
+    match sorted.get(lower + 1) {
+        Some(upper) => {
+            let frac = rank - Number::Int(lower as i128);
+            sorted[lower].clone() + (upper.clone() - sorted[lower].clone()) * frac
+        },
+        None => sorted[lower].clone()
+    }
+}
+
+fn in_range(n: &Number, min: i128, max: i128) -> bool {
+    matches!(n.compare(&Number::Int(min)), Some(Ordering::Greater | Ordering::Equal))
+        && matches!(n.compare(&Number::Int(max)), Some(Ordering::Less | Ordering::Equal))
+}
+
+fn real(n: Number) -> Option<f64> {
+    match n {
+        Number::NaN | Number::Complex(_) => None,
+        n => Some(n.into())
+    }
+}
+
+fn count(n: Number) -> Option<u64> {
+    n.to_bigint()?.to_u64().filter(|n| *n <= MAX_COUNT)
+}
+
+/// Complementary error function 1 - erf(x), accurate also
+/// far in the tail where erf(x) rounds to 1
+pub fn erfc(x: f64) -> f64 {
+    if x < 0.0 {
+        return 2.0 - erfc(-x);
+    }
+    if x < 2.0 {
+        // erf(x) = 2/sqrt(pi) e^(-x^2) sum of 2^n x^(2n+1) / (1*3*...*(2n+1)),
+        // terms are positive so nothing cancels out
+        let (mut term, mut sum, mut n) = (x, x, 0.0);
+        while term > sum * f64::EPSILON {
+            n += 1.0;
+            term *= 2.0 * x * x / (2.0 * n + 1.0);
+            sum += term;
+        }
+        return 1.0 - 2.0 / PI.sqrt() * (-x * x).exp() * sum;
+    }
+    // erfc(x) = e^(-x^2)/sqrt(pi) / (x + 1/2 / (x + 1 / (x + 3/2 / (x + ...))))
+    let mut f = x;
+    for k in (1..=ERFC_TERMS).rev() {
+        f = x + f64::from(k) / 2.0 / f;
+    }
+    (-x * x).exp() / PI.sqrt() / f
+}
+
+// x, mean and standard deviation of normal distribution functions,
+// the distribution is standard when they are omitted
+fn normal_args(args: &mut Vec<Number>) -> Option<(f64, f64, f64)> {
+    let mut args = args.drain(..).map(real);
+    let x = args.next()??;
+    let mu = args.next().unwrap_or(Some(0.0))?;
+    let sigma = args.next().unwrap_or(Some(1.0))?;
+    (sigma > 0.0).then_some((x, mu, sigma))
+}
+
+pub fn normpdf(args: &mut Vec<Number>) -> Number {
+    match normal_args(args) {
+        Some((x, mu, sigma)) => {
+            let z = (x - mu) / sigma;
+            ((-z * z / 2.0).exp() / (sigma * (2.0 * PI).sqrt())).into()
+        },
+        None => Number::NaN
+    }
+}
+
+/// Probability that a normally distributed value is at most x
+pub fn normcdf(args: &mut Vec<Number>) -> Number {
+    match normal_args(args) {
+        Some((x, mu, sigma)) => normal_cdf((x - mu) / sigma).into(),
+        None => Number::NaN
+    }
+}
+
+fn normal_cdf(z: f64) -> f64 {
+    erfc(-z / SQRT_2) / 2.0
+}
+
+/// x with normcdf(x) == p, for 0 < p < 1
+pub fn norminv(args: &mut Vec<Number>) -> Number {
+    match normal_args(args) {
+        Some((p, mu, sigma)) if p > 0.0 && p < 1.0 => (mu + sigma * probit(p)).into(),
+        _ => Number::NaN
+    }
+}
+
+// Acklam's approximation refined by a step of Halley's method
+fn probit(p: f64) -> f64 {
+    let poly = |coef: &[f64], x: f64| coef.iter().fold(0.0, |acc, c| acc * x + c);
+    let tail = |q: f64| {
+        let q = (-2.0 * q.ln()).sqrt();
+        poly(&ACKLAM_C, q) / (poly(&ACKLAM_D, q) * q + 1.0)
+    };
+    let x = match p {
+        p if p < 0.02425 => tail(p),
+        p if p > 1.0 - 0.02425 => -tail(1.0 - p),
+        p => {
+            let q = p - 0.5;
+            let r = q * q;
+            poly(&ACKLAM_A, r) * q / (poly(&ACKLAM_B, r) * r + 1.0)
+        }
+    };
+    let e = normal_cdf(x) - p;
+    let u = e * (2.0 * PI).sqrt() * (x * x / 2.0).exp();
+    x - u / (1.0 + x * u / 2.0)
+}
+
+// n, p and k of binomial distribution functions
+fn binomial_args(args: &mut Vec<Number>) -> Option<(u64, Number, u64)> {
+    let k = count(args.pop()?)?;
+    let p = args.pop()?;
+    let n = count(args.pop()?)?;
+    in_range(&p, 0, 1).then_some((n, p, k))
+}
+
+// a * ln(b) that is 0 for a == 0, so that 0^0 is 1
+fn xlny(a: u64, b: f64) -> f64 {
+    if a == 0 { 0.0 } else { a as f64 * b.ln() }
+}
+
+fn binomial(n: u64, p: &Number, k: u64) -> Number {
+    if k > n {
+        return Number::Int(0);
+    }
+    if n <= MAX_DIRECT_TRIALS {
+        let ways = integer::ncr(&mut vec![Number::Int(n.into()), Number::Int(k.into())]);
+        let q = Number::Int(1) - p.clone();
+        return ways * (p.clone() ^ Number::Int(k.into())) * (q ^ Number::Int((n - k).into()));
+    }
+    let p = f64::from(p.clone());
+    let ways = ln_gamma(n as f64 + 1.0) - ln_gamma(k as f64 + 1.0) - ln_gamma((n - k) as f64 + 1.0);
+    (ways + xlny(k, p) + xlny(n - k, 1.0 - p)).exp().into()
+}
+
+/// binompdf(n, p, k), probability of k successes in n trials
+pub fn binompdf(args: &mut Vec<Number>) -> Number {
+    match binomial_args(args) {
+        Some((n, p, k)) => binomial(n, &p, k),
+        None => Number::NaN
+    }
+}
+
+/// binomcdf(n, p, k), probability of at most k successes in n trials
+pub fn binomcdf(args: &mut Vec<Number>) -> Number {
+    match binomial_args(args) {
+        Some((n, p, k)) => (0..=k.min(n)).fold(Number::Int(0), |acc, i| acc + binomial(n, &p, i)),
+        None => Number::NaN
+    }
+}
+
+// mean and k of Poisson distribution functions
+fn poisson_args(args: &mut Vec<Number>) -> Option<(f64, u64)> {
+    let k = count(args.pop()?)?;
+    let lambda = real(args.pop()?)?;
+    (lambda > 0.0).then_some((lambda, k))
+}
+
+fn poisson(lambda: f64, k: u64) -> f64 {
+    (xlny(k, lambda) - lambda - ln_gamma(k as f64 + 1.0)).exp()
+}
+
+/// poissonpdf(mean, k), probability of exactly k events
+pub fn poissonpdf(args: &mut Vec<Number>) -> Number {
+    match poisson_args(args) {
+        Some((lambda, k)) => poisson(lambda, k).into(),
+        None => Number::NaN
+    }
+}
+
+/// poissoncdf(mean, k), probability of at most k events
+pub fn poissoncdf(args: &mut Vec<Number>) -> Number {
+    match poisson_args(args) {
+        Some((lambda, k)) => (0..=k).map(|i| poisson(lambda, i)).sum::<f64>().min(1.0).into(),
+        None => Number::NaN
+    }
+}
@@ -3,11 +3,13 @@ use super::*;
 #[test]
 fn test_function() {
     let f = parse_function("sin").unwrap();
-    let mut args = vec![Number::Float(std::f64::consts::PI)];
-    match f.exec(&mut args) {
-        Number::Float(f) => assert!(f.abs() < 1e-10),
+    let mut args = vec![Number::Float(1.0)];
+    match f.exec(&mut args, &Config::default()) {
+        Number::Float(f) => assert!((f - 0.8414709848078965).abs() < 1e-10),
         _ => panic!("Expected float")
     }
+    // pi is 180 degrees converted, so its sine is exact
+    assert_eq!(f.exec(&mut vec![Number::Float(std::f64::consts::PI)], &Config::default()), Number::Int(0));
 }
 
 #[test]
@@ -16,8 +18,256 @@ fn test_function2() {
     let mut args = vec![
         Number::Float(8.0), 
         Number::Float(2.0)];
-    match f.exec(&mut args) {
+    match f.exec(&mut args, &Config::default()) {
         Number::Float(f) => assert!((f-3.0).abs() < 1e-10),
         _ => panic!("Expected float")
     }
 }
+
+#[test]
+fn test_decimal_function() {
+    let config = Config { precision: Some(30), ..Default::default() };
+    let f = parse_function("sqrt").unwrap();
+    let mut args = vec![Number::Int(2)];
+    assert_eq!(f.exec(&mut args, &config).to_string(), "1.41421356237309504880168872421");
+
+    let f = parse_function("log").unwrap();
+    let mut args = vec![Number::Int(8), Number::Int(2)];
+    assert_eq!(f.exec(&mut args, &config).to_string(), "3");
+}
+
+fn call(name: &str, mut args: Vec<Number>) -> Number {
+    parse_function(name).unwrap().exec(&mut args, &Config::default())
+}
+
+#[test]
+fn test_complex_functions() {
+    use num_complex::Complex64;
+    let z = Number::Complex(Complex64::new(3.0, 4.0));
+    assert_eq!(call("sqrt", vec![Number::Int(-1)]), Number::Complex(Complex64::i()));
+    match call("ln", vec![Number::Int(-2)]) {
+        Number::Complex(c) => {
+            assert!((c.re - 2f64.ln()).abs() < 1e-10);
+            assert!((c.im - std::f64::consts::PI).abs() < 1e-10);
+        },
+        n => panic!("Expected complex, got {n}")
+    }
+    assert_eq!(call("abs", vec![z.clone()]), Number::Float(5.0));
+    assert_eq!(call("re", vec![z.clone()]), Number::Float(3.0));
+    assert_eq!(call("im", vec![z.clone()]), Number::Float(4.0));
+    assert_eq!(call("im", vec![Number::Int(3)]), Number::Int(0));
+    assert_eq!(call("conj", vec![z.clone()]), Number::Complex(Complex64::new(3.0, -4.0)));
+    assert_eq!(call("arg", vec![Number::Int(-1)]), Number::Float(std::f64::consts::PI));
+    assert!(matches!(call("sin", vec![z]), Number::Complex(_)));
+    assert_eq!(call("sqrt", vec![Number::Int(4)]), Number::Float(2.0));
+}
+
+#[test]
+fn test_registry() {
+    let rate = Number::Int(3);
+    let mut functions = FunctionRegistry::empty();
+    functions.register("triple", Arity::Exact(1), move |args| args[0].clone() * rate.clone());
+    functions.register("count", Arity::Variadic(0), |args| Number::Int(args.len() as i128));
+    assert!(functions.get("sin").is_none());
+
+    let mut args = vec![Number::Int(5)];
+    assert_eq!(functions.get("triple").unwrap().exec(&mut args, &Config::default()), Number::Int(15));
+    let count = functions.get("count").unwrap();
+    assert!(count.arity.accepts(0) && count.arity.accepts(4));
+    assert_eq!(count.exec(&mut vec![Number::Int(1), Number::Int(2)], &Config::default()), Number::Int(2));
+
+    assert!(FunctionRegistry::new().contains("sin"));
+    assert!(FunctionRegistry::new().contains("len"));
+}
+
+#[test]
+fn test_value_functions() {
+    let mut functions = FunctionRegistry::empty();
+    functions.register_values("first", Arity::Exact(1), |args, _| match args.pop() {
+        Some(Value::List(items)) => items.into_iter().next().ok_or_else(|| String::from("Empty list")),
+        _ => Err(String::from("Expected list"))
+    });
+    let first = functions.get("first").unwrap();
+    assert!(first.takes_values());
+    let mut args = vec![Value::List(vec![Value::Number(Number::Int(7))])];
+    assert_eq!(first.exec_values(&mut args, &Config::default()), Ok(Value::Number(Number::Int(7))));
+    assert_eq!(first.exec(&mut vec![Number::Int(7)], &Config::default()), Number::NaN);
+
+    let sqrt = parse_function("sqrt").unwrap();
+    assert_eq!(sqrt.exec_values(&mut vec![Value::Number(Number::Int(4))], &Config::default()), Ok(Value::Number(Number::Float(2.0))));
+    let err = sqrt.exec_values(&mut vec![Value::Str(String::from("a"))], &Config::default());
+    assert_eq!(err, Err(String::from("Expected number, got \"a\"")));
+}
+
+#[test]
+fn test_variadic_functions() {
+    let nums = |v: &[i128]| v.iter().map(|i| Number::Int(*i)).collect::<Vec<_>>();
+    assert_eq!(call("max", nums(&[1, 5, 3, 9])), Number::Int(9));
+    assert_eq!(call("min", nums(&[4, -2, 7])), Number::Int(-2));
+    assert_eq!(call("min", vec![Number::Int(1), Number::Float(0.5)]), Number::Float(0.5));
+    assert_eq!(call("sum", nums(&[1, 2, 3, 4])), Number::Int(10));
+    assert_eq!(call("avg", nums(&[1, 2])).to_string(), "3/2");
+    assert_eq!(call("mean", nums(&[2, 4, 6])), Number::Int(4));
+    assert_eq!(call("median", nums(&[5, 1, 3])), Number::Int(3));
+    assert_eq!(call("median", nums(&[4, 1, 3, 2])).to_string(), "5/2");
+    assert_eq!(call("gcd", nums(&[12, -18, 30])), Number::Int(6));
+    assert_eq!(call("lcm", nums(&[4, 6, 10])), Number::Int(60));
+    assert_eq!(call("gcd", vec![Number::Int(4), Number::Float(2.5)]), Number::NaN);
+    assert_eq!(call("max", vec![Number::Int(1), Number::NaN]), Number::NaN);
+}
+
+#[test]
+fn test_integer_functions() {
+    let nums = |v: &[i128]| v.iter().map(|i| Number::Int(*i)).collect::<Vec<_>>();
+    assert_eq!(call("nCr", nums(&[5, 2])), Number::Int(10));
+    assert_eq!(call("nCr", nums(&[5, 6])), Number::NaN);
+    assert_eq!(call("nPr", nums(&[5, 2])), Number::Int(20));
+    assert_eq!(call("nPr", nums(&[5, 0])), Number::Int(1));
+    // 2^100 choose 2 overflows i128
+    let big = Number::Int(1 << 100);
+    assert_eq!(call("nCr", vec![big, Number::Int(2)]).to_string(), "803469022129495137770981046169947475960987382190648066048000");
+    assert_eq!(call("nCr", vec![Number::Float(5.5), Number::Int(2)]), Number::NaN);
+
+    assert_eq!(call("isprime", nums(&[97])), Number::Int(1));
+    assert_eq!(call("isprime", nums(&[1])), Number::Int(0));
+    assert_eq!(call("isprime", nums(&[561])), Number::Int(0));
+    assert_eq!(call("isprime", nums(&[(1 << 61) - 1])), Number::Int(1));
+    assert_eq!(call("isprime", nums(&[((1 << 61) - 1) * 3])), Number::Int(0));
+
+    assert_eq!(call("mod_pow", nums(&[2, 10, 1000])), Number::Int(24));
+    assert_eq!(call("mod_pow", nums(&[-2, 3, 5])), Number::Int(2));
+    assert_eq!(call("mod_pow", nums(&[3, -1, 7])), Number::Int(5));
+    assert_eq!(call("mod_pow", nums(&[2, -1, 4])), Number::NaN);
+    assert_eq!(call("mod_pow", nums(&[2, 3, 0])), Number::NaN);
+}
+
+#[test]
+fn test_factorial() {
+    use super::integer::factorial;
+    assert_eq!(factorial(&Number::Int(0)), Number::Int(1));
+    assert_eq!(factorial(&Number::Int(20)), Number::Int(2432902008176640000));
+    assert_eq!(factorial(&Number::Int(35)).to_string(), "10333147966386144929666651337523200000000");
+    assert_eq!(factorial(&Number::Int(-1)), Number::NaN);
+    assert_eq!(factorial(&Number::Int(1_000_000)), Number::NaN);
+    match factorial(&Number::Float(0.5)) {
+        Number::Float(f) => assert!((f - std::f64::consts::PI.sqrt() / 2.0).abs() < 1e-14),
+        n => panic!("Expected float, got {n}")
+    }
+    assert!((gamma(5.0) - 24.0).abs() < 1e-12);
+    assert!((gamma(-0.5) + 2.0 * std::f64::consts::PI.sqrt()).abs() < 1e-12);
+    assert!(gamma(-2.0).is_nan());
+    assert_eq!(gamma(172.0), f64::INFINITY);
+}
+
+#[test]
+fn test_factor() {
+    use super::integer::factor;
+    use num_bigint::BigInt;
+    let factors = |n: i128| factor(&BigInt::from(n)).unwrap().into_iter()
+        .map(|(p, power)| (p.to_string(), power))
+        .collect::<Vec<_>>();
+    let pairs = |v: &[(&str, u32)]| v.iter().map(|(p, power)| (p.to_string(), *power)).collect::<Vec<_>>();
+    assert_eq!(factors(360), pairs(&[("2", 3), ("3", 2), ("5", 1)]));
+    assert_eq!(factors(1), pairs(&[]));
+    assert_eq!(factors(600851475143), pairs(&[("71", 1), ("839", 1), ("1471", 1), ("6857", 1)]));
+    // two primes above the range of trial division
+    assert_eq!(factors(1000003 * 998244353), pairs(&[("1000003", 1), ("998244353", 1)]));
+    assert_eq!(factors((1 << 61) - 1), pairs(&[("2305843009213693951", 1)]));
+}
+
+#[test]
+fn test_statistics() {
+    let nums = |v: &[i128]| v.iter().map(|i| Number::Int(*i)).collect::<Vec<_>>();
+    let data = nums(&[2, 4, 4, 4, 5, 5, 7, 9]);
+    assert_eq!(call("variance", data.clone()).to_string(), "32/7");
+    assert_eq!(call("stdev", data.clone()), Number::Float(2.138089935299395));
+    assert_eq!(call("mean", data.clone()), Number::Int(5));
+    assert_eq!(call("median", data).to_string(), "9/2");
+    assert_eq!(call("variance", vec![Number::Int(1), Number::Float(2.5)]), Number::Float(1.125));
+    // the same as PERCENTILE.INC in spreadsheets
+    assert_eq!(call("percentile", nums(&[4, 1, 3, 2, 25])).to_string(), "7/4");
+    assert_eq!(call("percentile", nums(&[15, 20, 35, 40, 50, 90])), Number::Int(46));
+    assert_eq!(call("percentile", nums(&[3, 1, 100])), Number::Int(3));
+    assert_eq!(call("percentile", nums(&[3, 1, 0])), Number::Int(1));
+    assert_eq!(call("percentile", nums(&[3, 1, 101])), Number::NaN);
+    assert_eq!(call("variance", vec![Number::Int(1), Number::NaN]), Number::NaN);
+}
+
+#[test]
+fn test_distributions() {
+    let approx = |name: &str, args: &[f64], expected: f64| {
+        // whole numbers are passed as integers, like counts of binompdf
+        let number = |x: f64| if x.fract() == 0.0 { Number::Int(x as i128) } else { Number::Float(x) };
+        match call(name, args.iter().map(|x| number(*x)).collect()) {
+            Number::Float(f) => assert!((f - expected).abs() <= 1e-13 * expected.abs(), "{name}{args:?} = {f}, expected {expected}"),
+            n => panic!("Expected float, got {n}")
+        }
+    };
+    // reference values computed with arbitrary precision
+    approx("normcdf", &[1.96], 0.9750021048517795);
+    approx("normcdf", &[-1.0], 0.15865525393145705);
+    approx("normcdf", &[-10.0], 7.619853024160525e-24);
+    approx("normcdf", &[110.0, 100.0, 15.0], 0.7475074624530771);
+    approx("normpdf", &[0.0], 0.3989422804014327);
+    approx("normpdf", &[1.0, 0.0, 2.0], 0.17603266338214976);
+    approx("norminv", &[0.975], 1.9599639845400542);
+    approx("norminv", &[0.3], -0.5244005127080407);
+    approx("norminv", &[1e-10], -6.361340902404056);
+    approx("norminv", &[0.5, 10.0, 2.0], 10.0);
+    approx("binompdf", &[10.0, 0.3, 3.0], 0.266827932);
+    approx("binomcdf", &[10.0, 0.3, 3.0], 0.6496107184);
+    approx("binompdf", &[1000.0, 0.5, 500.0], 0.025225018178360804);
+    approx("poissonpdf", &[3.0, 2.0], 0.22404180765538773);
+    approx("poissoncdf", &[3.0, 2.0], 0.42319008112684353);
+    approx("poissoncdf", &[1000.0, 1000.0], 0.5084093671684426);
+
+    let half = Number::Int(1) / Number::Int(2);
+    let ints = |n: i128, k: i128| vec![Number::Int(n), half.clone(), Number::Int(k)];
+    assert_eq!(call("binompdf", ints(10, 5)).to_string(), "63/256");
+    assert_eq!(call("binomcdf", ints(4, 1)).to_string(), "5/16");
+    assert_eq!(call("binompdf", ints(4, 5)), Number::Int(0));
+    assert_eq!(call("binompdf", vec![Number::Int(4), Number::Int(2), Number::Int(1)]), Number::NaN);
+    assert_eq!(call("norminv", vec![Number::Int(1)]), Number::NaN);
+    assert_eq!(call("normcdf", vec![Number::Int(0), Number::Int(0), Number::Int(-1)]), Number::NaN);
+    assert_eq!(call("poissoncdf", vec![Number::Int(0), Number::Int(1)]), Number::NaN);
+}
+
+#[test]
+fn test_angle() {
+    use angle::Angle;
+    assert_eq!(Angle::parse("°"), Some(Angle::Deg));
+    assert_eq!(Angle::parse("turn"), None);
+    assert_eq!(Angle::Deg.convert(Number::Int(90), Angle::Grad, None), Number::Int(100));
+    assert_eq!(Angle::Rad.convert(Number::Float(std::f64::consts::PI), Angle::Deg, None), Number::Float(180.0));
+    assert_eq!(call("deg", vec![Number::Float(std::f64::consts::PI)]), Number::Float(180.0));
+    assert_eq!(call("rad", vec![Number::Int(180)]), Number::Float(std::f64::consts::PI));
+
+    let call_in = |angle: Angle, name: &str, arg: Number| {
+        parse_function(name).unwrap().exec(&mut vec![arg], &Config { angle, ..Default::default() })
+    };
+    let half = Number::Int(1) / Number::Int(2);
+    assert_eq!(call_in(Angle::Deg, "sin", Number::Int(30)), half);
+    assert_eq!(call_in(Angle::Deg, "sin", Number::Int(-390)), -half.clone());
+    assert_eq!(call_in(Angle::Deg, "cos", Number::Int(90)), Number::Int(0));
+    assert_eq!(call_in(Angle::Deg, "tan", Number::Int(225)), Number::Int(1));
+    assert_eq!(call_in(Angle::Deg, "tan", Number::Int(90)), Number::NaN);
+    assert_eq!(call_in(Angle::Deg, "asin", Number::Float(0.5)), Number::Int(30));
+    assert_eq!(call_in(Angle::Deg, "acos", Number::Int(-1)), Number::Int(180));
+    assert_eq!(call_in(Angle::Deg, "atan", Number::Int(-1)), Number::Int(-45));
+    match call_in(Angle::Deg, "sin", Number::Int(10)) {
+        Number::Float(f) => assert!((f - 0.17364817766693033).abs() < 1e-12),
+        n => panic!("Expected float, got {n}")
+    }
+    // conversions do not depend on the mode
+    assert_eq!(call_in(Angle::Deg, "rad", Number::Int(180)), Number::Float(std::f64::consts::PI));
+
+    assert_eq!(call_in(Angle::Grad, "sin", Number::Int(100)), Number::Int(1));
+    assert_eq!(call_in(Angle::Grad, "asin", Number::Int(1)), Number::Int(100));
+
+    // degrees converted to radians are still common angles
+    let radians = |degrees: i128| Angle::Deg.convert(Number::Int(degrees), Angle::Rad, None);
+    assert_eq!(call("sin", vec![radians(30)]), half);
+    assert_eq!(call("cos", vec![radians(-240)]), -half);
+    assert_eq!(call("tan", vec![radians(405)]), Number::Int(1));
+    assert!(matches!(call("sin", vec![Number::Float(0.5)]), Number::Float(_)));
+}
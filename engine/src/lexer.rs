@@ -52,10 +52,48 @@ pub enum TokenKind {
     Lpar,
     /// )
     Rpar,
+    /// [
+    Lbracket,
+    /// ]
+    Rbracket,
     /// ,
     Coma,
     /// =
     Assign,
+    /// <
+    Lt,
+    /// <=
+    Le,
+    /// >
+    Gt,
+    /// >=
+    Ge,
+    /// ==
+    Eq,
+    /// !=
+    Ne,
+    /// &&
+    And,
+    /// ||
+    Or,
+    /// !
+    Not,
+    /// &
+    BitAnd,
+    /// |
+    BitOr,
+    /// xor
+    Xor,
+    /// ~
+    BitNot,
+    /// °
+    Degree,
+    /// <<
+    Shl,
+    /// >>
+    Shr,
+    /// >>>
+    Ushr,
     /// not recognized
     Unknown,
     /// end of input
@@ -66,7 +104,13 @@ pub enum TokenKind {
 pub enum LiteralKind {
     Int(Base),
     Float,
+    /// 4i, 2.5i
+    Imaginary,
     String,
+    /// 2026-10-18, 2026-10-18T09:10
+    Date,
+    /// 09:10, 17:45:30
+    Time
 }
 
 #[derive(Clone, Copy, PartialEq, Debug)]
@@ -100,6 +144,33 @@ fn is_ident(c: char) -> bool {
     c.is_alphanumeric() || c == '_'
 }
 
+fn digits(s: &str, count: usize) -> bool {
+    s.len() >= count && s.as_bytes()[..count].iter().all(u8::is_ascii_digit)
+}
+
+// length of yyyy-mm-dd at the start of the input
+fn date_len(s: &str) -> Option<usize> {
+    let b = s.as_bytes();
+    let valid = digits(s, 4) && b.get(4) == Some(&b'-') && digits(&s[5..], 2)
+        && b.get(7) == Some(&b'-') && digits(&s[8..], 2) && !digits(&s[10..], 1);
+    valid.then_some(10)
+}
+
+// length of h:mm, hh:mm or hh:mm:ss at the start of the input
+fn time_len(s: &str) -> Option<usize> {
+    let hours = if digits(s, 2) { 2 } else if digits(s, 1) { 1 } else { return None };
+    let mut len = hours;
+    for _ in 0..2 {
+        let rest = &s[len..];
+        if !rest.starts_with(':') || !digits(&rest[1..], 2) {
+            break;
+        }
+        len += 3;
+    }
+    (len > hours && !digits(&s[len..], 1)).then_some(len)
+}
+
+#[derive(Clone)]
 pub struct Tokenizer<'a> {
     chars: Chars<'a>,
     len_total: usize,
@@ -119,6 +190,10 @@ impl Tokenizer<'_> {
         self.chars.clone().next().unwrap_or(EOF_CHAR)
     }
 
+    fn second(&self) -> char {
+        self.chars.clone().nth(1).unwrap_or(EOF_CHAR)
+    }
+
     fn bump(&mut self) -> Option<char> {
         self.chars.next()
     }
@@ -140,7 +215,7 @@ impl Tokenizer<'_> {
         let char = self.first();
         let token_kind = match char {
             '0'..='9' | '.' => {
-                let number_kind: LiteralKind = self.do_number();
+                let number_kind: LiteralKind = self.do_date().unwrap_or_else(|| self.do_number());
                 TokenKind::Literal(number_kind)
             },
             '"' => {
@@ -148,8 +223,12 @@ impl Tokenizer<'_> {
                 TokenKind::Literal(literal_kind)
             }
             c if is_ident_start(c) => {
+                let rest = self.chars.as_str();
                 self.do_while(is_ident);
-                TokenKind::Ident
+                match &rest[..rest.len() - self.chars.as_str().len()] {
+                    "xor" => TokenKind::Xor,
+                    _ => TokenKind::Ident
+                }
             }
             '+' => { self.bump(); TokenKind::Add }
             '-' => { self.bump(); TokenKind::Sub },
@@ -159,8 +238,23 @@ impl Tokenizer<'_> {
             '%' => { self.bump(); TokenKind::Mod },
             '(' => { self.bump(); TokenKind::Lpar },
             ')' => { self.bump(); TokenKind::Rpar },
+            '[' => { self.bump(); TokenKind::Lbracket },
+            ']' => { self.bump(); TokenKind::Rbracket },
             ',' => { self.bump(); TokenKind::Coma },
-            '=' => { self.bump(); TokenKind::Assign },
+            '=' => { self.bump(); self.followed_by('=', TokenKind::Eq, TokenKind::Assign) },
+            '<' if self.second() == '<' => { self.bump(); self.bump(); TokenKind::Shl },
+            '<' => { self.bump(); self.followed_by('=', TokenKind::Le, TokenKind::Lt) },
+            '>' if self.second() == '>' => {
+                self.bump();
+                self.bump();
+                self.followed_by('>', TokenKind::Ushr, TokenKind::Shr)
+            },
+            '>' => { self.bump(); self.followed_by('=', TokenKind::Ge, TokenKind::Gt) },
+            '!' => { self.bump(); self.followed_by('=', TokenKind::Ne, TokenKind::Not) },
+            '&' => { self.bump(); self.followed_by('&', TokenKind::And, TokenKind::BitAnd) },
+            '|' => { self.bump(); self.followed_by('|', TokenKind::Or, TokenKind::BitOr) },
+            '~' => { self.bump(); TokenKind::BitNot },
+            '°' => { self.bump(); TokenKind::Degree },
             EOF_CHAR => TokenKind::Eof,
             _ => TokenKind::Unknown
         };
@@ -170,6 +264,16 @@ impl Tokenizer<'_> {
         token
     }
 
+    // two character operators, like <= or &&
+    fn followed_by(&mut self, c: char, long: TokenKind, short: TokenKind) -> TokenKind {
+        if self.first() == c {
+            self.bump();
+            long
+        } else {
+            short
+        }
+    }
+
     fn do_number(&mut self) -> LiteralKind {
         let mut kind = LiteralKind::Int(Base::Dec);
         if self.first() == '0' {
@@ -200,11 +304,17 @@ impl Tokenizer<'_> {
             self.bump();
             self.do_while(is_digit);
         }
-        if matches!(self.first(), 'e' | 'E') {
+        // e starts an exponent, unless it starts a word like EUR
+        if matches!(self.first(), 'e' | 'E') && !self.second().is_alphabetic() {
             kind = LiteralKind::Float;
             self.bump();
             self.do_exp();
         }
+        // i suffix makes number imaginary, unless it starts an identifier
+        if self.first() == 'i' && !is_ident(self.second()) {
+            kind = LiteralKind::Imaginary;
+            self.bump();
+        }
     kind
     }
 
@@ -216,6 +326,22 @@ impl Tokenizer<'_> {
         self.do_while(is_digit);
     }
 
+    // Dates and times are written without spaces, 2026 - 10 - 18 is a subtraction
+    fn do_date(&mut self) -> Option<LiteralKind> {
+        let rest = self.chars.as_str();
+        let (kind, len) = match date_len(rest) {
+            Some(len) if rest[len..].starts_with('T') => {
+                (LiteralKind::Date, len + time_len(&rest[len + 1..]).map_or(0, |time| time + 1))
+            },
+            Some(len) => (LiteralKind::Date, len),
+            None => (LiteralKind::Time, time_len(rest)?)
+        };
+        for _ in 0..len {
+            self.bump();
+        }
+        Some(kind)
+    }
+
     fn do_string(&mut self) -> LiteralKind {
         self.bump(); // advance past opening '"'
         loop {
@@ -229,6 +355,8 @@ impl Tokenizer<'_> {
                     // skip over whatever is escaped
                     self.bump();
                 },
+                // unterminated string ends with the input
+                EOF_CHAR if self.chars.as_str().is_empty() => break,
                 _ => ()
             }
             self.bump();
@@ -14,7 +14,7 @@ fn tokenize(input: &str) -> impl Iterator<Item = Token> + '_ {
 fn test_tokenizer_loop() {
     let input = "123+0x4f";
 
-    let mut tokenizer = tokenize(&input);
+    let mut tokenizer = tokenize(input);
 
     let token = tokenizer.next().unwrap();
 
@@ -70,6 +70,16 @@ fn test_exp_float() {
     assert_eq!(input, token.value_from(input));
 }
 
+#[test]
+fn test_word_after_number() {
+    let input = "5 EUR";
+    let tokens: Vec<Token> = tokenize(input).collect();
+    assert_eq!(tokens, vec![
+        Token::new(TokenKind::Literal(LiteralKind::Int(Base::Dec)), 0, 2),
+        Token::new(TokenKind::Ident, 2, 3)
+    ]);
+}
+
 #[test]
 fn test_ident() {
     let input = "a0_b";
@@ -84,6 +94,26 @@ fn test_string() {
     let token = tokenize(input).next().unwrap();
     assert_eq!(TokenKind::Literal(LiteralKind::String), token.kind);
     assert_eq!(input, token.value_from(input));
+
+    let input = "\"ab";
+    let tokens: Vec<Token> = tokenize(input).collect();
+    assert_eq!(tokens, vec![Token::new(TokenKind::Literal(LiteralKind::String), 0, 3)]);
+}
+
+#[test]
+fn test_date() {
+    let kinds = |input: &str| tokenize(input).map(|t| (t.kind, t.len)).collect::<Vec<_>>();
+    assert_eq!(kinds("2026-10-18"), vec![(TokenKind::Literal(LiteralKind::Date), 10)]);
+    assert_eq!(kinds("2026-10-18T09:10:30"), vec![(TokenKind::Literal(LiteralKind::Date), 19)]);
+    assert_eq!(kinds("9:10-17:45"), vec![
+        (TokenKind::Literal(LiteralKind::Time), 4),
+        (TokenKind::Sub, 1),
+        (TokenKind::Literal(LiteralKind::Time), 5)
+    ]);
+    assert_eq!(kinds("2026 - 10")[0], (TokenKind::Literal(LiteralKind::Int(Base::Dec)), 5));
+    assert_eq!(kinds("2026-10-180")[0], (TokenKind::Literal(LiteralKind::Int(Base::Dec)), 4));
+    let token = tokenize("12:3").next().unwrap();
+    assert_eq!((token.kind, token.len), (TokenKind::Literal(LiteralKind::Int(Base::Dec)), 2));
 }
 
 #[test]
@@ -96,4 +126,72 @@ fn test_eating_whitespaces() {
         Token::new(TokenKind::Literal(LiteralKind::Int(Base::Dec)), 4, 3),
     ];
     assert_eq!(expected, tokens);
-}
\ No newline at end of file
+}
+
+#[test]
+fn test_imaginary() {
+    let input = "3+4.5i";
+    let tokens: Vec<Token> = tokenize(input).collect();
+    let expected = vec![
+        Token::new(TokenKind::Literal(LiteralKind::Int(Base::Dec)), 0, 1),
+        Token::new(TokenKind::Add, 1, 1),
+        Token::new(TokenKind::Literal(LiteralKind::Imaginary), 2, 4),
+    ];
+    assert_eq!(expected, tokens);
+
+    let input = "2in";
+    let tokens: Vec<Token> = tokenize(input).collect();
+    let expected = vec![
+        Token::new(TokenKind::Literal(LiteralKind::Int(Base::Dec)), 0, 1),
+        Token::new(TokenKind::Ident, 1, 2),
+    ];
+    assert_eq!(expected, tokens);
+}
+
+#[test]
+fn test_comparison() {
+    let input = "a<=b == !c&&d||e != f<g>=h";
+    let kinds: Vec<TokenKind> = tokenize(input).map(|t| t.kind).filter(|k| *k != TokenKind::Ident).collect();
+    let expected = vec![
+        TokenKind::Le, TokenKind::Eq, TokenKind::Not, TokenKind::And,
+        TokenKind::Or, TokenKind::Ne, TokenKind::Lt, TokenKind::Ge
+    ];
+    assert_eq!(expected, kinds);
+
+    let input = "x = 1";
+    let kinds: Vec<TokenKind> = tokenize(input).map(|t| t.kind).collect();
+    assert_eq!(kinds[1], TokenKind::Assign);
+}
+
+#[test]
+fn test_bitwise() {
+    let input = "a&b|c xor d<<e>>f>>>~g && xored";
+    let kinds: Vec<TokenKind> = tokenize(input).map(|t| t.kind).filter(|k| *k != TokenKind::Ident).collect();
+    let expected = vec![
+        TokenKind::BitAnd, TokenKind::BitOr, TokenKind::Xor, TokenKind::Shl,
+        TokenKind::Shr, TokenKind::Ushr, TokenKind::BitNot, TokenKind::And
+    ];
+    assert_eq!(expected, kinds);
+}
+
+#[test]
+fn test_brackets() {
+    let input = "[1, x][0]";
+    let kinds: Vec<TokenKind> = tokenize(input).map(|t| t.kind).collect();
+    let expected = vec![
+        TokenKind::Lbracket, TokenKind::Literal(LiteralKind::Int(Base::Dec)), TokenKind::Coma,
+        TokenKind::Ident, TokenKind::Rbracket, TokenKind::Lbracket,
+        TokenKind::Literal(LiteralKind::Int(Base::Dec)), TokenKind::Rbracket
+    ];
+    assert_eq!(expected, kinds);
+}
+
+#[test]
+fn test_degree_sign() {
+    let input = "30°";
+    let tokens: Vec<Token> = tokenize(input).collect();
+    assert_eq!(tokens, vec![
+        Token::new(TokenKind::Literal(LiteralKind::Int(Base::Dec)), 0, 2),
+        Token::new(TokenKind::Degree, 2, 2)
+    ]);
+}
@@ -1,10 +1,18 @@
 mod lexer;
 pub mod eval;
+pub mod ast;
 pub mod parser;
 pub mod errors;
 pub mod number;
 pub mod function;
+pub mod unit;
+pub mod symbolic;
+pub mod numeric;
+pub mod matrix;
+pub mod date;
+pub mod config;
 
+pub use config::Config;
 pub use errors::Error;
 pub use parser::Parser;
-pub use eval::Calculator;
\ No newline at end of file
+pub use eval::{Calculator, Value};
\ No newline at end of file
@@ -0,0 +1,332 @@
+use crate::number::Number;
+use num_complex::Complex64;
+use std::fmt::Display;
+
+#[cfg(test)]
+mod tests;
+
+// elements of inexact matrices this much smaller than the largest one
+// are taken as zero when looking for pivots
+const EPSILON: f64 = 1e-12;
+
+// iterations of Durand-Kerner method before eigenvalues are accepted as they are
+const MAX_ROOT_STEPS: usize = 1000;
+
+/// Matrix of numbers, stored row by row
+#[derive(Clone, Debug, PartialEq)]
+pub struct Matrix {
+    pub rows: usize,
+    pub cols: usize,
+    data: Vec<Number>
+}
+
+impl Matrix {
+    /// None unless there are rows * cols elements and both are positive
+    pub fn new(rows: usize, cols: usize, data: Vec<Number>) -> Option<Matrix> {
+        (rows > 0 && cols > 0 && data.len() == rows * cols).then_some(Matrix { rows, cols, data })
+    }
+
+    pub fn zeros(rows: usize, cols: usize) -> Matrix {
+        Matrix { rows, cols, data: vec![Number::Int(0); rows * cols] }
+    }
+
+    pub fn identity(n: usize) -> Matrix {
+        let mut m = Matrix::zeros(n, n);
+        for i in 0..n {
+            m.data[i * n + i] = Number::Int(1);
+        }
+        m
+    }
+
+    pub fn get(&self, i: usize, j: usize) -> &Number {
+        &self.data[i * self.cols + j]
+    }
+
+    fn set(&mut self, i: usize, j: usize, n: Number) {
+        self.data[i * self.cols + j] = n;
+    }
+
+    pub fn row(&self, i: usize) -> &[Number] {
+        &self.data[i * self.cols..(i + 1) * self.cols]
+    }
+
+    pub fn elements(&self) -> &[Number] {
+        &self.data
+    }
+
+    /// Applies the function to each element
+    pub fn map<F>(self, f: F) -> Matrix
+        where F: FnMut(Number) -> Number
+    {
+        Matrix { data: self.data.into_iter().map(f).collect(), ..self }
+    }
+
+    /// Size like 2x3, rows first
+    pub fn size(&self) -> String {
+        format!("{}x{}", self.rows, self.cols)
+    }
+
+    fn check_square(&self) -> Result<usize, String> {
+        if self.rows != self.cols {
+            return Err(format!("Matrix must be square, got {}", self.size()));
+        }
+        Ok(self.rows)
+    }
+
+    pub fn transpose(&self) -> Matrix {
+        let mut res = Matrix::zeros(self.cols, self.rows);
+        for i in 0..self.rows {
+            for j in 0..self.cols {
+                res.set(j, i, self.get(i, j).clone());
+            }
+        }
+        res
+    }
+
+    pub fn mul(&self, rhs: &Matrix) -> Result<Matrix, String> {
+        if self.cols != rhs.rows {
+            return Err(format!("Cannot multiply {} matrix by {} matrix", self.size(), rhs.size()));
+        }
+        let mut res = Matrix::zeros(self.rows, rhs.cols);
+        for i in 0..self.rows {
+            for j in 0..rhs.cols {
+                let dot = (0..self.cols).fold(Number::Int(0), |acc, k| {
+                    acc + self.get(i, k).clone() * rhs.get(k, j).clone()
+                });
+                res.set(i, j, dot);
+            }
+        }
+        Ok(res)
+    }
+
+    /// Integer power by repeated squaring, negative powers of the inverse
+    pub fn pow(&self, n: i128) -> Result<Matrix, String> {
+        let size = self.check_square()?;
+        let mut base = if n < 0 { self.inv()? } else { self.clone() };
+        let mut res = Matrix::identity(size);
+        let mut n = n.unsigned_abs();
+        while n > 0 {
+            if n % 2 == 1 {
+                res = res.mul(&base)?;
+            }
+            n /= 2;
+            if n > 0 {
+                base = base.mul(&base)?;
+            }
+        }
+        Ok(res)
+    }
+
+    pub fn det(&self) -> Result<Number, String> {
+        let size = self.check_square()?;
+        let mut m = self.clone();
+        let (pivots, odd) = m.eliminate();
+        if pivots.len() < size {
+            return Ok(Number::Int(0));
+        }
+        let det = (0..size).fold(Number::Int(1), |acc, i| acc * m.get(i, i).clone());
+        Ok(if odd { -det } else { det })
+    }
+
+    pub fn inv(&self) -> Result<Matrix, String> {
+        let size = self.check_square()?;
+        self.solve(&Matrix::identity(size))
+    }
+
+    /// Number of linearly independent rows
+    pub fn rank(&self) -> usize {
+        self.clone().eliminate().0.len()
+    }
+
+    /// x with self * x == b, for a square matrix and b of the same number of rows
+    pub fn solve(&self, b: &Matrix) -> Result<Matrix, String> {
+        let size = self.check_square()?;
+        if b.rows != size {
+            return Err(format!("Cannot solve {} system with {} right side", self.size(), b.size()));
+        }
+        // augmented matrix [self | b]
+        let cols = size + b.cols;
+        let mut m = Matrix::zeros(size, cols);
+        for i in 0..size {
+            for j in 0..cols {
+                let n = if j < size { self.get(i, j) } else { b.get(i, j - size) };
+                m.set(i, j, n.clone());
+            }
+        }
+        let (pivots, _) = m.eliminate();
+        if pivots.iter().filter(|col| **col < size).count() < size {
+            return Err(String::from("Matrix is singular"));
+        }
+        // back substitution, from the last row up
+        let mut x = Matrix::zeros(size, b.cols);
+        for i in (0..size).rev() {
+            for j in 0..b.cols {
+                let known = (i + 1..size).fold(Number::Int(0), |acc, k| {
+                    acc + m.get(i, k).clone() * x.get(k, j).clone()
+                });
+                x.set(i, j, (m.get(i, size + j).clone() - known) / m.get(i, i).clone());
+            }
+        }
+        Ok(x)
+    }
+
+    // Row echelon form by Gaussian elimination. Returns pivot columns
+    // and whether rows were swapped an odd number of times.
+    fn eliminate(&mut self) -> (Vec<usize>, bool) {
+        let scale = self.data.iter().map(magnitude).fold(0.0, f64::max);
+        let mut pivots = vec![];
+        let mut odd = false;
+        for col in 0..self.cols {
+            let r = pivots.len();
+            if r == self.rows {
+                break;
+            }
+            // the largest pivot loses the least precision
+            let best = (r..self.rows)
+                .max_by(|a, b| magnitude(self.get(*a, col)).total_cmp(&magnitude(self.get(*b, col))))
+                .unwrap_or(r);
+            if is_negligible(self.get(best, col), scale) {
+                continue;
+            }
+            if best != r {
+                for j in 0..self.cols {
+                    self.data.swap(best * self.cols + j, r * self.cols + j);
+                }
+                odd = !odd;
+            }
+            for i in r + 1..self.rows {
+                let f = self.get(i, col).clone() / self.get(r, col).clone();
+                if f.is_zero() {
+                    continue;
+                }
+                for j in col + 1..self.cols {
+                    let n = self.get(i, j).clone() - f.clone() * self.get(r, j).clone();
+                    self.set(i, j, n);
+                }
+                self.set(i, col, Number::Int(0));
+            }
+            pivots.push(col);
+        }
+        (pivots, odd)
+    }
+
+    /// Eigenvalues, real ones in ascending order followed by complex ones
+    pub fn eigenvalues(&self) -> Result<Vec<Number>, String> {
+        let size = self.check_square()?;
+        let upper = (0..size).all(|i| (0..i).all(|j| self.get(i, j).is_zero()));
+        let lower = (0..size).all(|i| (i + 1..size).all(|j| self.get(i, j).is_zero()));
+        let mut values: Vec<Number> = if upper || lower {
+            (0..size).map(|i| self.get(i, i).clone()).collect()
+        } else {
+            let poly = self.characteristic();
+            roots(&poly).into_iter().map(|z| exact_root(&poly, z)).collect()
+        };
+        values.sort_by(|a, b| match (a, b) {
+            (Number::Complex(a), Number::Complex(b)) => a.re.total_cmp(&b.re).then(a.im.total_cmp(&b.im)),
+            (Number::Complex(_), _) => std::cmp::Ordering::Greater,
+            (_, Number::Complex(_)) => std::cmp::Ordering::Less,
+            (a, b) => a.compare(b).unwrap_or(std::cmp::Ordering::Equal)
+        });
+        Ok(values)
+    }
+
+    // Coefficients of det(xI - A) from the constant term up, computed
+    // by Faddeev-LeVerrier method, exact for exact matrices
+    fn characteristic(&self) -> Vec<Number> {
+        let n = self.rows;
+        let mut coef = vec![Number::Int(0); n + 1];
+        coef[n] = Number::Int(1);
+        let mut m = Matrix::zeros(n, n);
+        for k in 1..=n {
+            // M_k = A M_(k-1) + c_(n-k+1) I
+            m = self.mul(&m).expect("square matrix");
+            for i in 0..n {
+                let d = m.get(i, i).clone() + coef[n - k + 1].clone();
+                m.set(i, i, d);
+            }
+            // c_(n-k) = -tr(A M_k)/k
+            let am = self.mul(&m).expect("square matrix");
+            let trace = (0..n).fold(Number::Int(0), |acc, i| acc + am.get(i, i).clone());
+            coef[n - k] = -trace / Number::Int(k as i128);
+        }
+        coef
+    }
+}
+
+impl Display for Matrix {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "[")?;
+        for i in 0..self.rows {
+            if i > 0 {
+                write!(f, ", ")?;
+            }
+            write!(f, "[")?;
+            for (j, n) in self.row(i).iter().enumerate() {
+                if j > 0 {
+                    write!(f, ", ")?;
+                }
+                n.fmt(f)?;
+            }
+            write!(f, "]")?;
+        }
+        write!(f, "]")
+    }
+}
+
+fn magnitude(n: &Number) -> f64 {
+    n.to_complex().map_or(f64::NAN, |c| c.norm())
+}
+
+// exact numbers are negligible only when they are zero
+fn is_negligible(n: &Number, scale: f64) -> bool {
+    match n {
+        Number::Int(_) | Number::BigInt(_) | Number::Rational(_) => n.is_zero(),
+        n => magnitude(n) <= scale * EPSILON
+    }
+}
+
+// Roots of a polynomial with coefficients from the constant term up,
+// by Durand-Kerner method
+fn roots(coef: &[Number]) -> Vec<Complex64> {
+    let degree = coef.len() - 1;
+    let lead = coef[degree].to_complex().unwrap_or_default();
+    let monic: Vec<Complex64> = coef.iter().map(|c| c.to_complex().unwrap_or_default() / lead).collect();
+    let eval = |z: Complex64| monic.iter().rev().fold(Complex64::new(0.0, 0.0), |acc, c| acc * z + c);
+    // roots are not larger than 1 + the largest coefficient
+    let radius = 1.0 + monic[..degree].iter().map(|c| c.norm()).fold(0.0, f64::max);
+    let mut z: Vec<Complex64> = (0..degree)
+        .map(|k| Complex64::from_polar(radius, 0.4 + 2.0 * std::f64::consts::PI * k as f64 / degree as f64))
+        .collect();
+    for _ in 0..MAX_ROOT_STEPS {
+        let mut change: f64 = 0.0;
+        for i in 0..degree {
+            let den = (0..degree).filter(|j| *j != i).fold(Complex64::new(1.0, 0.0), |acc, j| acc * (z[i] - z[j]));
+            if den.norm() == 0.0 {
+                continue;
+            }
+            let step = eval(z[i]) / den;
+            z[i] -= step;
+            change = change.max(step.norm() / z[i].norm().max(1.0));
+        }
+        if change < f64::EPSILON {
+            break;
+        }
+    }
+    z
+}
+
+// Integer roots are made exact when the polynomial is exactly zero there,
+// imaginary parts lost in rounding are dropped
+fn exact_root(coef: &[Number], z: Complex64) -> Number {
+    if z.im.abs() > z.norm() * 1e-9 {
+        return z.into();
+    }
+    let k = Number::from(z.re.round());
+    if let Some(k) = k.to_bigint().map(Number::from) {
+        let value = coef.iter().rev().fold(Number::Int(0), |acc, c| acc * k.clone() + c.clone());
+        if value.is_zero() && coef.iter().all(|c| matches!(c, Number::Int(_) | Number::BigInt(_) | Number::Rational(_))) {
+            return k;
+        }
+    }
+    z.re.into()
+}
@@ -0,0 +1,64 @@
+use super::*;
+
+fn matrix(rows: &[&[i128]]) -> Matrix {
+    let data = rows.iter().flat_map(|row| row.iter().map(|n| Number::Int(*n))).collect();
+    Matrix::new(rows.len(), rows[0].len(), data).unwrap()
+}
+
+#[test]
+fn test_new() {
+    assert!(Matrix::new(2, 2, vec![Number::Int(1); 3]).is_none());
+    assert!(Matrix::new(0, 0, vec![]).is_none());
+    assert_eq!(Matrix::identity(2), matrix(&[&[1, 0], &[0, 1]]));
+    assert_eq!(Matrix::zeros(1, 2).to_string(), "[[0, 0]]");
+    assert_eq!(matrix(&[&[1, 2, 3], &[4, 5, 6]]).transpose().to_string(), "[[1, 4], [2, 5], [3, 6]]");
+}
+
+#[test]
+fn test_mul() {
+    let a = matrix(&[&[1, 2], &[3, 4]]);
+    let b = matrix(&[&[5], &[6]]);
+    assert_eq!(a.mul(&b).unwrap(), matrix(&[&[17], &[39]]));
+    assert_eq!(b.mul(&a).unwrap_err(), "Cannot multiply 2x1 matrix by 2x2 matrix");
+    assert_eq!(a.pow(3).unwrap(), matrix(&[&[37, 54], &[81, 118]]));
+    assert_eq!(a.pow(0).unwrap(), Matrix::identity(2));
+    assert_eq!(a.pow(-1).unwrap().to_string(), "[[-2, 1], [3/2, -1/2]]");
+}
+
+#[test]
+fn test_det() {
+    assert_eq!(matrix(&[&[1, 2], &[3, 4]]).det().unwrap(), Number::Int(-2));
+    assert_eq!(matrix(&[&[0, 1], &[1, 0]]).det().unwrap(), Number::Int(-1));
+    assert_eq!(matrix(&[&[2, 0, 1], &[1, 3, 2], &[1, 1, 2]]).det().unwrap(), Number::Int(6));
+    assert_eq!(matrix(&[&[1, 2], &[2, 4]]).det().unwrap(), Number::Int(0));
+    assert_eq!(matrix(&[&[1, 2]]).det().unwrap_err(), "Matrix must be square, got 1x2");
+}
+
+#[test]
+fn test_rank() {
+    assert_eq!(matrix(&[&[1, 2], &[2, 4]]).rank(), 1);
+    assert_eq!(matrix(&[&[1, 2, 3], &[4, 5, 6], &[7, 8, 9]]).rank(), 2);
+    assert_eq!(matrix(&[&[0, 0]]).rank(), 0);
+    assert_eq!(Matrix::identity(3).rank(), 3);
+}
+
+#[test]
+fn test_solve() {
+    let a = matrix(&[&[2, 1], &[1, 3]]);
+    assert_eq!(a.solve(&matrix(&[&[3], &[5]])).unwrap().to_string(), "[[4/5], [7/5]]");
+    assert_eq!(a.inv().unwrap().to_string(), "[[3/5, -1/5], [-1/5, 2/5]]");
+    assert_eq!(matrix(&[&[1, 2], &[2, 4]]).inv().unwrap_err(), "Matrix is singular");
+    assert_eq!(a.solve(&matrix(&[&[1]])).unwrap_err(), "Cannot solve 2x2 system with 1x1 right side");
+}
+
+#[test]
+fn test_eigenvalues() {
+    let values = |m: Matrix| m.eigenvalues().unwrap().iter().map(|n| n.to_string()).collect::<Vec<_>>();
+    assert_eq!(values(matrix(&[&[2, 1], &[1, 2]])), ["1", "3"]);
+    assert_eq!(values(matrix(&[&[1, 2], &[0, 3]])), ["1", "3"]);
+    assert_eq!(values(matrix(&[&[2, 0, 0], &[1, 3, 4], &[0, 4, 3]])), ["-1", "2", "7"]);
+    let rotation = matrix(&[&[0, -1], &[1, 0]]).eigenvalues().unwrap();
+    assert_eq!(rotation.len(), 2);
+    assert!(rotation.iter().all(|n| matches!(n, Number::Complex(z) if z.re.abs() < 1e-9 && (z.im.abs() - 1.0).abs() < 1e-9)));
+    assert_eq!(matrix(&[&[1, 2]]).eigenvalues().unwrap_err(), "Matrix must be square, got 1x2");
+}
@@ -1,24 +1,284 @@
 #[cfg(test)]
 mod tests;
 pub mod parse;
+pub mod decimal;
+pub mod radix;
+pub mod pattern;
 
 use std::ops::{Add, BitXor, Div, Mul, Neg, Rem, Sub};
+use std::cmp::Ordering;
 use std::fmt::Display;
+use num_bigint::BigInt;
+use num_rational::BigRational;
+use num_complex::Complex64;
+use num_traits::{Pow, Signed, ToPrimitive, Zero};
+use decimal::Decimal;
 
+// Integer results larger than this many bits are treated as NaN
+// so that expressions like 9^9^9 do not exhaust memory
+const MAX_BITS: u64 = 1 << 20;
 
-#[derive(Clone, Copy, Debug, PartialEq)]
+// Number of fraction digits used when rational is displayed as decimal
+const DECIMAL_DIGITS: usize = 10;
+
+#[derive(Clone, Debug, PartialEq, Default)]
 pub enum Number {
     Int(i128),
+    BigInt(BigInt),
+    Rational(BigRational),
     Float(f64),
+    Decimal(Decimal),
+    Complex(Complex64),
+    #[default]
     NaN
 }
 
+impl Number {
+    pub fn is_zero(&self) -> bool {
+        match self {
+            Number::Int(i) => *i == 0,
+            Number::BigInt(b) => b.is_zero(),
+            Number::Rational(r) => r.is_zero(),
+            Number::Float(f) => *f == 0.0,
+            Number::Decimal(d) => d.is_zero(),
+            Number::Complex(c) => c.is_zero(),
+            Number::NaN => false
+        }
+    }
+
+    pub fn is_negative(&self) -> bool {
+        match self {
+            Number::Int(i) => *i < 0,
+            Number::BigInt(b) => b.is_negative(),
+            Number::Rational(r) => r.is_negative(),
+            Number::Float(f) => *f < 0.0,
+            Number::Decimal(d) => d.is_negative(),
+            Number::Complex(_) | Number::NaN => false
+        }
+    }
+
+    fn is_integer(&self) -> bool {
+        match self {
+            Number::Int(_) | Number::BigInt(_) => true,
+            Number::Float(f) => f.fract() == 0.0,
+            Number::Decimal(d) => d.is_integer(),
+            Number::Rational(_) | Number::Complex(_) | Number::NaN => false
+        }
+    }
+
+    /// Integer value, None for other kinds of numbers
+    pub fn to_bigint(&self) -> Option<BigInt> {
+        match self {
+            Number::Int(i) => Some(BigInt::from(*i)),
+            Number::BigInt(b) => Some(b.clone()),
+            _ => None
+        }
+    }
+
+    // Runs integer operation on i128 and falls back to BigInt on overflow.
+    // Returns None if any of the arguments is not an integer
+    // or the operation has no integer result.
+    fn int_op<F, B>(&self, rhs: &Number, fast: F, big: B) -> Option<Number>
+        where F: Fn(i128, i128) -> Option<i128>,
+              B: Fn(BigInt, BigInt) -> Option<BigInt>
+    {
+        if let (Number::Int(l), Number::Int(r)) = (self, rhs) {
+            if let Some(res) = fast(*l, *r) {
+                return Some(Number::Int(res));
+            }
+        }
+        let (l, r) = (self.to_bigint()?, rhs.to_bigint()?);
+        big(l, r).map(Number::from)
+    }
+
+    fn to_rational(&self) -> Option<BigRational> {
+        match self {
+            Number::Rational(r) => Some(r.clone()),
+            n => n.to_bigint().map(BigRational::from_integer)
+        }
+    }
+
+    // Runs exact operation on rationals (integers are treated as rationals too).
+    // Returns None if any of the arguments is a float.
+    fn rational_op<F>(&self, rhs: &Number, op: F) -> Option<Number>
+        where F: Fn(BigRational, BigRational) -> BigRational
+    {
+        let (l, r) = (self.to_rational()?, rhs.to_rational()?);
+        Some(op(l, r).into())
+    }
+
+    /// Converts number to decimal with given precision
+    pub fn to_decimal(&self, prec: usize) -> Option<Decimal> {
+        match self {
+            Number::Int(i) => Some(Decimal::from_bigint(BigInt::from(*i), prec)),
+            Number::BigInt(b) => Some(Decimal::from_bigint(b.clone(), prec)),
+            Number::Rational(r) => Some(Decimal::from_rational(r, prec)),
+            Number::Float(f) => Decimal::from_f64(*f, prec),
+            Number::Decimal(d) => Some(d.clone()),
+            Number::Complex(_) | Number::NaN => None
+        }
+    }
+
+    /// Converts real or complex number to complex
+    pub fn to_complex(&self) -> Option<Complex64> {
+        match self {
+            Number::Complex(c) => Some(*c),
+            Number::NaN => None,
+            n => Some(Complex64::new(n.clone().into(), 0.0))
+        }
+    }
+
+    // Runs operation on complex numbers if any of the arguments is complex.
+    // Returns None otherwise.
+    fn complex_op<F>(&self, rhs: &Number, op: F) -> Option<Number>
+        where F: Fn(Complex64, Complex64) -> Complex64
+    {
+        if !matches!(self, Number::Complex(_)) && !matches!(rhs, Number::Complex(_)) {
+            return None;
+        }
+        let (l, r) = (self.to_complex()?, rhs.to_complex()?);
+        Some(op(l, r).into())
+    }
+
+    // precision of decimal arguments, the larger one if both are decimals,
+    // None if neither is
+    fn decimal_precision(&self, rhs: &Number) -> Option<usize> {
+        match (self, rhs) {
+            (Number::Decimal(l), Number::Decimal(r)) => Some(l.precision().max(r.precision())),
+            (Number::Decimal(d), _) | (_, Number::Decimal(d)) => Some(d.precision()),
+            _ => None
+        }
+    }
+
+    // Runs operation on decimals if any of the arguments is a decimal,
+    // with the precision of decimal arguments. Returns None otherwise.
+    fn decimal_op<F>(&self, rhs: &Number, op: F) -> Option<Number>
+        where F: Fn(Decimal, Decimal, usize) -> Option<Decimal>
+    {
+        let prec = self.decimal_precision(rhs)?;
+        let (l, r) = (self.to_decimal(prec)?, rhs.to_decimal(prec)?);
+        Some(op(l, r, prec).into())
+    }
+
+    /// Compares values of real numbers, so that 1/2 equals 0.5.
+    /// Complex numbers and NaN cannot be compared.
+    pub fn compare(&self, other: &Number) -> Option<Ordering> {
+        if let (Some(l), Some(r)) = (self.to_rational(), other.to_rational()) {
+            return Some(l.cmp(&r));
+        }
+        match (self, other) {
+            (Number::NaN | Number::Complex(_), _) | (_, Number::NaN | Number::Complex(_)) => None,
+            (Number::Decimal(_), _) | (_, Number::Decimal(_)) => {
+                let prec = self.decimal_precision(other).unwrap_or(DECIMAL_DIGITS);
+                let diff = self.to_decimal(prec)?.sub(&other.to_decimal(prec)?, prec);
+                match (diff.is_zero(), diff.is_negative()) {
+                    (true, _) => Some(Ordering::Equal),
+                    (_, true) => Some(Ordering::Less),
+                    _ => Some(Ordering::Greater)
+                }
+            },
+            _ => {
+                let (l, r): (f64, f64) = (self.clone().into(), other.clone().into());
+                l.partial_cmp(&r)
+            }
+        }
+    }
+}
+
+// Bitwise operations are defined on integers only, as if they had infinitely
+// many sign bits, other numbers give NaN
+impl Number {
+    pub fn bit_and(&self, rhs: &Number) -> Number {
+        self.int_op(rhs, |l, r| Some(l & r), |l, r| Some(l & r)).unwrap_or(Number::NaN)
+    }
+
+    pub fn bit_or(&self, rhs: &Number) -> Number {
+        self.int_op(rhs, |l, r| Some(l | r), |l, r| Some(l | r)).unwrap_or(Number::NaN)
+    }
+
+    pub fn bit_xor(&self, rhs: &Number) -> Number {
+        self.int_op(rhs, |l, r| Some(l ^ r), |l, r| Some(l ^ r)).unwrap_or(Number::NaN)
+    }
+
+    pub fn bit_not(&self) -> Number {
+        match self {
+            Number::Int(i) => Number::Int(!*i),
+            Number::BigInt(b) => Number::from(-b - 1),
+            _ => Number::NaN
+        }
+    }
+
+    /// Shift left, shift by a negative amount is NaN
+    pub fn shl(&self, rhs: &Number) -> Number {
+        let fast = |l: i128, r: i128| match r {
+            0..=126 => l.checked_mul(1 << r),
+            _ => None
+        };
+        let big = |l: BigInt, r: BigInt| match r.to_u64() {
+            Some(r) if l.bits().saturating_add(r) <= MAX_BITS => Some(l << r),
+            _ => None
+        };
+        self.int_op(rhs, fast, big).unwrap_or(Number::NaN)
+    }
+
+    /// Arithmetic shift right, rounds towards negative infinity
+    pub fn shr(&self, rhs: &Number) -> Number {
+        let fast = |l: i128, r: i128| match r {
+            0..=127 => Some(l >> r),
+            128.. => Some(l >> 127),
+            _ => None
+        };
+        let big = |l: BigInt, r: BigInt| r.to_u64().map(|r| l >> r);
+        self.int_op(rhs, fast, big).unwrap_or(Number::NaN)
+    }
+
+    /// Logical shift right of the number's two's complement in given
+    /// number of bits, so that -1 >>> 60 is 15 in 64 bits
+    pub fn ushr(&self, rhs: &Number, bits: u32) -> Number {
+        let big = |l: BigInt, r: BigInt| {
+            let r = r.to_u64()?;
+            let modulus = BigInt::from(1) << bits;
+            let l = ((l % &modulus) + &modulus) % modulus;
+            Some(l >> r)
+        };
+        self.int_op(rhs, |_, _| None, big).unwrap_or(Number::NaN)
+    }
+}
+
+fn fmt_decimal(val: &BigRational, digits: usize) -> String {
+    let scale = BigInt::from(10).pow(digits);
+    let scaled = (val.abs() * BigRational::from_integer(scale.clone())).round().to_integer();
+    let int_part = &scaled / &scale;
+    let frac_part = (&scaled % &scale).to_string();
+    let frac_part = format!("{frac_part:0>digits$}");
+    let frac_part = frac_part.trim_end_matches('0');
+    let sign = if val.is_negative() && !scaled.is_zero() { "-" } else { "" };
+    if frac_part.is_empty() {
+        format!("{sign}{int_part}")
+    } else {
+        format!("{sign}{int_part}.{frac_part}")
+    }
+}
+
+/// Prints rational numbers as fractions, e.g. `1/3`. Alternate flag
+/// prints them as decimals instead, e.g. `{:#}` prints `0.3333333333`
+/// and `{:#.3}` prints `0.333`.
 impl Display for Number {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            &Number::Int(val) => write!(f, "{val}"),
-            &Number::Float(val) => write!(f, "{val}"),
-            &Number::NaN => write!(f, "NaN")
+            Number::Int(val) => write!(f, "{val}"),
+            Number::BigInt(val) => write!(f, "{val}"),
+            Number::Rational(val) if f.alternate() => {
+                let digits = f.precision().unwrap_or(DECIMAL_DIGITS);
+                write!(f, "{}", fmt_decimal(val, digits))
+            },
+            Number::Rational(val) => write!(f, "{val}"),
+            Number::Float(val) => write!(f, "{val}"),
+            Number::Decimal(val) => write!(f, "{val}"),
+            Number::Complex(val) if val.re == 0.0 => write!(f, "{}i", val.im),
+            Number::Complex(val) if val.im < 0.0 => write!(f, "{}-{}i", val.re, -val.im),
+            Number::Complex(val) => write!(f, "{}+{}i", val.re, val.im),
+            Number::NaN => write!(f, "NaN")
         }
     }
 }
@@ -27,7 +287,12 @@ impl From<Number> for f64 {
     fn from(value: Number) -> Self {
         match value {
             Number::Int(i) => i as f64, //TODO: do safe conversion here
+            Number::BigInt(b) => b.to_f64().unwrap_or(f64::NAN),
+            Number::Rational(r) => r.to_f64().unwrap_or(f64::NAN),
             Number::Float(f) => f,
+            Number::Decimal(d) => d.to_f64(),
+            Number::Complex(c) if c.im == 0.0 => c.re,
+            Number::Complex(_) => f64::NAN,
             Number::NaN => f64::NAN
         }
     }
@@ -35,12 +300,64 @@ impl From<Number> for f64 {
 
 impl From<f64> for Number {
     fn from(value: f64) -> Self {
-        if value == std::f64::NAN || value == std::f64::INFINITY || value == std::f64::NEG_INFINITY {
+        if value.is_finite() {
+            Number::Float(value)
+        } else {
+            Number::NaN
+        }
+    }
+}
+
+// BigInt values are normalized back to Int whenever they fit in i128
+impl From<BigInt> for Number {
+    fn from(value: BigInt) -> Self {
+        match value.to_i128() {
+            Some(i) => Number::Int(i),
+            None if value.bits() > MAX_BITS => Number::NaN,
+            None => Number::BigInt(value)
+        }
+    }
+}
+
+// Rationals with denominator 1 are normalized to integers
+impl From<BigRational> for Number {
+    fn from(value: BigRational) -> Self {
+        if value.is_integer() {
+            value.to_integer().into()
+        } else if value.numer().bits() + value.denom().bits() > MAX_BITS {
             Number::NaN
         } else {
-            Number::Float(value)
+            Number::Rational(value)
+        }
+    }
+}
+
+impl From<Option<Decimal>> for Number {
+    fn from(value: Option<Decimal>) -> Self {
+        match value {
+            Some(d) => Number::Decimal(d),
+            None => Number::NaN
         }
+    }
+}
 
+// Complex numbers with no imaginary part are normalized to floats
+impl From<Complex64> for Number {
+    fn from(value: Complex64) -> Self {
+        if !value.is_finite() {
+            Number::NaN
+        } else if value.im == 0.0 {
+            Number::Float(value.re)
+        } else {
+            Number::Complex(value)
+        }
+    }
+}
+
+// true is 1 and false is 0
+impl From<bool> for Number {
+    fn from(value: bool) -> Self {
+        Number::Int(value as i128)
     }
 }
 
@@ -59,18 +376,19 @@ impl From<Option<i128>> for Number {
     }
 }
 
-impl Default for Number {
-    fn default() -> Self {
-        Number::NaN
-    }
-}
-
 impl Neg for Number {
     type Output = Number;
     fn neg(self) -> Self::Output {
         match self {
             Number::NaN => Number::NaN,
-            Number::Int(l) => l.checked_neg().into(),
+            Number::Int(l) => match l.checked_neg() {
+                Some(n) => Number::Int(n),
+                None => (-BigInt::from(l)).into()
+            },
+            Number::BigInt(l) => (-l).into(),
+            Number::Rational(l) => (-l).into(),
+            Number::Decimal(l) => Number::Decimal(l.neg()),
+            Number::Complex(l) => (-l).into(),
             _ => {
                 let l: f64 = self.into();
                 (-l).into()
@@ -82,45 +400,72 @@ impl Neg for Number {
 impl Add for Number {
     type Output = Number;
     fn add(self, rhs: Self) -> Self::Output {
-        match (self, rhs) {
-            (Number::NaN, _) | (_, Number::NaN) => Number::NaN,
-            (Number::Int(l), Number::Int(r)) => l.checked_add(r).into(),
-            _ => {
-                let l: f64 = self.into();
-                let r: f64 = rhs.into();
-                (l + r).into()
-            }
+        if self == Number::NaN || rhs == Number::NaN {
+            return Number::NaN;
         }
+        if let Some(res) = self.int_op(&rhs, i128::checked_add, |l, r| Some(l + r)) {
+            return res;
+        }
+        if let Some(res) = self.rational_op(&rhs, |l, r| l + r) {
+            return res;
+        }
+        if let Some(res) = self.complex_op(&rhs, |l, r| l + r) {
+            return res;
+        }
+        if let Some(res) = self.decimal_op(&rhs, |l, r, prec| Some(l.add(&r, prec))) {
+            return res;
+        }
+        let l: f64 = self.into();
+        let r: f64 = rhs.into();
+        (l + r).into()
     }
 }
 
 impl Sub for Number {
     type Output = Number;
     fn sub(self, rhs: Self) -> Self::Output {
-        match (self, rhs) {
-            (Number::NaN, _) | (_, Number::NaN) => Number::NaN,
-            (Number::Int(l), Number::Int(r)) => l.checked_sub(r).into(),
-            _ => {
-                let l: f64 = self.into();
-                let r: f64 = rhs.into();
-                (l - r).into()
-            }
+        if self == Number::NaN || rhs == Number::NaN {
+            return Number::NaN;
+        }
+        if let Some(res) = self.int_op(&rhs, i128::checked_sub, |l, r| Some(l - r)) {
+            return res;
         }
+        if let Some(res) = self.rational_op(&rhs, |l, r| l - r) {
+            return res;
+        }
+        if let Some(res) = self.complex_op(&rhs, |l, r| l - r) {
+            return res;
+        }
+        if let Some(res) = self.decimal_op(&rhs, |l, r, prec| Some(l.sub(&r, prec))) {
+            return res;
+        }
+        let l: f64 = self.into();
+        let r: f64 = rhs.into();
+        (l - r).into()
     }
 }
 
 impl Mul for Number {
     type Output = Number;
     fn mul(self, rhs: Self) -> Self::Output {
-        match (self, rhs) {
-            (Number::NaN, _) | (_, Number::NaN) => Number::NaN,
-            (Number::Int(l), Number::Int(r)) => l.checked_mul(r).into(),
-            _ => {
-                let l: f64 = self.into();
-                let r: f64 = rhs.into();
-                (l * r).into()
-            }
+        if self == Number::NaN || rhs == Number::NaN {
+            return Number::NaN;
+        }
+        if let Some(res) = self.int_op(&rhs, i128::checked_mul, |l, r| Some(l * r)) {
+            return res;
+        }
+        if let Some(res) = self.rational_op(&rhs, |l, r| l * r) {
+            return res;
+        }
+        if let Some(res) = self.complex_op(&rhs, |l, r| l * r) {
+            return res;
         }
+        if let Some(res) = self.decimal_op(&rhs, |l, r, prec| Some(l.mul(&r, prec))) {
+            return res;
+        }
+        let l: f64 = self.into();
+        let r: f64 = rhs.into();
+        (l * r).into()
     }
 }
 
@@ -128,37 +473,54 @@ impl Mul for Number {
 impl Rem for Number {
     type Output = Number;
     fn rem(self, rhs: Self) -> Self::Output {
-        match (self, rhs) {
-            (Number::NaN, _) | (_, Number::NaN) => Number::NaN,
-            (Number::Int(l), Number::Int(r)) => l.checked_rem(r).into(),
-            _ => {
-                let l: f64 = self.into();
-                let r: f64 = rhs.into();
-                l.rem_euclid(r).into()
-            }
+        if self == Number::NaN || rhs == Number::NaN || rhs.is_zero() {
+            return Number::NaN;
         }
+        if let Some(res) = self.int_op(&rhs, i128::checked_rem, |l, r| Some(l % r)) {
+            return res;
+        }
+        if let Some(res) = self.rational_op(&rhs, |l, r| l % r) {
+            return res;
+        }
+        if matches!(self, Number::Complex(_)) || matches!(rhs, Number::Complex(_)) {
+            return Number::NaN;
+        }
+        if let Some(res) = self.decimal_op(&rhs, |l, r, prec| l.rem(&r, prec)) {
+            return res;
+        }
+        let l: f64 = self.into();
+        let r: f64 = rhs.into();
+        l.rem_euclid(r).into()
     }
 }
 
 impl Div for Number {
     type Output = Number;
     fn div(self, rhs: Self) -> Self::Output {
-        match (self, rhs) {
-            (Number::NaN, _) | (_, Number::NaN) => Number::NaN,
-            (Number::Int(l), Number::Int(r)) => {
-                let rem = l.checked_rem(r);
-                match rem {
-                    Some(r) if r > 0 => Number::Float(self.into()) / Number::Float(rhs.into()),
-                    Some(_) => Number::Int(l / r),
-                    None => Number::NaN
-                }
-            },
-            _ => {
-                let l: f64 = self.into();
-                let r: f64 = rhs.into();
-                (l / r).into()
+        if self == Number::NaN || rhs == Number::NaN || rhs.is_zero() {
+            return Number::NaN;
+        }
+        let exact = self.int_op(&rhs, |l, r| {
+            match l.checked_rem(r) {
+                Some(0) => l.checked_div(r),
+                _ => None
             }
+        }, |l, r| (&l % &r).is_zero().then(|| l / r));
+        if let Some(res) = exact {
+            return res;
         }
+        if let Some(res) = self.rational_op(&rhs, |l, r| l / r) {
+            return res;
+        }
+        if let Some(res) = self.complex_op(&rhs, |l, r| l / r) {
+            return res;
+        }
+        if let Some(res) = self.decimal_op(&rhs, |l, r, prec| l.div(&r, prec)) {
+            return res;
+        }
+        let l: f64 = self.into();
+        let r: f64 = rhs.into();
+        (l / r).into()
     }
 }
 
@@ -166,21 +528,46 @@ impl Div for Number {
 impl BitXor for Number {
     type Output = Number;
     fn bitxor(self, rhs: Self) -> Self::Output {
-        match (self, rhs) {
-            (Number::NaN, _) | (_, Number::NaN) => Number::NaN,
-            (Number::Int(l), Number::Int(r)) => {
-                if let Ok(exp) = r.try_into() {
-                    l.checked_pow(exp).into()
-                } else {
-                    Number::NaN
+        if self == Number::NaN || rhs == Number::NaN {
+            return Number::NaN;
+        }
+        if let (Some(base), Number::Int(exp)) = (self.to_bigint(), &rhs) {
+            if let Ok(exp) = u32::try_from(*exp) {
+                if let Some(res) = base.to_i128().and_then(|b| b.checked_pow(exp)) {
+                    return Number::Int(res);
                 }
-                
-            },
-            _ => {
-                let l: f64 = self.into();
-                let r: f64 = rhs.into();
-                l.powf(r).into()
+                if base.bits().saturating_mul(exp as u64) > MAX_BITS {
+                    return Number::NaN;
+                }
+                return base.pow(exp).into();
             }
         }
+        if let (Some(base), Number::Int(exp)) = (self.to_rational(), &rhs) {
+            if let Ok(exp) = i32::try_from(*exp) {
+                let bits = base.numer().bits() + base.denom().bits();
+                if (base.is_zero() && exp < 0) || bits.saturating_mul(exp.unsigned_abs() as u64) > MAX_BITS {
+                    return Number::NaN;
+                }
+                return base.pow(exp).into();
+            }
+        }
+        // negative numbers to fractional powers have complex results, e.g. (-8)^(1/3)
+        let is_complex = matches!(self, Number::Complex(_)) || matches!(rhs, Number::Complex(_));
+        if is_complex || (self.is_negative() && !rhs.is_integer()) {
+            let (base, exp) = match (self.to_complex(), rhs.to_complex()) {
+                (Some(base), Some(exp)) => (base, exp),
+                _ => return Number::NaN
+            };
+            return match rhs {
+                Number::Int(n) if i32::try_from(n).is_ok() => base.powi(n as i32).into(),
+                _ => base.powc(exp).into()
+            };
+        }
+        if let Some(res) = self.decimal_op(&rhs, |l, r, prec| l.pow(&r, prec)) {
+            return res;
+        }
+        let l: f64 = self.into();
+        let r: f64 = rhs.into();
+        l.powf(r).into()
     }
 }
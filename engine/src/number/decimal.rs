@@ -0,0 +1,600 @@
+#[cfg(test)]
+mod tests;
+
+use std::cmp::Ordering;
+use std::fmt::Display;
+use num_bigint::BigInt;
+use num_integer::Integer;
+use num_rational::BigRational;
+use num_traits::{One, Signed, ToPrimitive, Zero};
+
+// Digits computed on top of requested precision in functions,
+// so that rounding errors do not show up in the result
+const GUARD_DIGITS: usize = 10;
+
+pub const MAX_PRECISION: usize = 1000;
+
+// Widest gap between exponents we are willing to align in add and rem
+const MAX_ALIGN: i64 = 100_000;
+
+/// Decimal float with arbitrary precision: value is `mantissa * 10^exp`.
+/// Mantissa never has trailing zeros, so equal values compare equal.
+/// The decimal remembers the precision it was computed with, operations
+/// on numbers that are not decimals use it.
+#[derive(Clone, Debug)]
+pub struct Decimal {
+    mantissa: BigInt,
+    exp: i64,
+    prec: usize
+}
+
+// values are equal regardless of the precision they were computed with
+impl PartialEq for Decimal {
+    fn eq(&self, other: &Self) -> bool {
+        self.mantissa == other.mantissa && self.exp == other.exp
+    }
+}
+
+fn pow10(n: usize) -> BigInt {
+    BigInt::from(10).pow(n as u32)
+}
+
+fn digits(n: &BigInt) -> usize {
+    if n.is_zero() {
+        0
+    } else {
+        n.magnitude().to_str_radix(10).len()
+    }
+}
+
+// Fixed point helpers used by series: x is represented as x * 10^wp
+fn fixed_mul(a: &BigInt, b: &BigInt, scale: &BigInt) -> BigInt {
+    a * b / scale
+}
+
+fn fixed_div(a: &BigInt, b: &BigInt, scale: &BigInt) -> BigInt {
+    a * scale / b
+}
+
+// sum of x^(2n+1)/(2n+1), converges for |x| < 1
+fn atanh_fixed(x: &BigInt, scale: &BigInt) -> BigInt {
+    let x2 = fixed_mul(x, x, scale);
+    let mut power = x.clone();
+    let mut sum = x.clone();
+    let mut n = 1u32;
+    loop {
+        power = fixed_mul(&power, &x2, scale);
+        let term = &power / (2 * n + 1);
+        if term.is_zero() {
+            return sum;
+        }
+        sum += term;
+        n += 1;
+    }
+}
+
+// sum of (-1)^n x^(2n+1)/(2n+1), converges for |x| <= 1
+fn atan_fixed(x: &BigInt, scale: &BigInt) -> BigInt {
+    let x2 = fixed_mul(x, x, scale);
+    let mut power = x.clone();
+    let mut sum = x.clone();
+    let mut n = 1u32;
+    loop {
+        power = -fixed_mul(&power, &x2, scale);
+        let term = &power / (2 * n + 1);
+        if term.is_zero() {
+            return sum;
+        }
+        sum += term;
+        n += 1;
+    }
+}
+
+// Taylor series for sin (start = x, k = 1) or cos (start = 1, k = 0)
+fn sin_cos_fixed(x: &BigInt, start: BigInt, k: u32, scale: &BigInt) -> BigInt {
+    let x2 = fixed_mul(x, x, scale);
+    let mut term = start;
+    let mut sum = term.clone();
+    let mut n = k;
+    loop {
+        term = -fixed_mul(&term, &x2, scale) / ((n + 1) * (n + 2));
+        if term.is_zero() {
+            return sum;
+        }
+        sum += &term;
+        n += 2;
+    }
+}
+
+fn exp_fixed(x: &BigInt, scale: &BigInt) -> BigInt {
+    let mut term = scale.clone();
+    let mut sum = scale.clone();
+    let mut n = 1u32;
+    loop {
+        term = fixed_mul(&term, x, scale) / n;
+        if term.is_zero() {
+            return sum;
+        }
+        sum += &term;
+        n += 1;
+    }
+}
+
+impl Decimal {
+    /// Creates decimal `mantissa * 10^exp` rounded to `prec` significant digits.
+    pub fn new(mantissa: BigInt, exp: i64, prec: usize) -> Decimal {
+        Decimal { mantissa, exp, prec }.round(prec)
+    }
+
+    pub fn zero(prec: usize) -> Decimal {
+        Decimal { mantissa: BigInt::zero(), exp: 0, prec }
+    }
+
+    /// Number of significant digits the decimal was computed with
+    pub fn precision(&self) -> usize {
+        self.prec
+    }
+
+    fn from_fixed(x: BigInt, wp: usize, prec: usize) -> Decimal {
+        Decimal::new(x, -(wp as i64), prec)
+    }
+
+    pub fn from_bigint(value: BigInt, prec: usize) -> Decimal {
+        Decimal::new(value, 0, prec)
+    }
+
+    pub fn from_rational(value: &BigRational, prec: usize) -> Decimal {
+        let numer = Decimal::from_bigint(value.numer().clone(), prec + GUARD_DIGITS);
+        let denom = Decimal::from_bigint(value.denom().clone(), prec + GUARD_DIGITS);
+        numer.div(&denom, prec).unwrap_or_else(|| Decimal::zero(prec))
+    }
+
+    /// Converts binary float to decimal. The conversion is exact before
+    /// rounding, so 0.1f64 becomes 0.1000000000000000055511151231257827...
+    pub fn from_f64(value: f64, prec: usize) -> Option<Decimal> {
+        if !value.is_finite() {
+            return None;
+        }
+        if value == 0.0 {
+            return Some(Decimal::zero(prec));
+        }
+        let bits = value.to_bits();
+        let sign = if bits >> 63 == 0 { 1 } else { -1 };
+        let raw_exp = ((bits >> 52) & 0x7ff) as i64;
+        let fraction = bits & 0xf_ffff_ffff_ffff;
+        let (mantissa, exp2) = if raw_exp == 0 {
+            (fraction, -1074)
+        } else {
+            (fraction | (1 << 52), raw_exp - 1075)
+        };
+        let mantissa = BigInt::from(mantissa) * sign;
+        if exp2 >= 0 {
+            Some(Decimal::new(mantissa << exp2 as usize, 0, prec))
+        } else {
+            // m * 2^-k == m * 5^k * 10^-k
+            let k = (-exp2) as u32;
+            Some(Decimal::new(mantissa * BigInt::from(5).pow(k), -(k as i64), prec))
+        }
+    }
+
+    /// Parses float literal such as `1.25`, `.5`, `-3e-7`.
+    pub fn parse(input: &str, prec: usize) -> Option<Decimal> {
+        let (sign, input) = match input.strip_prefix('-') {
+            Some(rest) => (-1, rest),
+            None => (1, input)
+        };
+        let (num, exp) = match input.find(['e', 'E']) {
+            Some(pos) => (&input[..pos], input[pos + 1..].parse::<i64>().ok()?),
+            None => (input, 0)
+        };
+        let (int_part, frac_part) = match num.find('.') {
+            Some(pos) => (&num[..pos], &num[pos + 1..]),
+            None => (num, "")
+        };
+        let digits = format!("{int_part}{frac_part}");
+        if digits.is_empty() || !digits.chars().all(|c| c.is_ascii_digit()) {
+            return None;
+        }
+        let mantissa = BigInt::parse_bytes(digits.as_bytes(), 10)? * sign;
+        Some(Decimal::new(mantissa, exp - frac_part.len() as i64, prec))
+    }
+
+    fn round(mut self, prec: usize) -> Decimal {
+        self.prec = prec;
+        let d = digits(&self.mantissa);
+        if d > prec {
+            let drop = d - prec;
+            let divisor = pow10(drop);
+            let (mut q, r) = self.mantissa.div_rem(&divisor);
+            let sign = self.mantissa.signum();
+            // round half to even
+            match (r.abs() * 2u32).cmp(&divisor) {
+                Ordering::Greater => q += &sign,
+                Ordering::Equal if q.is_odd() => q += &sign,
+                _ => ()
+            }
+            self.mantissa = q;
+            self.exp += drop as i64;
+        }
+        if self.mantissa.is_zero() {
+            self.exp = 0;
+            return self;
+        }
+        let ten = BigInt::from(10);
+        loop {
+            let (q, r) = self.mantissa.div_rem(&ten);
+            if !r.is_zero() {
+                break;
+            }
+            self.mantissa = q;
+            self.exp += 1;
+        }
+        self
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.mantissa.is_zero()
+    }
+
+    pub fn is_negative(&self) -> bool {
+        self.mantissa.is_negative()
+    }
+
+    pub fn is_integer(&self) -> bool {
+        self.exp >= 0
+    }
+
+    pub fn abs(&self) -> Decimal {
+        Decimal { mantissa: self.mantissa.abs(), ..*self }
+    }
+
+    // position of the most significant digit, e.g. 0 for 1.5 and -2 for 0.01
+    fn magnitude(&self) -> i64 {
+        self.exp + digits(&self.mantissa) as i64 - 1
+    }
+
+    /// Integer part of the number, or None if it is too large
+    pub fn to_bigint(&self) -> Option<BigInt> {
+        if self.exp >= 0 {
+            if self.exp > MAX_ALIGN {
+                return None;
+            }
+            Some(&self.mantissa * pow10(self.exp as usize))
+        } else if self.magnitude() < 0 {
+            Some(BigInt::zero())
+        } else {
+            Some(&self.mantissa / pow10((-self.exp) as usize))
+        }
+    }
+
+    fn round_to_bigint(&self) -> Option<BigInt> {
+        let half = Decimal { mantissa: BigInt::from(5) * self.mantissa.signum(), exp: -1, prec: 1 };
+        self.add(&half, digits(&self.mantissa) + 2).to_bigint()
+    }
+
+    pub fn to_f64(&self) -> f64 {
+        format!("{}e{}", self.mantissa, self.exp).parse().unwrap_or(f64::NAN)
+    }
+
+    fn to_fixed(&self, wp: usize) -> BigInt {
+        let shift = self.exp + wp as i64;
+        if shift >= 0 {
+            &self.mantissa * pow10(shift as usize)
+        } else if -shift > digits(&self.mantissa) as i64 {
+            BigInt::zero()
+        } else {
+            &self.mantissa / pow10((-shift) as usize)
+        }
+    }
+
+    pub fn add(&self, rhs: &Decimal, prec: usize) -> Decimal {
+        if self.is_zero() {
+            return rhs.clone().round(prec);
+        }
+        if rhs.is_zero() {
+            return self.clone().round(prec);
+        }
+        // skip aligning if one of the numbers is too small to matter
+        let gap = self.magnitude() - rhs.magnitude();
+        if gap > prec as i64 + 2 {
+            return self.clone().round(prec);
+        }
+        if -gap > prec as i64 + 2 {
+            return rhs.clone().round(prec);
+        }
+        let exp = self.exp.min(rhs.exp);
+        let l = &self.mantissa * pow10((self.exp - exp) as usize);
+        let r = &rhs.mantissa * pow10((rhs.exp - exp) as usize);
+        Decimal::new(l + r, exp, prec)
+    }
+
+    pub fn sub(&self, rhs: &Decimal, prec: usize) -> Decimal {
+        self.add(&rhs.neg(), prec)
+    }
+
+    pub fn mul(&self, rhs: &Decimal, prec: usize) -> Decimal {
+        Decimal::new(&self.mantissa * &rhs.mantissa, self.exp + rhs.exp, prec)
+    }
+
+    pub fn div(&self, rhs: &Decimal, prec: usize) -> Option<Decimal> {
+        if rhs.is_zero() {
+            return None;
+        }
+        let shift = (prec + 2 + digits(&rhs.mantissa)).saturating_sub(digits(&self.mantissa));
+        let (q, r) = (&self.mantissa * pow10(shift)).div_rem(&rhs.mantissa);
+        // extra digit marks inexact result, so half-even rounding stays correct
+        let sticky = if r.is_zero() {
+            BigInt::zero()
+        } else {
+            self.mantissa.signum() * rhs.mantissa.signum()
+        };
+        let exp = self.exp - rhs.exp - shift as i64 - 1;
+        Some(Decimal::new(q * 10 + sticky, exp, prec))
+    }
+
+    /// Remainder of truncated division, same sign as self
+    pub fn rem(&self, rhs: &Decimal, prec: usize) -> Option<Decimal> {
+        if rhs.is_zero() || (self.exp - rhs.exp).abs() > MAX_ALIGN {
+            return None;
+        }
+        let exp = self.exp.min(rhs.exp);
+        let l = &self.mantissa * pow10((self.exp - exp) as usize);
+        let r = &rhs.mantissa * pow10((rhs.exp - exp) as usize);
+        Some(Decimal::new(l % r, exp, prec))
+    }
+
+    pub fn neg(&self) -> Decimal {
+        Decimal { mantissa: -&self.mantissa, ..*self }
+    }
+
+    pub fn powi(&self, exp: i64, prec: usize) -> Option<Decimal> {
+        // keep exponent of the result well within i64
+        let magnitude = self.magnitude().unsigned_abs() + 1;
+        if magnitude.saturating_mul(exp.unsigned_abs()) > 1 << 50 {
+            return None;
+        }
+        let wp = prec + GUARD_DIGITS + digits(&BigInt::from(exp));
+        let mut base = self.clone();
+        let mut result = Decimal::from_bigint(BigInt::one(), wp);
+        let mut n = exp.unsigned_abs();
+        while n > 0 {
+            if n & 1 == 1 {
+                result = result.mul(&base, wp);
+            }
+            base = base.mul(&base, wp);
+            n >>= 1;
+        }
+        if exp < 0 {
+            Decimal::from_bigint(BigInt::one(), wp).div(&result, prec)
+        } else {
+            Some(result.round(prec))
+        }
+    }
+
+    pub fn pow(&self, rhs: &Decimal, prec: usize) -> Option<Decimal> {
+        if rhs.is_integer() {
+            if let Some(exp) = rhs.to_bigint().and_then(|e| e.to_i64()) {
+                return self.powi(exp, prec);
+            }
+        }
+        if self.is_zero() && !rhs.is_negative() {
+            return Some(Decimal::zero(prec));
+        }
+        let wp = prec + GUARD_DIGITS;
+        rhs.mul(&self.ln(wp)?, wp).exp(prec)
+    }
+
+    pub fn sqrt(&self, prec: usize) -> Option<Decimal> {
+        if self.is_negative() {
+            return None;
+        }
+        if self.is_zero() {
+            return Some(Decimal::zero(prec));
+        }
+        let mut shift = (2 * (prec + 2)).saturating_sub(digits(&self.mantissa)) as i64;
+        if (self.exp - shift) % 2 != 0 {
+            shift += 1;
+        }
+        let n = &self.mantissa * pow10(shift as usize);
+        let root = n.sqrt();
+        let exp = (self.exp - shift) / 2;
+        if &root * &root == n {
+            Some(Decimal::new(root, exp, prec))
+        } else {
+            Some(Decimal::new(root * 10 + 1, exp - 1, prec))
+        }
+    }
+
+    pub fn pi(prec: usize) -> Decimal {
+        // Machin formula: pi = 16 atan(1/5) - 4 atan(1/239)
+        let wp = prec + GUARD_DIGITS;
+        let scale = pow10(wp);
+        let a = atan_fixed(&(&scale / 5), &scale);
+        let b = atan_fixed(&(&scale / 239), &scale);
+        Decimal::from_fixed(a * 16 - b * 4, wp, prec)
+    }
+
+    fn ln2_fixed(scale: &BigInt) -> BigInt {
+        // ln 2 = 2 atanh(1/3)
+        atanh_fixed(&(scale / 3), scale) * 2
+    }
+
+    fn ln10_fixed(scale: &BigInt) -> BigInt {
+        // ln 10 = 3 ln 2 + ln 1.25 = 3 ln 2 + 2 atanh(1/9)
+        Decimal::ln2_fixed(scale) * 3 + atanh_fixed(&(scale / 9), scale) * 2
+    }
+
+    pub fn e(prec: usize) -> Decimal {
+        Decimal::from_bigint(BigInt::one(), prec).exp(prec).unwrap_or_else(|| Decimal::zero(prec))
+    }
+
+    pub fn ln(&self, prec: usize) -> Option<Decimal> {
+        if self.is_negative() || self.is_zero() {
+            return None;
+        }
+        // x = r * 2^j * 10^k, where r is close to 1
+        let k = self.magnitude();
+        let wp = prec + GUARD_DIGITS + digits(&BigInt::from(k));
+        let scale = pow10(wp);
+        let mut r = Decimal { mantissa: self.mantissa.clone(), exp: self.exp - k, prec: wp }.to_fixed(wp);
+        let limit = &scale * 3 / 2;
+        let mut j = 0;
+        while r > limit {
+            r /= 2;
+            j += 1;
+        }
+        // ln r = 2 atanh((r - 1) / (r + 1))
+        let s = fixed_div(&(&r - &scale), &(&r + &scale), &scale);
+        let res = atanh_fixed(&s, &scale) * 2
+            + Decimal::ln2_fixed(&scale) * j
+            + Decimal::ln10_fixed(&scale) * k;
+        Some(Decimal::from_fixed(res, wp, prec))
+    }
+
+    pub fn log10(&self, prec: usize) -> Option<Decimal> {
+        let wp = prec + GUARD_DIGITS;
+        let ln10 = Decimal::from_fixed(Decimal::ln10_fixed(&pow10(wp)), wp, wp);
+        self.ln(wp)?.div(&ln10, prec)
+    }
+
+    pub fn exp(&self, prec: usize) -> Option<Decimal> {
+        // exp(10^10) has over 4 * 10^9 digits, no point computing it
+        if self.magnitude() > 9 {
+            return if self.is_negative() { Some(Decimal::zero(prec)) } else { None };
+        }
+        // x = k ln 2 + r, exp x = 2^k exp(r)
+        let wp = prec + GUARD_DIGITS + self.magnitude().max(0) as usize;
+        let scale = pow10(wp);
+        let ln2 = Decimal::ln2_fixed(&scale);
+        let x = self.to_fixed(wp);
+        let k = Decimal::from_fixed(x.clone(), wp, wp)
+            .div(&Decimal::from_fixed(ln2.clone(), wp, wp), wp)?
+            .round_to_bigint()?;
+        // halve r a few times and square the result back, series converges faster
+        const HALVINGS: u32 = 8;
+        let r = (x - &ln2 * &k) >> HALVINGS;
+        let mut res = exp_fixed(&r, &scale);
+        for _ in 0..HALVINGS {
+            res = fixed_mul(&res, &res, &scale);
+        }
+        let res = Decimal::from_fixed(res, wp, wp);
+        let k = k.to_i64()?;
+        let two = Decimal::from_bigint(BigInt::from(2), wp);
+        Some(res.mul(&two.powi(k, wp)?, prec))
+    }
+
+    // Reduces x to r in [-pi/4, pi/4] and returns it with quadrant number
+    fn reduce_angle(&self, wp: usize) -> Option<(BigInt, u8)> {
+        let wp = wp + self.magnitude().max(0) as usize;
+        if wp > MAX_PRECISION * 10 {
+            return None;
+        }
+        let half_pi = Decimal::pi(wp).mul(&Decimal::new(BigInt::from(5), -1, wp), wp);
+        let q = self.div(&half_pi, wp)?.round_to_bigint()?;
+        let r = self.sub(&half_pi.mul(&Decimal::from_bigint(q.clone(), wp), wp), wp);
+        let quadrant = q.mod_floor(&BigInt::from(4)).to_u8()?;
+        Some((r.to_fixed(wp), quadrant))
+    }
+
+    pub fn sin(&self, prec: usize) -> Option<Decimal> {
+        let wp = prec + GUARD_DIGITS;
+        let (r, quadrant) = self.reduce_angle(wp)?;
+        let wp = wp + self.magnitude().max(0) as usize;
+        let scale = pow10(wp);
+        let res = match quadrant {
+            0 => sin_cos_fixed(&r, r.clone(), 1, &scale),
+            1 => sin_cos_fixed(&r, scale.clone(), 0, &scale),
+            2 => -sin_cos_fixed(&r, r.clone(), 1, &scale),
+            _ => -sin_cos_fixed(&r, scale.clone(), 0, &scale)
+        };
+        Some(Decimal::from_fixed(res, wp, prec))
+    }
+
+    pub fn cos(&self, prec: usize) -> Option<Decimal> {
+        let wp = prec + GUARD_DIGITS;
+        let (r, quadrant) = self.reduce_angle(wp)?;
+        let wp = wp + self.magnitude().max(0) as usize;
+        let scale = pow10(wp);
+        let res = match quadrant {
+            0 => sin_cos_fixed(&r, scale.clone(), 0, &scale),
+            1 => -sin_cos_fixed(&r, r.clone(), 1, &scale),
+            2 => -sin_cos_fixed(&r, scale.clone(), 0, &scale),
+            _ => sin_cos_fixed(&r, r.clone(), 1, &scale)
+        };
+        Some(Decimal::from_fixed(res, wp, prec))
+    }
+
+    pub fn tan(&self, prec: usize) -> Option<Decimal> {
+        let wp = prec + GUARD_DIGITS;
+        self.sin(wp)?.div(&self.cos(wp)?, prec)
+    }
+
+    pub fn atan(&self, prec: usize) -> Option<Decimal> {
+        let wp = prec + GUARD_DIGITS;
+        let one = Decimal::from_bigint(BigInt::one(), wp);
+        if self.abs().magnitude() >= 0 && self.abs() != one {
+            // atan x = +-pi/2 - atan(1/x) for |x| > 1
+            let half_pi = Decimal::pi(wp).mul(&Decimal::new(BigInt::from(5), -1, wp), wp);
+            let inv = one.div(self, wp)?.atan(wp)?;
+            let res = if self.is_negative() { half_pi.neg().sub(&inv, wp) } else { half_pi.sub(&inv, wp) };
+            return Some(res.round(prec));
+        }
+        // atan x = 2 atan(x / (1 + sqrt(1 + x^2))), applied twice
+        let mut x = self.clone();
+        for _ in 0..2 {
+            let root = one.add(&x.mul(&x, wp), wp).sqrt(wp)?;
+            x = x.div(&one.add(&root, wp), wp)?;
+        }
+        let scale = pow10(wp);
+        Some(Decimal::from_fixed(atan_fixed(&x.to_fixed(wp), &scale) * 4, wp, prec))
+    }
+
+    pub fn asin(&self, prec: usize) -> Option<Decimal> {
+        let wp = prec + GUARD_DIGITS;
+        let one = Decimal::from_bigint(BigInt::one(), wp);
+        match self.abs().sub(&one, wp) {
+            d if d.is_zero() => {
+                let half_pi = Decimal::pi(wp).mul(&Decimal::new(BigInt::from(5), -1, wp), wp);
+                let res = if self.is_negative() { half_pi.neg() } else { half_pi };
+                Some(res.round(prec))
+            },
+            d if !d.is_negative() => None,
+            _ => {
+                let root = one.sub(&self.mul(self, wp), wp).sqrt(wp)?;
+                self.div(&root, wp)?.atan(prec)
+            }
+        }
+    }
+
+    pub fn acos(&self, prec: usize) -> Option<Decimal> {
+        let wp = prec + GUARD_DIGITS;
+        let half_pi = Decimal::pi(wp).mul(&Decimal::new(BigInt::from(5), -1, wp), wp);
+        Some(half_pi.sub(&self.asin(wp)?, prec))
+    }
+}
+
+/// Prints the number in plain notation if it fits, otherwise
+/// in scientific notation, e.g. `1.5e-30`
+impl Display for Decimal {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let sign = if self.is_negative() { "-" } else { "" };
+        let digits = self.mantissa.magnitude().to_str_radix(10);
+        let len = digits.len() as i64;
+        // number of digits before decimal point
+        let point = len + self.exp;
+        if self.is_zero() {
+            write!(f, "0")
+        } else if point > 21.max(len) || point < -5 {
+            let (first, rest) = digits.split_at(1);
+            let dot = if rest.is_empty() { "" } else { "." };
+            write!(f, "{sign}{first}{dot}{rest}e{}", point - 1)
+        } else if self.exp >= 0 {
+            write!(f, "{sign}{digits}{}", "0".repeat(self.exp as usize))
+        } else if point > 0 {
+            let (int_part, frac_part) = digits.split_at(point as usize);
+            write!(f, "{sign}{int_part}.{frac_part}")
+        } else {
+            write!(f, "{sign}0.{}{digits}", "0".repeat((-point) as usize))
+        }
+    }
+}
@@ -0,0 +1,92 @@
+use super::*;
+
+const PREC: usize = 50;
+
+fn dec(s: &str) -> Decimal {
+    Decimal::parse(s, PREC).unwrap()
+}
+
+#[test]
+fn test_parse_and_display() {
+    assert_eq!(dec("1.250").to_string(), "1.25");
+    assert_eq!(dec(".5").to_string(), "0.5");
+    assert_eq!(dec("3e-7").to_string(), "3e-7");
+    assert_eq!(dec("12e3").to_string(), "12000");
+    assert_eq!(dec("1.5e30").to_string(), "1.5e30");
+    assert_eq!(dec("0.000").to_string(), "0");
+    assert!(Decimal::parse("1.2.3", PREC).is_none());
+}
+
+#[test]
+fn test_rounding() {
+    assert_eq!(Decimal::parse("1.25", 2).unwrap().to_string(), "1.2");
+    assert_eq!(Decimal::parse("1.35", 2).unwrap().to_string(), "1.4");
+    assert_eq!(Decimal::parse("9.99", 2).unwrap().to_string(), "10");
+    assert_eq!(Decimal::parse("1.251", 2).unwrap().to_string(), "1.3");
+}
+
+#[test]
+fn test_arithmetic() {
+    assert_eq!(dec("0.1").add(&dec("0.2"), PREC), dec("0.3"));
+    assert_eq!(dec("0.1").sub(&dec("0.3"), PREC), dec("-0.2"));
+    assert_eq!(dec("1.5").mul(&dec("-0.2"), PREC), dec("-0.3"));
+    assert_eq!(dec("1").div(&dec("8"), PREC).unwrap(), dec("0.125"));
+    assert_eq!(dec("1").div(&dec("3"), 5).unwrap(), dec("0.33333"));
+    assert_eq!(dec("2").div(&dec("3"), 5).unwrap(), dec("0.66667"));
+    assert!(dec("1").div(&dec("0"), PREC).is_none());
+    assert_eq!(dec("7.5").rem(&dec("2"), PREC).unwrap(), dec("1.5"));
+    assert_eq!(dec("1e100").add(&dec("1"), PREC), dec("1e100"));
+}
+
+#[test]
+fn test_from_f64() {
+    let d = Decimal::from_f64(0.1, PREC).unwrap();
+    assert_eq!(d.to_string(), "0.1000000000000000055511151231257827021181583404541");
+    assert_eq!(Decimal::from_f64(-2.5, PREC).unwrap(), dec("-2.5"));
+    assert!(Decimal::from_f64(f64::NAN, PREC).is_none());
+}
+
+#[test]
+fn test_pow() {
+    assert_eq!(dec("1.1").powi(2, PREC).unwrap(), dec("1.21"));
+    assert_eq!(dec("2").powi(-2, PREC).unwrap(), dec("0.25"));
+    assert_eq!(dec("2").pow(&dec("0.5"), PREC).unwrap(), dec("2").sqrt(PREC).unwrap());
+    assert_eq!(dec("-8").pow(&dec("3"), PREC).unwrap(), dec("-512"));
+    assert!(dec("-8").pow(&dec("0.5"), PREC).is_none());
+}
+
+#[test]
+fn test_constants() {
+    assert_eq!(Decimal::pi(PREC), dec("3.1415926535897932384626433832795028841971693993751"));
+    assert_eq!(Decimal::e(PREC), dec("2.7182818284590452353602874713526624977572470937"));
+}
+
+#[test]
+fn test_sqrt() {
+    assert_eq!(dec("2").sqrt(PREC).unwrap(), dec("1.4142135623730950488016887242096980785696718753769"));
+    assert_eq!(dec("0.0144").sqrt(PREC).unwrap(), dec("0.12"));
+    assert!(dec("-1").sqrt(PREC).is_none());
+}
+
+#[test]
+fn test_ln_exp() {
+    assert_eq!(dec("2").ln(PREC).unwrap(), dec("0.69314718055994530941723212145817656807550013436026"));
+    assert_eq!(dec("2").log10(PREC).unwrap(), dec("0.30102999566398119521373889472449302676818988146211"));
+    assert_eq!(dec("1").ln(PREC).unwrap(), Decimal::zero(PREC));
+    assert!(dec("0").ln(PREC).is_none());
+    let x = dec("123.456");
+    assert_eq!(x.ln(PREC).unwrap().exp(PREC).unwrap().round(PREC - 2), x);
+    assert_eq!(dec("-1").exp(PREC).unwrap(), dec("0.36787944117144232159552377016146086744581113103177"));
+}
+
+#[test]
+fn test_trig() {
+    assert_eq!(dec("1").sin(PREC).unwrap(), dec("0.84147098480789650665250232163029899962256306079837"));
+    assert_eq!(dec("1").cos(PREC).unwrap(), dec("0.54030230586813971740093660744297660373231042061792"));
+    assert_eq!(dec("1").tan(PREC).unwrap(), dec("1.5574077246549022305069748074583601730872507723815"));
+    assert_eq!(dec("-1").sin(PREC).unwrap(), dec("-0.84147098480789650665250232163029899962256306079837"));
+    assert_eq!(dec("0.5").asin(PREC).unwrap(), dec("0.52359877559829887307710723054658381403286156656252"));
+    let four = dec("4");
+    assert_eq!(dec("1").atan(PREC).unwrap().mul(&four, PREC), Decimal::pi(PREC));
+    assert!(dec("1.5").acos(PREC).is_none());
+}
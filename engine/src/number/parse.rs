@@ -1,5 +1,8 @@
 use super::Number;
+use super::decimal::Decimal;
 use crate::errors::Error;
+use num_bigint::BigInt;
+use num_complex::Complex64;
 
 fn filter_whitespace(c: &char) -> bool {
     !c.is_whitespace() && *c != '_'
@@ -9,10 +12,16 @@ fn sanitize(input: &str) -> String {
     input.chars().filter(filter_whitespace).collect()
 }
 
+fn parse_radix(digits: &str, radix: u32) -> Result<Number, Error> {
+    match BigInt::parse_bytes(digits.as_bytes(), radix) {
+        Some(val) => Ok(val.into()),
+        None => Err("Invalid digit found in number".into())
+    }
+}
+
 pub fn parse_int(input: &str) -> Result<Number, Error> {
     let s = sanitize(input);
-    let val = s.parse().map_err(Error::wrap)?;
-    Ok(Number::Int(val))
+    parse_radix(&s, 10)
 }
 
 pub fn parse_int_bin(input: &str) -> Result<Number, Error> {
@@ -20,8 +29,7 @@ pub fn parse_int_bin(input: &str) -> Result<Number, Error> {
     if !s.starts_with("0b") {
         return Err("Binary number must start with 0b".into());
     }
-    let val = i128::from_str_radix(&s[2..], 2).map_err(Error::wrap)?;
-    Ok(Number::Int(val))
+    parse_radix(&s[2..], 2)
 }
 
 pub fn parse_int_oct(input: &str) -> Result<Number, Error> {
@@ -29,8 +37,7 @@ pub fn parse_int_oct(input: &str) -> Result<Number, Error> {
     if !s.starts_with("0o") {
         return Err("Octal number must start with 0o".into());
     }
-    let val =i128::from_str_radix(&s[2..], 8).map_err(Error::wrap)?;
-    Ok(Number::Int(val))
+    parse_radix(&s[2..], 8)
 }
 
 pub fn parse_int_hex(input: &str) -> Result<Number, Error> {
@@ -38,20 +45,37 @@ pub fn parse_int_hex(input: &str) -> Result<Number, Error> {
     if !s.starts_with("0x") {
         return Err("Hex number must start with 0x".into());
     }
-    let val = i128::from_str_radix(&s[2..], 16).map_err(Error::wrap)?;
-    Ok(Number::Int(val))
+    parse_radix(&s[2..], 16)
 }
 
-pub fn parse_float(input: &str) -> Result<Number, Error> {
+/// Float literal as f64, or as decimal with given number of significant digits
+pub fn parse_float(input: &str, precision: Option<usize>) -> Result<Number, Error> {
     let s = sanitize(input);
+    if let Some(prec) = precision {
+        return match Decimal::parse(&s, prec) {
+            Some(d) => Ok(Number::Decimal(d)),
+            None => Err("Invalid float literal".into())
+        };
+    }
     let val = s.parse().map_err(Error::wrap)?;
     Ok(Number::Float(val))
 }
 
-pub fn parse_const(input: &str) -> Result<Number, Error> {
-    match input {
-        "pi" => Ok(Number::Float(std::f64::consts::PI)),
-        "e" => Ok(Number::Float(std::f64::consts::E)),
+pub fn parse_imaginary(input: &str) -> Result<Number, Error> {
+    let s = sanitize(input);
+    let s = s.strip_suffix('i').unwrap_or(&s);
+    let val = s.parse().map_err(Error::wrap)?;
+    Ok(Complex64::new(0.0, val).into())
+}
+
+/// Constant e, pi or i, e and pi as decimals when precision is given
+pub fn parse_const(input: &str, precision: Option<usize>) -> Result<Number, Error> {
+    match (input, precision) {
+        ("i", _) => Ok(Number::Complex(Complex64::i())),
+        ("pi", Some(prec)) => Ok(Number::Decimal(Decimal::pi(prec))),
+        ("e", Some(prec)) => Ok(Number::Decimal(Decimal::e(prec))),
+        ("pi", None) => Ok(Number::Float(std::f64::consts::PI)),
+        ("e", None) => Ok(Number::Float(std::f64::consts::E)),
         _ => Err("Unknown constant".into())
     }
 }
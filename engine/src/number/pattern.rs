@@ -0,0 +1,113 @@
+#[cfg(test)]
+mod tests;
+
+use num_bigint::BigInt;
+use num_rational::BigRational;
+use num_traits::{Pow, Signed, Zero};
+use super::Number;
+
+// Digits part of a pattern like #,##0.00
+struct Digits {
+    min_int: usize,
+    min_frac: usize,
+    max_frac: usize,
+    grouping: bool
+}
+
+impl Digits {
+    fn parse(digits: &str) -> Option<Digits> {
+        let (int, frac) = digits.split_once('.').unwrap_or((digits, ""));
+        if frac.contains(['.', ',']) || frac.trim_start_matches('0').contains('0') {
+            return None;
+        }
+        Some(Digits {
+            min_int: int.matches('0').count(),
+            min_frac: frac.matches('0').count(),
+            max_frac: frac.len(),
+            grouping: int.contains(',')
+        })
+    }
+}
+
+/// Formats real number by pattern like `0.00`, `#,##0.0` or `$0.00`.
+/// `0` is a digit always printed, `#` a digit printed only when needed,
+/// `,` separates thousands and `%` multiplies the number by 100.
+/// Text around the digits is kept as it is. Exact numbers are rounded
+/// exactly, floats as they are printed, halves away from zero.
+pub fn format(n: &Number, pattern: &str) -> Result<String, String> {
+    let invalid = || format!("Invalid format pattern: {pattern}");
+    let start = pattern.find(['0', '#']).ok_or_else(invalid)?;
+    let len = pattern[start..].find(|c| !matches!(c, '0' | '#' | ',' | '.')).unwrap_or(pattern.len() - start);
+    let (prefix, rest) = pattern.split_at(start);
+    let (digits, suffix) = rest.split_at(len);
+    if suffix.contains(['0', '#']) {
+        return Err(invalid());
+    }
+    let digits = Digits::parse(digits).ok_or_else(invalid)?;
+
+    let mut value = exact(n).ok_or_else(|| format!("Expected real number, got {n}"))?;
+    if prefix.contains('%') || suffix.contains('%') {
+        value *= BigRational::from_integer(BigInt::from(100));
+    }
+    let scale = BigInt::from(10).pow(digits.max_frac);
+    let scaled = (value.abs() * BigRational::from_integer(scale.clone())).round().to_integer();
+    let int_part = (&scaled / &scale).to_string();
+    let frac_part = format!("{:0>width$}", (&scaled % &scale).to_string(), width = digits.max_frac);
+
+    let mut int_part = match int_part.as_str() {
+        "0" if digits.min_int == 0 => String::new(),
+        _ => format!("{int_part:0>width$}", width = digits.min_int)
+    };
+    if digits.grouping {
+        int_part = group(&int_part);
+    }
+    let mut frac_part = frac_part.as_str();
+    while frac_part.len() > digits.min_frac && frac_part.ends_with('0') {
+        frac_part = &frac_part[..frac_part.len() - 1];
+    }
+    let sign = if value.is_negative() && !scaled.is_zero() { "-" } else { "" };
+    let point = if frac_part.is_empty() { "" } else { "." };
+    Ok(format!("{sign}{prefix}{int_part}{point}{frac_part}{suffix}"))
+}
+
+/// Rounds real number exactly to given number of fraction digits,
+/// halves away from zero, like `format` does
+pub fn round(n: &Number, digits: usize) -> Option<Number> {
+    let scale = BigRational::from_integer(BigInt::from(10).pow(digits));
+    Some(((exact(n)? * &scale).round() / scale).into())
+}
+
+// Value of a real number as a fraction. Floats and decimals are taken
+// as they are printed, so that 2.675 is rounded like written.
+fn exact(n: &Number) -> Option<BigRational> {
+    match n {
+        Number::Float(_) | Number::Decimal(_) => parse_decimal(&n.to_string()),
+        Number::Complex(_) | Number::NaN => None,
+        n => n.to_rational()
+    }
+}
+
+// decimal like -12.5 or 1.5e-7
+pub(crate) fn parse_decimal(s: &str) -> Option<BigRational> {
+    let (mantissa, exp) = match s.split_once('e') {
+        Some((mantissa, exp)) => (mantissa, exp.parse::<i32>().ok()?),
+        None => (s, 0)
+    };
+    let (int, frac) = mantissa.split_once('.').unwrap_or((mantissa, ""));
+    let digits: BigInt = format!("{int}{frac}").parse().ok()?;
+    let exp = exp - frac.len() as i32;
+    let ten = BigRational::from_integer(BigInt::from(10));
+    Some(BigRational::from_integer(digits) * ten.pow(exp))
+}
+
+// thousands separated by commas
+fn group(digits: &str) -> String {
+    let mut res = String::new();
+    for (i, c) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i).is_multiple_of(3) {
+            res.push(',');
+        }
+        res.push(c);
+    }
+    res
+}
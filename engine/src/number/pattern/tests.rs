@@ -0,0 +1,31 @@
+use super::*;
+
+fn rational(n: i128, d: i128) -> Number {
+    BigRational::new(BigInt::from(n), BigInt::from(d)).into()
+}
+
+#[test]
+fn test_format() {
+    assert_eq!(format(&Number::Float(12.3456), "0.00").unwrap(), "12.35");
+    assert_eq!(format(&Number::Int(5), "0.00").unwrap(), "5.00");
+    assert_eq!(format(&rational(2, 3), "0.000").unwrap(), "0.667");
+    assert_eq!(format(&rational(-1, 2), "0").unwrap(), "-1");
+    assert_eq!(format(&Number::Float(2.675), "0.00").unwrap(), "2.68");
+    assert_eq!(format(&Number::Float(-0.001), "0.00").unwrap(), "0.00");
+    assert_eq!(format(&Number::Float(1.5), "0.0##").unwrap(), "1.5");
+    assert_eq!(format(&Number::Float(0.25), "#.##").unwrap(), ".25");
+    assert_eq!(format(&Number::Int(7), "000").unwrap(), "007");
+    assert_eq!(format(&Number::Int(1234567), "#,##0").unwrap(), "1,234,567");
+    assert_eq!(format(&Number::Float(-1234.5), "$#,##0.00").unwrap(), "-$1,234.50");
+    assert_eq!(format(&Number::Float(0.125), "0.0%").unwrap(), "12.5%");
+    assert_eq!(format(&Number::Float(1.5e-7), "0.0000000").unwrap(), "0.0000002");
+}
+
+#[test]
+fn test_format_errors() {
+    assert_eq!(format(&Number::Int(1), "abc").unwrap_err(), "Invalid format pattern: abc");
+    assert_eq!(format(&Number::Int(1), "0.0.0").unwrap_err(), "Invalid format pattern: 0.0.0");
+    assert_eq!(format(&Number::Int(1), "0 to 0").unwrap_err(), "Invalid format pattern: 0 to 0");
+    assert_eq!(format(&Number::Int(1), "0.#0").unwrap_err(), "Invalid format pattern: 0.#0");
+    assert!(format(&Number::NaN, "0").is_err());
+}
@@ -0,0 +1,140 @@
+#[cfg(test)]
+mod tests;
+
+use std::fmt::Display;
+use num_bigint::BigInt;
+use num_integer::Integer;
+use num_traits::Signed;
+use super::Number;
+
+/// Base in which integers are printed
+#[derive(Clone, Copy, Debug, PartialEq, Default)]
+pub enum Radix {
+    Bin = 2,
+    Oct = 8,
+    #[default]
+    Dec = 10,
+    Hex = 16
+}
+
+impl Radix {
+    /// Radix for its name, like `hex` in `255 in hex`
+    pub fn parse(name: &str) -> Option<Radix> {
+        match name {
+            "bin" => Some(Radix::Bin),
+            "oct" => Some(Radix::Oct),
+            "dec" => Some(Radix::Dec),
+            "hex" => Some(Radix::Hex),
+            _ => None
+        }
+    }
+
+    fn prefix(self) -> &'static str {
+        match self {
+            Radix::Bin => "0b",
+            Radix::Oct => "0o",
+            Radix::Dec => "",
+            Radix::Hex => "0x"
+        }
+    }
+
+    // digits in a group and the separator, both can be read back as a number
+    fn grouping(self) -> (usize, char) {
+        match self {
+            Radix::Bin | Radix::Hex => (4, '_'),
+            Radix::Oct => (3, '_'),
+            Radix::Dec => (3, ' ')
+        }
+    }
+}
+
+impl Display for Radix {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Radix::Bin => write!(f, "bin"),
+            Radix::Oct => write!(f, "oct"),
+            Radix::Dec => write!(f, "dec"),
+            Radix::Hex => write!(f, "hex")
+        }
+    }
+}
+
+/// Fixed width integer like `u32` or `i8`, values out of its range
+/// wrap around the way they do in a CPU register
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Word {
+    pub bits: u32,
+    pub signed: bool
+}
+
+impl Word {
+    /// Word for its name, `u8` to `u128` or `i8` to `i128`
+    pub fn parse(name: &str) -> Option<Word> {
+        let signed = match name.get(..1)? {
+            "i" => true,
+            "u" => false,
+            _ => return None
+        };
+        match name[1..].parse() {
+            Ok(bits @ (8 | 16 | 32 | 64 | 128)) => Some(Word { bits, signed }),
+            _ => None
+        }
+    }
+
+    /// Integer wrapped into the range of the word, None for other numbers
+    pub fn wrap(&self, n: &Number) -> Option<Number> {
+        let modulus = BigInt::from(1) << self.bits;
+        let mut res = n.to_bigint()?.mod_floor(&modulus);
+        if self.signed && res >= &modulus >> 1 {
+            res -= modulus;
+        }
+        Some(res.into())
+    }
+}
+
+impl Display for Word {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let sign = if self.signed { "i" } else { "u" };
+        write!(f, "{sign}{}", self.bits)
+    }
+}
+
+/// Wraps integer into the word, if there is one,
+/// other numbers are returned unchanged
+pub fn wrap(n: Number, word: Option<Word>) -> Number {
+    match word {
+        Some(word) => word.wrap(&n).unwrap_or(n),
+        None => n
+    }
+}
+
+/// Prints integer in given radix, like `0xff`. When word is given, negative
+/// numbers are printed in two's complement except in decimal. Grouping
+/// separates groups of digits, like in `0xffff_ffff`.
+/// None for numbers that are not integers.
+pub fn format(n: &Number, radix: Radix, word: Option<Word>, grouping: bool) -> Option<String> {
+    let mut int = n.to_bigint()?;
+    if let Some(word) = word {
+        if radix != Radix::Dec && int.is_negative() {
+            int = Word { bits: word.bits, signed: false }.wrap(n)?.to_bigint()?;
+        }
+    }
+    let sign = if int.is_negative() { "-" } else { "" };
+    let mut digits = int.abs().to_str_radix(radix as u32);
+    if grouping {
+        digits = group(&digits, radix.grouping());
+    }
+    Some(format!("{sign}{}{digits}", radix.prefix()))
+}
+
+// separates groups of digits counting from the right
+fn group(digits: &str, (size, separator): (usize, char)) -> String {
+    let mut res = String::new();
+    for (i, c) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i).is_multiple_of(size) {
+            res.push(separator);
+        }
+        res.push(c);
+    }
+    res
+}
@@ -0,0 +1,40 @@
+use super::*;
+
+fn int(n: i128) -> Number {
+    Number::Int(n)
+}
+
+#[test]
+fn test_word() {
+    let u8 = Word::parse("u8").unwrap();
+    assert_eq!(u8, Word { bits: 8, signed: false });
+    assert_eq!(Word::parse("i128"), Some(Word { bits: 128, signed: true }));
+    assert_eq!(Word::parse("u7"), None);
+    assert_eq!(Word::parse("x32"), None);
+    assert_eq!(Word::parse(""), None);
+
+    assert_eq!(u8.wrap(&int(300)), Some(int(44)));
+    assert_eq!(u8.wrap(&int(-1)), Some(int(255)));
+    let i8 = Word::parse("i8").unwrap();
+    assert_eq!(i8.wrap(&int(128)), Some(int(-128)));
+    assert_eq!(i8.wrap(&int(-129)), Some(int(127)));
+    assert_eq!(i8.wrap(&Number::Float(2.5)), None);
+    assert_eq!(i8.to_string(), "i8");
+}
+
+#[test]
+fn test_format() {
+    assert_eq!(format(&int(255), Radix::Hex, None, false).as_deref(), Some("0xff"));
+    assert_eq!(format(&int(5), Radix::Bin, None, false).as_deref(), Some("0b101"));
+    assert_eq!(format(&int(-8), Radix::Oct, None, false).as_deref(), Some("-0o10"));
+    assert_eq!(format(&Number::Float(0.5), Radix::Hex, None, false), None);
+
+    assert_eq!(format(&int(0xfffff), Radix::Hex, None, true).as_deref(), Some("0xf_ffff"));
+    assert_eq!(format(&int(-1234567), Radix::Dec, None, true).as_deref(), Some("-1 234 567"));
+
+    let u16 = Word::parse("u16");
+    assert_eq!(format(&int(-1), Radix::Hex, u16, false).as_deref(), Some("0xffff"));
+    assert_eq!(format(&int(-1), Radix::Dec, u16, false).as_deref(), Some("-1"));
+    assert_eq!(wrap(int(65536), u16), int(0));
+    assert_eq!(wrap(int(65536), None), int(65536));
+}
@@ -33,17 +33,17 @@ fn test_parse_hex() {
 
 #[test]
 fn test_parse_float() {
-    let n = parse_float("1.23").unwrap();
+    let n = parse_float("1.23", None).unwrap();
     if let Number::Float(f) = n {
         assert!((1.23-f).abs() < 1e-10);
     } else {
-        assert!(false);
+        panic!("Not a float");
     }
 }
 
 #[test]
 fn test_parse_float_exp() {
-    let n = parse_float(".23e-1").unwrap();
+    let n = parse_float(".23e-1", None).unwrap();
     match n {
         Number::Float(f) => assert!((0.23e-1-f).abs() < 1e-10),
         _ => panic!("Not a float")
@@ -74,4 +74,173 @@ fn test_rem() {
     let (l, r) = (Number::Int(3), Number::Int(2));
     let res = l % r;
     assert_eq!(res, Number::Int(1));
-}
\ No newline at end of file
+}
+
+#[test]
+fn test_overflow_to_bigint() {
+    let res = Number::Int(i128::MAX) + Number::Int(1);
+    assert_eq!(res.to_string(), "170141183460469231731687303715884105728");
+
+    let res = Number::Int(2) ^ Number::Int(200);
+    assert_eq!(res.to_string(), "1606938044258990275541962092341162602522202993782792835301376");
+}
+
+#[test]
+fn test_bigint_normalizes_to_int() {
+    let big = Number::Int(i128::MAX) * Number::Int(4);
+    assert!(matches!(big, Number::BigInt(_)));
+    assert_eq!(big - Number::Int(i128::MAX) * Number::Int(3), Number::Int(i128::MAX));
+
+    let big = Number::Int(2) ^ Number::Int(130);
+    assert_eq!(big / (Number::Int(2) ^ Number::Int(129)), Number::Int(2));
+}
+
+#[test]
+fn test_bigint_ops() {
+    let big = Number::Int(10) ^ Number::Int(40);
+    assert_eq!(big.clone() % Number::Int(7), Number::Int(4));
+    assert_eq!((-big.clone()).to_string(), "-10000000000000000000000000000000000000000");
+    assert_eq!(-Number::Int(i128::MIN), Number::Int(i128::MAX) + Number::Int(1));
+    assert_eq!((big / Number::Int(3)).to_string(), "10000000000000000000000000000000000000000/3");
+}
+
+#[test]
+fn test_parse_bigint() {
+    let n = parse_int("1 000 000 000 000 000 000 000 000 000 000 000 000 000").unwrap();
+    assert_eq!(n, Number::Int(10) ^ Number::Int(39));
+    let n = parse_int_hex("0x1_0000_0000_0000_0000_0000_0000_0000_0000").unwrap();
+    assert_eq!(n, Number::Int(2) ^ Number::Int(128));
+    let n = parse_int_bin("0b1_0000_0000_0000_0000_0000_0000_0000_0000").unwrap();
+    assert_eq!(n, Number::Int(2) ^ Number::Int(32));
+    let n = parse_int_oct("0o4_000_000_000_000_000_000_000_000_000_000_000_000_000_000").unwrap();
+    assert_eq!(n, Number::Int(2) ^ Number::Int(128));
+}
+
+#[test]
+fn test_pow_too_big() {
+    let res = Number::Int(9) ^ (Number::Int(9) ^ Number::Int(9));
+    assert_eq!(res, Number::NaN);
+}
+
+fn ratio(n: i128, d: i128) -> Number {
+    Number::Int(n) / Number::Int(d)
+}
+
+#[test]
+fn test_rational_div() {
+    let r = ratio(2, 6);
+    assert!(matches!(r, Number::Rational(_)));
+    assert_eq!(r.to_string(), "1/3");
+    assert_eq!(ratio(-7, 2).to_string(), "-7/2");
+    assert_eq!(ratio(6, 3), Number::Int(2));
+}
+
+#[test]
+fn test_rational_arithmetic() {
+    assert_eq!(ratio(1, 3) + ratio(1, 6), ratio(1, 2));
+    assert_eq!(ratio(1, 3) - Number::Int(1), ratio(-2, 3));
+    assert_eq!(ratio(1, 3) * Number::Int(3), Number::Int(1));
+    assert_eq!(ratio(1, 3) / ratio(1, 6), Number::Int(2));
+    assert_eq!(ratio(7, 2) % Number::Int(2), ratio(3, 2));
+    assert_eq!(-ratio(1, 3), ratio(-1, 3));
+    assert_eq!(ratio(1, 3) / Number::Int(0), Number::NaN);
+}
+
+#[test]
+fn test_rational_pow() {
+    assert_eq!(ratio(2, 3) ^ Number::Int(2), ratio(4, 9));
+    assert_eq!(ratio(2, 3) ^ Number::Int(-2), ratio(9, 4));
+    assert_eq!(Number::Int(2) ^ Number::Int(-3), ratio(1, 8));
+    assert_eq!(Number::Int(0) ^ Number::Int(-1), Number::NaN);
+    match Number::Int(4) ^ ratio(1, 2) {
+        Number::Float(f) => assert!((f - 2.0).abs() < 1e-10),
+        _ => panic!("Not a float")
+    }
+}
+
+#[test]
+fn test_rational_with_float() {
+    match ratio(1, 4) + Number::Float(0.5) {
+        Number::Float(f) => assert!((f - 0.75).abs() < 1e-10),
+        _ => panic!("Not a float")
+    }
+}
+
+#[test]
+fn test_rational_display_decimal() {
+    assert_eq!(format!("{:#}", ratio(1, 3)), "0.3333333333");
+    assert_eq!(format!("{:#}", ratio(-1, 4)), "-0.25");
+    assert_eq!(format!("{:#.2}", ratio(2, 3)), "0.67");
+    assert_eq!(format!("{:#.2}", ratio(-1, 1000)), "0");
+    assert_eq!(format!("{:#}", ratio(10, 3)), "3.3333333333");
+}
+
+#[test]
+fn test_decimal_precision() {
+    let dec = |s: &str| parse_float(s, Some(50)).unwrap();
+    let sum = dec("0.1") + dec("0.2");
+    assert_eq!(sum, dec("0.3"));
+    assert_eq!(sum.to_string(), "0.3");
+
+    let n = Number::Int(1) - dec("0.9") * Number::Int(3) / Number::Int(4);
+    assert_eq!(n.to_string(), "0.325");
+
+    // operations keep the precision of decimal arguments
+    let third = Number::Int(1) / parse_float("3.0", Some(5)).unwrap();
+    assert_eq!(third.to_string(), "0.33333");
+    assert_eq!((Number::Int(1) / dec("3.0")).to_string().len(), 52);
+    assert_eq!((third + dec("0.000001")).to_string(), "0.333331");
+
+    // exact numbers stay exact
+    assert_eq!(Number::Int(1) / Number::Int(3), ratio(1, 3));
+    assert!(matches!(parse_float("0.1", None).unwrap(), Number::Float(_)));
+}
+
+fn complex(re: f64, im: f64) -> Number {
+    Number::Complex(num_complex::Complex64::new(re, im))
+}
+
+fn assert_complex(n: Number, re: f64, im: f64) {
+    match n {
+        Number::Complex(c) => assert!((c.re - re).abs() < 1e-10 && (c.im - im).abs() < 1e-10, "got {c}"),
+        _ => panic!("Not a complex: {n}")
+    }
+}
+
+#[test]
+fn test_parse_imaginary() {
+    assert_eq!(parse_imaginary("4i").unwrap(), complex(0.0, 4.0));
+    assert_eq!(parse_imaginary("1 000.5 i").unwrap(), complex(0.0, 1000.5));
+    assert_eq!(parse_const("i", None).unwrap(), complex(0.0, 1.0));
+}
+
+#[test]
+fn test_complex_arithmetic() {
+    let z = Number::Int(3) + complex(0.0, 4.0);
+    assert_eq!(z, complex(3.0, 4.0));
+    assert_eq!(z.to_string(), "3+4i");
+    assert_eq!((Number::Int(3) - complex(0.0, 4.0)).to_string(), "3-4i");
+    assert_eq!(complex(0.0, 1.0) * complex(0.0, 1.0), Number::Float(-1.0));
+    assert_complex(Number::Int(1) / complex(1.0, 1.0), 0.5, -0.5);
+    assert_eq!(-complex(1.0, 2.0), complex(-1.0, -2.0));
+    assert_eq!(complex(1.0, 2.0) % Number::Int(2), Number::NaN);
+    assert_eq!(complex(1.0, 2.0) / Number::Int(0), Number::NaN);
+}
+
+#[test]
+fn test_complex_pow() {
+    assert_complex(Number::Int(-8) ^ ratio(1, 3), 1.0, 3.0f64.sqrt());
+    assert_complex(Number::Float(-4.0) ^ Number::Float(0.5), 0.0, 2.0);
+    assert_eq!(complex(1.0, 1.0) ^ Number::Int(2), complex(0.0, 2.0));
+    assert_eq!(Number::Int(-8) ^ Number::Int(2), Number::Int(64));
+}
+
+#[test]
+fn test_compare() {
+    use std::cmp::Ordering;
+    let half = Number::Int(1) / Number::Int(2);
+    assert_eq!(half.compare(&Number::Float(0.5)), Some(Ordering::Equal));
+    assert_eq!(Number::Int(2).compare(&half), Some(Ordering::Greater));
+    assert_eq!(Number::Float(-1.0).compare(&Number::Float(1.0)), Some(Ordering::Less));
+    assert_eq!(Number::NaN.compare(&Number::Int(1)), None);
+}
@@ -0,0 +1,104 @@
+use crate::errors::Error;
+
+#[cfg(test)]
+mod tests;
+
+/// Accuracy of integrals and roots, relative to their size when above 1
+pub const TOLERANCE: f64 = 1e-10;
+
+// limits that stop the search for functions that do not converge
+const MAX_DEPTH: u32 = 40;
+const MAX_EVALS: usize = 200_000;
+const MAX_ITERATIONS: usize = 100;
+
+struct Integrand<F> {
+    f: F,
+    evals: usize
+}
+
+impl<F: FnMut(f64) -> Result<f64, Error>> Integrand<F> {
+    fn eval(&mut self, x: f64) -> Result<f64, Error> {
+        self.evals += 1;
+        if self.evals > MAX_EVALS {
+            return Err(Error::from("Integral does not converge"));
+        }
+        (self.f)(x)
+    }
+
+    // Simpson's rule on both halves of [a, b], halves are split further
+    // until their sum agrees with the estimate for the whole interval
+    #[allow(clippy::too_many_arguments)]
+    fn adaptive(&mut self, a: f64, b: f64, fa: f64, fm: f64, fb: f64, whole: f64, eps: f64, depth: u32) -> Result<f64, Error> {
+        let m = (a + b) / 2.0;
+        let (flm, frm) = (self.eval((a + m) / 2.0)?, self.eval((m + b) / 2.0)?);
+        let left = simpson(a, m, fa, flm, fm);
+        let right = simpson(m, b, fm, frm, fb);
+        let delta = left + right - whole;
+        if depth == 0 || delta.abs() <= 15.0 * eps {
+            return Ok(left + right + delta / 15.0);
+        }
+        let left = self.adaptive(a, m, fa, flm, fm, left, eps / 2.0, depth - 1)?;
+        let right = self.adaptive(m, b, fm, frm, fb, right, eps / 2.0, depth - 1)?;
+        Ok(left + right)
+    }
+}
+
+fn simpson(a: f64, b: f64, fa: f64, fm: f64, fb: f64) -> f64 {
+    (b - a) / 6.0 * (fa + 4.0 * fm + fb)
+}
+
+/// Definite integral of f from a to b by adaptive Simpson's rule.
+/// Errors without a span come from the method itself.
+pub fn integrate(f: impl FnMut(f64) -> Result<f64, Error>, a: f64, b: f64) -> Result<f64, Error> {
+    if !a.is_finite() || !b.is_finite() {
+        return Err(Error::from("Bounds of integral must be finite"));
+    }
+    if a == b {
+        return Ok(0.0);
+    }
+    let mut integrand = Integrand { f, evals: 0 };
+    let (fa, fm, fb) = (integrand.eval(a)?, integrand.eval((a + b) / 2.0)?, integrand.eval(b)?);
+    let whole = simpson(a, b, fa, fm, fb);
+    let eps = TOLERANCE * whole.abs().max(1.0);
+    let res = integrand.adaptive(a, b, fa, fm, fb, whole, eps, MAX_DEPTH)?;
+    if !res.is_finite() {
+        return Err(Error::from("Integral does not converge"));
+    }
+    Ok(res)
+}
+
+/// Root of f near the guess by Newton's method with numeric derivative.
+/// A step that does not bring f closer to zero or leaves its domain is halved.
+pub fn solve(mut f: impl FnMut(f64) -> Result<f64, Error>, guess: f64) -> Result<f64, Error> {
+    let (mut x, mut fx) = (guess, f(guess)?);
+    for _ in 0..MAX_ITERATIONS {
+        if fx == 0.0 {
+            return Ok(x);
+        }
+        let h = 1e-7 * x.abs().max(1.0);
+        let slope = (f(x + h)? - f(x - h)?) / (2.0 * h);
+        if slope == 0.0 || !slope.is_finite() {
+            return Err(Error::from("No solution found, try another guess"));
+        }
+        // outside the domain of f, like ln(x) for x < 0, counts as NaN
+        let mut at = |x: f64| f(x).unwrap_or(f64::NAN);
+        let mut step = fx / slope;
+        let (mut next, mut f_next) = (x - step, at(x - step));
+        while (f_next.is_nan() || f_next.abs() >= fx.abs()) && step.abs() > f64::EPSILON * x.abs().max(1.0) {
+            step /= 2.0;
+            (next, f_next) = (x - step, at(x - step));
+        }
+        if f_next.is_nan() {
+            return Err(Error::from("No solution found, try another guess"));
+        }
+        let converged = (next - x).abs() <= TOLERANCE * next.abs().max(1.0);
+        (x, fx) = (next, f_next);
+        if converged {
+            break;
+        }
+    }
+    if fx.abs() > TOLERANCE.sqrt() {
+        return Err(Error::from("No solution found, try another guess"));
+    }
+    Ok(x)
+}
@@ -0,0 +1,59 @@
+use super::*;
+
+fn close(a: f64, b: f64) -> bool {
+    (a - b).abs() <= 1e-9 * b.abs().max(1.0)
+}
+
+#[test]
+fn test_integrate() {
+    let res = integrate(|x| Ok(x * x), 0.0, 3.0).unwrap();
+    assert!(close(res, 9.0), "{res}");
+    let res = integrate(|x| Ok(x.sin()), 0.0, std::f64::consts::PI).unwrap();
+    assert!(close(res, 2.0), "{res}");
+    let res = integrate(|x| Ok((-x * x).exp()), -10.0, 10.0).unwrap();
+    assert!(close(res, std::f64::consts::PI.sqrt()), "{res}");
+    let res = integrate(|x| Ok(1.0 / x), 2.0, 1.0).unwrap();
+    assert!(close(res, -std::f64::consts::LN_2), "{res}");
+    let res = integrate(|x| Ok(x.abs()), -1.0, 2.0).unwrap();
+    assert!(close(res, 2.5), "{res}");
+    assert_eq!(integrate(Ok, 1.0, 1.0).unwrap(), 0.0);
+}
+
+#[test]
+fn test_integrate_errors() {
+    assert!(integrate(Ok, 0.0, f64::INFINITY).is_err());
+    assert!(integrate(|x| Ok(1.0 / x), 0.0, 1.0).is_err());
+    let err = integrate(|_| Err(Error::from("Unknown variable y")), 0.0, 1.0).unwrap_err();
+    assert_eq!(err.message, "Unknown variable y");
+}
+
+#[test]
+fn test_solve() {
+    let res = solve(|x| Ok(x * x - 2.0), 1.0).unwrap();
+    assert!(close(res, std::f64::consts::SQRT_2), "{res}");
+    let res = solve(|x| Ok(x * x - 2.0), -1.0).unwrap();
+    assert!(close(res, -std::f64::consts::SQRT_2), "{res}");
+    let res = solve(|x| Ok(x.cos() - x), 0.0).unwrap();
+    assert!(close(res, 0.7390851332151607), "{res}");
+    let res = solve(|x| Ok(x.exp() - 10.0), 0.0).unwrap();
+    assert!(close(res, 10f64.ln()), "{res}");
+    let res = solve(|x| Ok(x.atan()), 2.0).unwrap();
+    assert!(close(res, 0.0), "{res}");
+    let res = solve(|x| Ok((x - 1.0).powi(2)), 3.0).unwrap();
+    assert!((res - 1.0).abs() < 1e-4, "{res}");
+}
+
+#[test]
+fn test_solve_domain() {
+    let ln = |x: f64| if x > 0.0 { Ok(x.ln()) } else { Err(Error::from("Expected real number")) };
+    let res = solve(ln, 5.0).unwrap();
+    assert!(close(res, 1.0), "{res}");
+    let res = solve(|x| Ok(x.sqrt() - 1.0), 9.0).unwrap();
+    assert!(close(res, 1.0), "{res}");
+}
+
+#[test]
+fn test_solve_errors() {
+    assert!(solve(|x| Ok(x * x + 1.0), 1.0).is_err());
+    assert!(solve(|_| Ok(1.0), 0.0).is_err());
+}
@@ -1,39 +1,73 @@
 #[cfg(test)]
 mod tests;
 
+use crate::ast::{Node, NodeKind};
+use crate::config::Config;
+use crate::date::{Date, Duration};
 use crate::eval::{Expression, Op, Ident};
-use crate::{function, lexer};
+use crate::{function, lexer, unit};
+use crate::function::{Arity, FunctionRegistry};
+use crate::function::angle::Angle;
+use crate::unit::Unit;
 use crate::number::Number;
 use crate::number::parse;
+use crate::number::radix::{Radix, Word};
 use crate::errors::{Error, Span};
 use std::mem;
+use std::rc::Rc;
 
 pub struct Parser<'src> {
+    /// Postfix program for Calculator, lowered from the tree
     pub program: Vec<Expression>,
+    /// Expression tree, None for empty input
+    pub ast: Option<Node>,
+    /// Base requested for the result with `in hex`, None if not given
+    pub radix: Option<Radix>,
     source: &'src str,
     tokens: lexer::Tokenizer<'src>,
+    prev_token: lexer::Token,
     current_token: lexer::Token,
-    next_token: lexer::Token
+    next_token: lexer::Token,
+    functions: Rc<FunctionRegistry>,
+    config: Config
 }
 
 static ERR_UNEXP: &str = "Unexpected token";
 static ERR_EOF: &str = "Unexpected end of input";
+// functions built into the grammar, most of them with arguments
+// that are not evaluated before the call
+static SPECIAL_FORMS: [&str; 7] = ["diff", "integrate", "solve", "if", "factor", "map", "filter"];
 
 impl<'src> Parser<'src> {
     pub fn new(source: &'src str) -> Parser<'src> {
+        Self::with_config(source, &Config::default())
+    }
+
+    /// Parser that reads float literals and constants with the precision
+    /// of the session, like a calculator with `precision 50`
+    pub fn with_config(source: &'src str, config: &Config) -> Parser<'src> {
+        Self::with_functions(source, config, function::builtins())
+    }
+
+    /// Parser of a session that resolves function calls in the given registry
+    pub fn with_functions(source: &'src str, config: &Config, functions: Rc<FunctionRegistry>) -> Parser<'src> {
         Parser {
             source,
             tokens: lexer::Tokenizer::new(source),
             program: vec![],
+            ast: None,
+            radix: None,
+            prev_token: Default::default(),
             current_token: Default::default(),
             next_token: Default::default(),
+            functions,
+            config: config.clone()
         }
     }
 
     fn bump(&mut self) {
-        self.current_token = mem::replace(
-            &mut self.next_token, 
-            self.tokens.next_token());
+        let next = mem::replace(&mut self.next_token, self.tokens.next_token());
+        self.prev_token = mem::replace(&mut self.current_token, next);
     }
 
     fn init(&mut self) {
@@ -42,197 +76,756 @@ impl<'src> Parser<'src> {
     }
 
     fn current_token_value(&self) -> &'src str {
-        &self.current_token.value_from(&self.source)
+        self.current_token.value_from(self.source)
     }
 
+    // tokens include surrounding whitespace, span should not
     fn current_span(&self) -> Span {
-        Span::new(self.current_token.start, self.current_token.len)
+        let value = self.current_token_value();
+        let leading = value.len() - value.trim_start().len();
+        Span::new(self.current_token.start + leading, value.trim().len())
+    }
+
+    // span from the start of given token to the end of the previous one
+    fn span_from(&self, start: &Span) -> Span {
+        let text = &self.source[start.pos..self.current_token.start];
+        Span::new(start.pos, text.trim_end().len())
     }
 
-    fn error(&self, message: &str) -> Result<bool, Error> {
+    fn error<T>(&self, message: &str) -> Result<T, Error> {
         Err(Error::new(message, self.current_span()))
     }
 
-    // exp: assign | exp1 | empty
-    // assign: id = exp1
+    // line: exp | exp in radix
+    // radix: bin | oct | dec | hex
+    // exp: assign | define | or | empty
+    // assign: id = or
+    // define: id(params) = or
+    // params: id | id, params | empty
+    // or: and || or | and
+    // and: eq && and | eq
+    // eq: cmp == cmp | cmp != cmp | cmp
+    // cmp: bor op_cmp bor | bor
+    // op_cmp: < | <= | > | >=
+    // bor: bxor | bor | bxor
+    // bxor: band xor bxor | band
+    // band: shift & band | shift
+    // shift: conv op_shift shift | conv
+    // op_shift: << | >> | >>>
+    // conv: exp1 to unit | exp1 in unit | exp1 as word | exp1 as % of exp1 | exp1
+    // word: u8 | u16 | u32 | u64 | u128 | i8 | i16 | i32 | i64 | i128
     // exp1: exp2 op1 exp1 | exp2
     // op1: + | -
-    // exp2: exp3 op2 exp2 | exp3
+    // exp2: exp3 op2 exp2 | exp3 exp2 | postfix% of exp2 | exp3
     // op2: * | / | %
     // exp3: fact op3 exp3 | fact
     // op3: ^
-    // fact: +fact | -fact | (exp1) | func | id | literal
-    // func: id(exp1)
+    // fact: +fact | -fact | !fact | ~fact | postfix
+    // postfix: atom | postfix! | postfix% | postfix angle | postfix[or]
+    // angle: ° | deg | rad | grad
+    // atom: (or) unit? | [args] unit? | func | id | string | date | time | literal unit?
+    // func: id(args) | diff(or, id) | diff(or, id, int) | if(or, or, or)
+    //     | integrate(or, id, or, or) | solve(or, id, or) | solve(or, or) | factor(or)
+    //     | map(or, id, or) | filter(or, id, or) | len(or) | sort(or) | range(args)
+    //     | det(or) | inv(or) | transpose(or) | rank(or) | eig(or) | identity(or) | zeros(args)
+    // args: or | or, args | empty
+    // unit: unit_term | unit_term * unit | unit_term / unit
+    // unit_term: unit_id | unit_id ^ int | unit_id ^ -int
 
     pub fn parse(&mut self) -> Result<bool, Error> {
         self.init();
-        match self.parse_exp() {
-            Err(e) => {
+        let res = self.parse_exp().and_then(|node| {
+            if node.is_some() && self.is_keyword("in") {
+                self.bump();
+                self.radix = Radix::parse(self.current_token_value().trim());
+                if self.radix.is_none() {
+                    return self.error("Expected bin, oct, dec or hex");
+                }
+                self.bump();
+            }
+            match self.current_token.kind {
+                lexer::TokenKind::Eof => Ok(node),
+                _ => self.error(ERR_UNEXP)
+            }
+        });
+        match res {
+            Ok(node) => {
+                self.program = node.as_ref().map(Node::lower).unwrap_or_default();
+                self.ast = node;
+                Ok(self.ast.is_some())
+            },
+            // errors of number parsing carry no span, they belong to current token
+            Err(e) if e.span.len == 0 => {
                 Err(Error::new(&e.message, self.current_span()))
             },
-            Ok(b) => Ok(b)
+            Err(e) => Err(e)
         }
     }
 
-
-    // exp: assign | exp1 | empty
-    pub fn parse_exp(&mut self) -> Result<bool, Error> {
+    // exp: assign | define | or | empty
+    pub fn parse_exp(&mut self) -> Result<Option<Node>, Error> {
         match self.current_token.kind {
-            lexer::TokenKind::Eof => Ok(false),
+            lexer::TokenKind::Eof => Ok(None),
             lexer::TokenKind::Ident if self.next_token.kind == lexer::TokenKind::Assign => {
-                self.parse_assign()
+                self.parse_assign().map(Some)
             },
-            _ => self.parse_exp1()
+            lexer::TokenKind::Ident if self.is_definition() => self.parse_define().map(Some),
+            _ => self.parse_or()
         }
     }
+    // Looks ahead for id(id, ...) = to tell definition from function call
+    fn is_definition(&self) -> bool {
+        if self.next_token.kind != lexer::TokenKind::Lpar {
+            return false;
+        }
+        let mut tokens = self.tokens.clone();
+        let mut kind = tokens.next_token().kind;
+        while kind != lexer::TokenKind::Rpar {
+            if kind != lexer::TokenKind::Ident {
+                return false;
+            }
+            kind = match tokens.next_token().kind {
+                lexer::TokenKind::Coma => match tokens.next_token().kind {
+                    lexer::TokenKind::Ident => lexer::TokenKind::Ident,
+                    _ => return false
+                },
+                lexer::TokenKind::Rpar => break,
+                _ => return false
+            };
+        }
+        tokens.next_token().kind == lexer::TokenKind::Assign
+    }
 
-    // exp1: exp2 op1 exp1 | exp2
-    // op1: + | -
-    fn parse_exp1(&mut self) -> Result<bool, Error> {
-        let has = self.parse_exp2()?;
-        match self.current_token.kind {
-            kind @(lexer::TokenKind::Add | lexer::TokenKind::Sub) => {
+    // define: id(params) = or
+    // params: id | id, params | empty
+    fn parse_define(&mut self) -> Result<Node, Error> {
+        let name = self.current_token_value().trim();
+        let start = self.current_span();
+        if self.functions.contains(name) || SPECIAL_FORMS.contains(&name) {
+            return self.error(&format!("Cannot redefine built-in function {name}"));
+        }
+        self.bump();
+        self.bump(); // skip (
+        let mut params = vec![];
+        while self.current_token.kind == lexer::TokenKind::Ident {
+            let param = Ident::new(self.current_token_value().trim());
+            if params.contains(&param) {
+                return self.error("Duplicate parameter");
+            }
+            params.push(param);
+            self.bump();
+            if self.current_token.kind == lexer::TokenKind::Coma {
                 self.bump();
-                if self.parse_exp1()? {
-                    self.parse_binary_op(kind)?;
-                    Ok(true)
-                } else {
-                    self.error(ERR_EOF)
-                }
+            }
+        }
+        self.bump(); // skip )
+        self.bump(); // skip =
+        let body = match self.parse_or()? {
+            Some(body) => body,
+            None => return self.error("Missing function body")
+        };
+        let kind = NodeKind::Define(Ident::new(name), params, Box::new(body));
+        Ok(Node::new(kind, self.span_from(&start)))
+    }
+
+    // or: and || or | and
+    // and: eq && and | eq
+    // the right side is evaluated only when the left one does not decide
+    fn parse_or(&mut self) -> Result<Option<Node>, Error> {
+        self.parse_left_assoc(&[lexer::TokenKind::Or], Self::parse_and)
+    }
+
+    fn parse_and(&mut self) -> Result<Option<Node>, Error> {
+        self.parse_left_assoc(&[lexer::TokenKind::And], Self::parse_eq)
+    }
+
+    // eq: cmp == cmp | cmp != cmp | cmp
+    fn parse_eq(&mut self) -> Result<Option<Node>, Error> {
+        let kinds = [lexer::TokenKind::Eq, lexer::TokenKind::Ne];
+        self.parse_comparison(&kinds, Self::parse_cmp)
+    }
+
+    // cmp: bor op_cmp bor | bor
+    // op_cmp: < | <= | > | >=
+    fn parse_cmp(&mut self) -> Result<Option<Node>, Error> {
+        let kinds = [lexer::TokenKind::Lt, lexer::TokenKind::Le, lexer::TokenKind::Gt, lexer::TokenKind::Ge];
+        self.parse_comparison(&kinds, Self::parse_bit_or)
+    }
+
+    // comparisons do not chain, 1 < x < 3 is an error
+    fn parse_comparison(&mut self, kinds: &[lexer::TokenKind], operand: fn(&mut Self) -> Result<Option<Node>, Error>) -> Result<Option<Node>, Error> {
+        let lhs = operand(self)?;
+        if !kinds.contains(&self.current_token.kind) {
+            return Ok(lhs);
+        }
+        let kind = self.current_token.kind;
+        let span = self.current_span();
+        self.bump();
+        let rhs = match operand(self)? {
+            Some(rhs) => rhs,
+            None => return self.error(ERR_EOF)
+        };
+        if kinds.contains(&self.current_token.kind) {
+            return self.error("Comparisons cannot be chained, use &&");
+        }
+        self.parse_binary_op(kind, span, lhs, rhs).map(Some)
+    }
+
+    // bor: bxor | bor | bxor
+    // bxor: band xor bxor | band
+    // band: shift & band | shift
+    fn parse_bit_or(&mut self) -> Result<Option<Node>, Error> {
+        self.parse_left_assoc(&[lexer::TokenKind::BitOr], Self::parse_xor)
+    }
+
+    fn parse_xor(&mut self) -> Result<Option<Node>, Error> {
+        self.parse_left_assoc(&[lexer::TokenKind::Xor], Self::parse_bit_and)
+    }
+
+    fn parse_bit_and(&mut self) -> Result<Option<Node>, Error> {
+        self.parse_left_assoc(&[lexer::TokenKind::BitAnd], Self::parse_shift)
+    }
+
+    // shift: conv op_shift shift | conv
+    // op_shift: << | >> | >>>
+    fn parse_shift(&mut self) -> Result<Option<Node>, Error> {
+        let kinds = [lexer::TokenKind::Shl, lexer::TokenKind::Shr, lexer::TokenKind::Ushr];
+        self.parse_left_assoc(&kinds, Self::parse_conv)
+    }
+
+    // operators of these levels are left associative, so a & b & c is (a & b) & c
+    fn parse_left_assoc(&mut self, kinds: &[lexer::TokenKind], operand: fn(&mut Self) -> Result<Option<Node>, Error>) -> Result<Option<Node>, Error> {
+        let mut lhs = operand(self)?;
+        while kinds.contains(&self.current_token.kind) {
+            let kind = self.current_token.kind;
+            let span = self.current_span();
+            self.bump();
+            let rhs = match operand(self)? {
+                Some(rhs) => rhs,
+                None => return self.error(ERR_EOF)
+            };
+            lhs = Some(self.parse_binary_op(kind, span, lhs, rhs)?);
+        }
+        Ok(lhs)
+    }
+
+    // conv: exp1 to unit | exp1 in unit | exp1 as word | exp1 as % of exp1 | exp1
+    // `in` followed by a base, like in hex, is handled by parse
+    fn parse_conv(&mut self) -> Result<Option<Node>, Error> {
+        let node = match self.parse_exp1()? {
+            Some(node) => node,
+            None => return Ok(None)
+        };
+        if self.is_keyword("to") || (self.is_keyword("in") && self.is_unit(&self.next_token)) {
+            let span = self.current_span();
+            self.bump();
+            let unit = self.parse_unit()?;
+            let full = self.span_from(&node.span);
+            return Ok(Some(Node::new(NodeKind::Convert(Box::new(node), unit, span), full)));
+        }
+        // x as % of y is x/y%, the percentage of y that x is
+        if self.is_keyword("as") && self.next_token.kind == lexer::TokenKind::Mod {
+            let span = self.current_span();
+            self.bump();
+            let percent = self.current_span();
+            self.bump();
+            if !self.is_keyword("of") {
+                return self.error("Expected of");
+            }
+            self.bump();
+            let whole = match self.parse_exp1()? {
+                Some(whole) => whole,
+                None => return self.error(ERR_EOF)
+            };
+            let whole_span = whole.span.clone();
+            let whole = Node::new(NodeKind::Unary(Op::Percent, percent, Box::new(whole)), whole_span);
+            let full = self.span_from(&node.span);
+            return Ok(Some(Node::new(NodeKind::Binary(Op::Div, span, Box::new(node), Box::new(whole)), full)));
+        }
+        if self.is_keyword("as") {
+            let span = self.current_span();
+            self.bump();
+            let word = match Word::parse(self.current_token_value().trim()) {
+                Some(word) => word,
+                None => return self.error("Expected integer type like u32 or i8")
+            };
+            self.bump();
+            let full = self.span_from(&node.span);
+            return Ok(Some(Node::new(NodeKind::Cast(Box::new(node), word, span), full)));
+        }
+        Ok(Some(node))
+    }
+
+    fn is_keyword(&self, keyword: &str) -> bool {
+        self.current_token.kind == lexer::TokenKind::Ident
+            && self.current_token_value().trim() == keyword
+    }
+
+    // `as` is also attosecond, but not when a type or % follows
+    fn is_cast(&self) -> bool {
+        self.is_keyword("as") && (Word::parse(self.next_token.value_from(self.source).trim()).is_some()
+            || self.next_token.kind == lexer::TokenKind::Mod)
+    }
+
+    // 2pi, 3(x + 1) and (a)(b) are products without *. The left side ends
+    // with a number or a closing parenthesis and the right side starts with
+    // a name or an opening parenthesis. x(2) stays a call and 2 3 one number.
+    fn is_implicit_mul(&self) -> bool {
+        let after = matches!(self.prev_token.kind,
+            lexer::TokenKind::Rpar | lexer::TokenKind::Literal(
+                lexer::LiteralKind::Int(_) | lexer::LiteralKind::Float | lexer::LiteralKind::Imaginary));
+        let before = match self.current_token.kind {
+            lexer::TokenKind::Lpar => true,
+            lexer::TokenKind::Ident => !matches!(self.current_token_value().trim(), "to" | "in" | "as" | "of"),
+            _ => false
+        };
+        after && before
+    }
+
+    // % is percent when no operand follows, like in 15% or 15% of 80,
+    // and modulo otherwise, like in 7 % 3. Signs are taken as operators,
+    // 200 + 15% - 10 is 220 and modulo of negative number needs 7 % (-2).
+    fn is_percent(&self) -> bool {
+        match self.next_token.kind {
+            lexer::TokenKind::Literal(_) | lexer::TokenKind::Lpar | lexer::TokenKind::Lbracket
+                | lexer::TokenKind::Not | lexer::TokenKind::BitNot => false,
+            lexer::TokenKind::Ident => {
+                let word = self.next_token.value_from(self.source).trim();
+                matches!(word, "of" | "to" | "in" | "as")
             },
-            _ => Ok(has),
+            _ => true
         }
     }
 
-    // id = exp1
-    fn parse_assign(&mut self) -> Result<bool, Error> {
+    // exp1: exp2 op1 exp1 | exp2
+    // op1: + | -
+    // operators are left associative, so 1-2-3 is (1-2)-3
+    fn parse_exp1(&mut self) -> Result<Option<Node>, Error> {
+        let mut lhs = self.parse_exp2()?;
+        while let kind @(lexer::TokenKind::Add | lexer::TokenKind::Sub) = self.current_token.kind {
+            let span = self.current_span();
+            self.bump();
+            let rhs = match self.parse_exp2()? {
+                Some(rhs) => rhs,
+                None => return self.error(ERR_EOF)
+            };
+            lhs = Some(self.parse_binary_op(kind, span, lhs, rhs)?);
+        }
+        Ok(lhs)
+    }
+
+    // id = or
+    fn parse_assign(&mut self) -> Result<Node, Error> {
         let ident = self.current_token_value().trim();
+        let span = self.current_span();
+        // i, e and pi are always read as constants, a variable of the name could not be read back
+        if parse::parse_const(ident, None).is_ok() {
+            return Err(Error::new(&format!("Cannot assign to constant {ident}"), span));
+        }
         self.bump();
         self.bump(); // skip =
-        if self.parse_exp1()? {
-            self.program.push(Expression::Assign(Ident::new(ident)));
-            Ok(true)
-        } else {
-            self.error("Missing right side of assignment")
+        match self.parse_or()? {
+            Some(value) => {
+                let full = span.to(&value.span);
+                Ok(Node::new(NodeKind::Assign(Ident::new(ident), span, Box::new(value)), full))
+            },
+            None => self.error("Missing right side of assignment")
         }
     }
 
-    // exp2: exp3 op2 exp2| exp3
+    // exp2: exp3 op2 exp2 | exp3 exp2 | postfix% of exp2 | exp3
     // op2: * | / | %
-    fn parse_exp2(&mut self) -> Result<bool, Error> {
-        let has = self.parse_exp3()?;
-        match self.current_token.kind {
-            kind @(lexer::TokenKind::Mul | lexer::TokenKind::Div | lexer::TokenKind::Mod) => {
-                self.bump();
-                if self.parse_exp2()? {
-                    self.parse_binary_op(kind)?;
-                    Ok(true)
-                } else {
-                    self.error(ERR_EOF)
-                }
-            },
-            _ => Ok(has)
+    // operators are left associative, so 1/3*3 is (1/3)*3
+    // exp3 exp2 is implicit multiplication, see is_implicit_mul. It is a * with
+    // the same precedence, so 2x^2 is 2*(x^2), -2x is (-2)*x and 1/2x is (1/2)*x
+    fn parse_exp2(&mut self) -> Result<Option<Node>, Error> {
+        let mut lhs = self.parse_exp3()?;
+        loop {
+            let span = self.current_span();
+            let kind = match self.current_token.kind {
+                kind @(lexer::TokenKind::Mul | lexer::TokenKind::Div | lexer::TokenKind::Mod) => {
+                    self.bump();
+                    kind
+                },
+                // 50% of 80 is 50% * 80
+                _ if self.is_keyword("of") && matches!(&lhs, Some(Node { kind: NodeKind::Unary(Op::Percent, ..), .. })) => {
+                    self.bump();
+                    lexer::TokenKind::Mul
+                },
+                // implicit multiplication has no token to skip, errors point at its right side
+                _ if lhs.is_some() && self.is_implicit_mul() => lexer::TokenKind::Mul,
+                _ => break
+            };
+            let rhs = match self.parse_exp3()? {
+                Some(rhs) => rhs,
+                None => return self.error(ERR_EOF)
+            };
+            lhs = Some(self.parse_binary_op(kind, span, lhs, rhs)?);
         }
+        Ok(lhs)
     }
 
     // exp3: fact op3 exp3 | fact
     // op3: ^
-    fn parse_exp3(&mut self) -> Result<bool, Error> {
-        let has = self.parse_fact()?;
+    fn parse_exp3(&mut self) -> Result<Option<Node>, Error> {
+        let lhs = self.parse_fact()?;
         match self.current_token.kind {
             kind @lexer::TokenKind::Pow => {
+                let span = self.current_span();
                 self.bump();
-                if self.parse_exp3()? {
-                    self.parse_binary_op(kind)?;
-                    Ok(true)
-                } else {
-                    self.error(ERR_EOF)
+                match self.parse_exp3()? {
+                    Some(rhs) => self.parse_binary_op(kind, span, lhs, rhs).map(Some),
+                    None => self.error(ERR_EOF)
                 }
             },
-            _ => Ok(has)
+            _ => Ok(lhs)
         }
     }
 
-    // fact: +fact | -fact | (exp1) | func | id | literal
-    // func: id(exp1)
-    fn parse_fact(&mut self) -> Result<bool, Error> {
+    // fact: +fact | -fact | !fact | ~fact | postfix
+    // prefix operators apply after postfix ones, -3! is -(3!)
+    fn parse_fact(&mut self) -> Result<Option<Node>, Error> {
         match self.current_token.kind {
-            kind@ (lexer::TokenKind::Add |lexer::TokenKind::Sub) => {
+            kind@ (lexer::TokenKind::Add | lexer::TokenKind::Sub | lexer::TokenKind::Not | lexer::TokenKind::BitNot) => {
+                let span = self.current_span();
                 self.bump();
-                if !self.parse_fact()? {
-                    return self.error("Unary operator needs expression");
+                match self.parse_fact()? {
+                    Some(arg) => self.parse_unary_op(kind, span, arg).map(Some),
+                    None => self.error("Unary operator needs expression")
                 }
-                self.parse_unary_op(kind)
             }
+            _ => self.parse_postfix()
+        }
+    }
+
+    // postfix: atom | postfix! | postfix% | postfix angle | postfix[or]
+    // angle: ° | deg | rad | grad
+    fn parse_postfix(&mut self) -> Result<Option<Node>, Error> {
+        let mut node = match self.parse_atom()? {
+            Some(node) => node,
+            None => return Ok(None)
+        };
+        loop {
+            node = match self.current_token.kind {
+                lexer::TokenKind::Not => {
+                    let span = self.current_span();
+                    self.bump();
+                    let full = node.span.to(&span);
+                    Node::new(NodeKind::Unary(Op::Fact, span, Box::new(node)), full)
+                },
+                lexer::TokenKind::Mod if self.is_percent() => {
+                    let span = self.current_span();
+                    self.bump();
+                    let full = node.span.to(&span);
+                    Node::new(NodeKind::Unary(Op::Percent, span, Box::new(node)), full)
+                },
+                lexer::TokenKind::Degree => self.parse_angle(node, Angle::Deg),
+                lexer::TokenKind::Ident if self.is_angle() => {
+                    let unit = Angle::parse(self.current_token_value().trim()).unwrap_or_default();
+                    self.parse_angle(node, unit)
+                },
+                lexer::TokenKind::Lbracket => self.parse_index(node)?,
+                _ => return Ok(Some(node))
+            };
+        }
+    }
+
+    // postfix° | postfix deg | postfix rad | postfix grad
+    fn parse_angle(&mut self, node: Node, unit: Angle) -> Node {
+        let span = self.current_span();
+        self.bump();
+        let full = node.span.to(&span);
+        Node::new(NodeKind::Unary(Op::Angle(unit), span, Box::new(node)), full)
+    }
+
+    // deg, rad and grad after a value are angles, like units they are
+    // names of functions or variables when followed by ( or =
+    fn is_angle(&self) -> bool {
+        Angle::parse(self.current_token_value().trim()).is_some()
+            && !matches!(self.next_token.kind, lexer::TokenKind::Lpar | lexer::TokenKind::Assign)
+    }
+
+    // postfix[or]
+    fn parse_index(&mut self, list: Node) -> Result<Node, Error> {
+        let start = self.current_span();
+        self.bump();
+        let i = match self.current_token.kind {
+            lexer::TokenKind::Rbracket => None,
+            _ => self.parse_or()?
+        };
+        let i = match i {
+            Some(i) => i,
+            None => return self.error("Missing index")
+        };
+        if self.current_token.kind != lexer::TokenKind::Rbracket {
+            return self.error("Missing closing bracket");
+        }
+        let span = start.to(&self.current_span());
+        self.bump();
+        let full = list.span.to(&span);
+        Ok(Node::new(NodeKind::Index(Box::new(list), Box::new(i), span), full))
+    }
+
+    // [args] unit?
+    fn parse_list(&mut self) -> Result<Node, Error> {
+        let start = self.current_span();
+        self.bump();
+        let mut items = vec![];
+        while self.current_token.kind != lexer::TokenKind::Rbracket {
+            match self.parse_or()? {
+                Some(item) => items.push(item),
+                None => return self.error(&format!("Element {} is empty", items.len() + 1))
+            }
+            match self.current_token.kind {
+                lexer::TokenKind::Coma => self.bump(),
+                lexer::TokenKind::Rbracket => (),
+                _ => return self.error("Missing closing bracket")
+            }
+        }
+        let full = start.to(&self.current_span());
+        self.bump();
+        self.parse_unit_suffix(Node::new(NodeKind::List(items), full))
+    }
+
+    // atom: (or) unit? | [args] unit? | func | id | string | date | time | literal unit?
+    // func: id(args)
+    fn parse_atom(&mut self) -> Result<Option<Node>, Error> {
+        match self.current_token.kind {
+            lexer::TokenKind::Lbracket => self.parse_list().map(Some),
             lexer::TokenKind::Lpar => {
+                let start = self.current_span();
                 self.bump();
-                let has = self.parse_exp1()?;
+                let node = self.parse_or()?;
                 if self.current_token.kind != lexer::TokenKind::Rpar {
                     return self.error("Missing closing parenthesis");
                 };
                 self.bump();
-                Ok(has)
+                match node {
+                    // span of the node includes the parentheses
+                    Some(node) => {
+                        let node = Node::new(node.kind, self.span_from(&start));
+                        self.parse_unit_suffix(node).map(Some)
+                    },
+                    None => Ok(None)
+                }
+            },
+            lexer::TokenKind::Literal(lexer::LiteralKind::String) => self.parse_string().map(Some),
+            lexer::TokenKind::Literal(kind @ (lexer::LiteralKind::Date | lexer::LiteralKind::Time)) => {
+                self.parse_date(kind).map(Some)
             },
             lexer::TokenKind::Literal(kind) => {
-                self.parse_literal(kind)
+                let node = self.parse_literal(kind)?;
+                self.parse_unit_suffix(node).map(Some)
             },
             lexer::TokenKind::Ident => {
                 match self.next_token.kind {
-                    lexer::TokenKind::Lpar => self.parse_function(),
-                    lexer::TokenKind::Assign => self.parse_assign(),
-                    _ => self.parse_ident()
+                    lexer::TokenKind::Lpar => self.parse_function().map(Some),
+                    lexer::TokenKind::Assign => self.parse_assign().map(Some),
+                    _ => self.parse_ident().map(Some)
                 }
             }
-            lexer::TokenKind::Eof => Ok(false),
+            lexer::TokenKind::Eof => Ok(None),
             _ => self.error(ERR_UNEXP)
         }
     }
 
-    fn parse_function(&mut self) -> Result<bool, Error> {
-        let f = function::parse_function(self.current_token_value())?;
+    // functions from the registry are resolved here, special forms like diff
+    // have their own grammar, other names are calls of user functions
+    // looked up when evaluated
+    fn parse_function(&mut self) -> Result<Node, Error> {
+        let name = self.current_token_value().trim();
+        let span = self.current_span();
         self.bump();
         self.bump(); // skip "("
-        for i in 0..f.arg_count {
-            if !self.parse_exp1()? {
-                return self.error(&format!("Argument {} is empty", i+1));
+        let mut args = vec![];
+        while self.current_token.kind != lexer::TokenKind::Rpar {
+            match self.parse_or()? {
+                Some(arg) => args.push(arg),
+                None => return self.error(&format!("Argument {} is empty", args.len() + 1))
             }
             match self.current_token.kind {
                 lexer::TokenKind::Coma => self.bump(),
-                lexer::TokenKind::Rpar => break,
-                _ => {
-                    if i == f.arg_count -1 {
-                        return self.error("Expected closing bracket");
-                    }
-                    return self.error("Invalid number of arguments")
-                }
+                lexer::TokenKind::Rpar => (),
+                _ => return self.error("Expected closing bracket")
             }
         }
-        self.program.push(Expression::FuncCall(f));
+        let full = span.to(&self.current_span());
         self.bump();
-        Ok(true)
+        // solve(A, b) of a linear system is a function of the registry
+        if SPECIAL_FORMS.contains(&name) && !(name == "solve" && args.len() == 2) {
+            let node = match name {
+                "diff" => self.parse_diff(args, span)?,
+                "integrate" => self.parse_integrate(args, span)?,
+                "solve" => self.parse_solve(args, span)?,
+                "factor" => self.parse_factor(args, span)?,
+                "map" | "filter" => self.parse_map(name, args, span)?,
+                _ => self.parse_if(args, span)?
+            };
+            return Ok(Node::new(node, full));
+        }
+        let kind = match self.functions.get(name) {
+            Some(f) if !f.arity.accepts(args.len()) => {
+                let msg = format!("Function {name} expects {}, got {}", f.arity, args.len());
+                return Err(Error::new(&msg, span));
+            },
+            Some(f) => NodeKind::Func(f.clone(), span, args),
+            None => NodeKind::Call(Ident::new(name), span, args)
+        };
+        Ok(Node::new(kind, full))
     }
 
-    fn parse_ident(&mut self) -> Result<bool, Error> {
-        let val = self.current_token_value().trim();
-        let c = parse::parse_const(val);
-        if c.is_ok() {
-            self.program.push(Expression::Val(c.unwrap())); 
-        } else {
-            self.program.push(Expression::Ref(Ident::new(val)))
+    // diff(exp1, id) | diff(exp1, id, int)
+    // derivative is a special form, the variable is not evaluated
+    fn parse_diff(&mut self, mut args: Vec<Node>, span: Span) -> Result<NodeKind, Error> {
+        if !(2..=3).contains(&args.len()) {
+            let msg = format!("Function diff expects 2 or 3 arguments, got {}", args.len());
+            return Err(Error::new(&msg, span));
+        }
+        let order = match args.get(2) {
+            Some(Node { kind: NodeKind::Num(Number::Int(n)), .. }) if *n > 0 && *n <= u32::MAX as i128 => *n as u32,
+            Some(node) => return Err(Error::new("Order of derivative must be a positive integer", node.span.clone())),
+            None => 1
+        };
+        args.truncate(2);
+        let var = bound_var(args.pop().unwrap(), "Expected variable to differentiate by")?;
+        let node = args.pop().unwrap();
+        Ok(NodeKind::Diff(Box::new(node), var, order, span))
+    }
+
+    // integrate(exp1, id, exp1, exp1)
+    // body is evaluated for values of the variable between the bounds
+    fn parse_integrate(&mut self, args: Vec<Node>, span: Span) -> Result<NodeKind, Error> {
+        let [body, var, a, b] = match <[Node; 4]>::try_from(args) {
+            Ok(args) => args,
+            Err(args) => {
+                let msg = format!("Function integrate expects 4 arguments, got {}", args.len());
+                return Err(Error::new(&msg, span));
+            }
+        };
+        let var = bound_var(var, "Expected variable of integration")?;
+        Ok(NodeKind::Integrate(Box::new(body), var, Box::new(a), Box::new(b), span))
+    }
+
+    // solve(exp1, id, exp1)
+    // body is evaluated for values of the variable near the guess
+    fn parse_solve(&mut self, args: Vec<Node>, span: Span) -> Result<NodeKind, Error> {
+        let [body, var, guess] = match <[Node; 3]>::try_from(args) {
+            Ok(args) => args,
+            Err(args) => {
+                let msg = format!("Function solve expects 2 or 3 arguments, got {}", args.len());
+                return Err(Error::new(&msg, span));
+            }
+        };
+        let var = bound_var(var, "Expected variable to solve for")?;
+        Ok(NodeKind::Solve(Box::new(body), var, Box::new(guess), span))
+    }
+
+    // if(or, or, or)
+    // only one of the branches is evaluated
+    fn parse_if(&mut self, args: Vec<Node>, span: Span) -> Result<NodeKind, Error> {
+        let [cond, then, otherwise] = match <[Node; 3]>::try_from(args) {
+            Ok(args) => args,
+            Err(args) => {
+                let msg = format!("Function if expects 3 arguments, got {}", args.len());
+                return Err(Error::new(&msg, span));
+            }
+        };
+        Ok(NodeKind::If(Box::new(cond), Box::new(then), Box::new(otherwise), span))
+    }
+
+    // factor(or)
+    // the result prints as a product of primes and computes as the number
+    fn parse_factor(&mut self, args: Vec<Node>, span: Span) -> Result<NodeKind, Error> {
+        match <[Node; 1]>::try_from(args) {
+            Ok([arg]) => Ok(NodeKind::Factor(Box::new(arg), span)),
+            Err(args) => {
+                let msg = format!("Function factor expects {}, got {}", Arity::Exact(1), args.len());
+                Err(Error::new(&msg, span))
+            }
         }
+    }
+
+    // map(or, id, or) | filter(or, id, or)
+    // body is evaluated for each element of the list
+    fn parse_map(&mut self, name: &str, args: Vec<Node>, span: Span) -> Result<NodeKind, Error> {
+        let [body, var, list] = match <[Node; 3]>::try_from(args) {
+            Ok(args) => args,
+            Err(args) => {
+                let msg = format!("Function {name} expects 3 arguments, got {}", args.len());
+                return Err(Error::new(&msg, span));
+            }
+        };
+        let var = bound_var(var, "Expected variable for elements of the list")?;
+        match name {
+            "map" => Ok(NodeKind::Map(Box::new(body), var, Box::new(list), span)),
+            _ => Ok(NodeKind::Filter(Box::new(body), var, Box::new(list), span))
+        }
+    }
+
+    fn parse_ident(&mut self) -> Result<Node, Error> {
+        let val = self.current_token_value().trim();
+        let kind = match parse::parse_const(val, self.config.precision) {
+            // i is a number, pi and e keep their names in symbolic results
+            Ok(c) if val == "i" => NodeKind::Num(c),
+            Ok(c) => NodeKind::Const(String::from(val), c),
+            Err(_) => NodeKind::Var(Ident::new(val))
+        };
+        let node = Node::new(kind, self.current_span());
         self.bump();
-        Ok(true)
+        Ok(node)
     }
 
-    fn parse_literal(&mut self, l: lexer::LiteralKind) -> Result<bool, Error> {
+    fn parse_literal(&mut self, l: lexer::LiteralKind) -> Result<Node, Error> {
         let val = match l {
             lexer::LiteralKind::Int(b) => self.parse_int(b)?,
             lexer::LiteralKind::Float => self.parse_float()?,
+            lexer::LiteralKind::Imaginary => parse::parse_imaginary(self.current_token_value())?,
             _ => return Err(Error::new("Unknown Literal", self.current_span()))
         };
-        self.program.push(Expression::Val(val));
+        let node = Node::new(NodeKind::Num(val), self.current_span());
         self.bump();
-        Ok(true)
+        Ok(node)
+    }
+
+    // "text" with \" \\ \n and \t escapes
+    fn parse_string(&mut self) -> Result<Node, Error> {
+        let span = self.current_span();
+        let mut chars = self.current_token_value().trim().chars().skip(1);
+        let mut res = String::new();
+        loop {
+            match chars.next() {
+                Some('"') => break,
+                Some('\\') => match chars.next() {
+                    Some('"') => res.push('"'),
+                    Some('\\') => res.push('\\'),
+                    Some('n') => res.push('\n'),
+                    Some('t') => res.push('\t'),
+                    Some(c) => return Err(Error::new(&format!("Unknown escape \\{c}"), span)),
+                    None => return Err(Error::new("Missing closing quote", span))
+                },
+                Some(c) => res.push(c),
+                None => return Err(Error::new("Missing closing quote", span))
+            }
+        }
+        self.bump();
+        Ok(Node::new(NodeKind::Str(res), span))
+    }
+
+    // 2026-10-18, 2026-10-18T09:10 or 17:45
+    fn parse_date(&mut self, kind: lexer::LiteralKind) -> Result<Node, Error> {
+        let span = self.current_span();
+        let value = self.current_token_value().trim();
+        let kind = match kind {
+            lexer::LiteralKind::Date => Date::parse(value).map(NodeKind::Date),
+            _ => Duration::parse(value).map(NodeKind::Duration)
+        };
+        match kind {
+            Some(kind) => {
+                self.bump();
+                Ok(Node::new(kind, span))
+            },
+            None => Err(Error::new(&format!("Invalid date or time {value}"), span))
+        }
     }
 
     fn parse_int(&mut self, b: lexer::Base) -> Result<Number, Error> {
@@ -254,11 +847,77 @@ impl<'src> Parser<'src> {
     }
 
     fn parse_float(&mut self) -> Result<Number, Error> {
-        let f = parse::parse_float(&self.current_token_value())?;
+        let f = parse::parse_float(self.current_token_value(), self.config.precision)?;
         Ok(f)
     }
 
-    fn parse_binary_op(&mut self, kind: lexer::TokenKind) -> Result<bool, Error>{
+    fn is_unit(&self, token: &lexer::Token) -> bool {
+        token.kind == lexer::TokenKind::Ident && unit::is_unit(token.value_from(self.source).trim())
+    }
+
+    // unit after a value, like in 5 km or (2 + 3) km. Identifier followed
+    // by ( or = is a function call or an assignment and not a unit.
+    fn parse_unit_suffix(&mut self, node: Node) -> Result<Node, Error> {
+        if self.is_unit(&self.current_token) && !self.is_cast()
+            && !matches!(self.next_token.kind, lexer::TokenKind::Lpar | lexer::TokenKind::Assign) {
+            let start = self.current_span();
+            let unit = self.parse_unit()?;
+            let span = self.span_from(&start);
+            let full = node.span.to(&span);
+            return Ok(Node::new(NodeKind::WithUnit(Box::new(node), unit, span), full));
+        }
+        Ok(node)
+    }
+    // unit: unit_term | unit_term * unit | unit_term / unit
+    fn parse_unit(&mut self) -> Result<Unit, Error> {
+        let mut unit = self.parse_unit_term()?;
+        while let kind @(lexer::TokenKind::Mul | lexer::TokenKind::Div) = self.current_token.kind {
+            if !self.is_unit(&self.next_token) {
+                break;
+            }
+            self.bump();
+            let term = self.parse_unit_term()?;
+            unit = match kind {
+                lexer::TokenKind::Mul => unit.mul(&term),
+                _ => unit.div(&term)
+            };
+        }
+        Ok(unit)
+    }
+
+    // unit_term: unit_id | unit_id ^ int | unit_id ^ -int
+    fn parse_unit_term(&mut self) -> Result<Unit, Error> {
+        if self.current_token.kind != lexer::TokenKind::Ident {
+            return Err(Error::new("Expected unit", self.current_span()));
+        }
+        let unit = match unit::parse_unit(self.current_token_value().trim()) {
+            Some(unit) => unit,
+            None => return Err(Error::new("Unknown unit", self.current_span()))
+        };
+        self.bump();
+        if self.current_token.kind != lexer::TokenKind::Pow {
+            return Ok(unit);
+        }
+        self.bump();
+        let sign = match self.current_token.kind {
+            lexer::TokenKind::Sub => { self.bump(); -1 },
+            lexer::TokenKind::Add => { self.bump(); 1 },
+            _ => 1
+        };
+        let power = match self.current_token.kind {
+            lexer::TokenKind::Literal(lexer::LiteralKind::Int(b)) => self.parse_int(b)?,
+            _ => Number::NaN
+        };
+        match power {
+            Number::Int(p) if i32::try_from(p).is_ok() => {
+                self.bump();
+                Ok(unit.powi(sign * p as i32))
+            },
+            _ => Err(Error::new("Expected integer power of unit", self.current_span()))
+        }
+    }
+
+    fn parse_binary_op(&mut self, kind: lexer::TokenKind, span: Span, lhs: Option<Node>, rhs: Node) -> Result<Node, Error> {
         let op = match kind {
             lexer::TokenKind::Add => Op::Add,
             lexer::TokenKind::Sub => Op::Sub,
@@ -266,19 +925,47 @@ impl<'src> Parser<'src> {
             lexer::TokenKind::Mul => Op::Mul,
             lexer::TokenKind::Mod => Op::Mod,
             lexer::TokenKind::Pow => Op::Pow,
+            lexer::TokenKind::Lt => Op::Lt,
+            lexer::TokenKind::Le => Op::Le,
+            lexer::TokenKind::Gt => Op::Gt,
+            lexer::TokenKind::Ge => Op::Ge,
+            lexer::TokenKind::Eq => Op::Eq,
+            lexer::TokenKind::Ne => Op::Ne,
+            lexer::TokenKind::And => Op::And,
+            lexer::TokenKind::Or => Op::Or,
+            lexer::TokenKind::BitAnd => Op::BitAnd,
+            lexer::TokenKind::BitOr => Op::BitOr,
+            lexer::TokenKind::Xor => Op::Xor,
+            lexer::TokenKind::Shl => Op::Shl,
+            lexer::TokenKind::Shr => Op::Shr,
+            lexer::TokenKind::Ushr => Op::Ushr,
             _ => return self.error("Invalid binary operator")
         };
-        self.program.push(Expression::BinaryOp(op));
-        Ok(true)
+        let lhs = match lhs {
+            Some(lhs) => lhs,
+            None => return Err(Error::new(ERR_UNEXP, span))
+        };
+        let full = lhs.span.to(&rhs.span);
+        Ok(Node::new(NodeKind::Binary(op, span, Box::new(lhs), Box::new(rhs)), full))
     }
 
-    fn parse_unary_op(&mut self, kind: lexer::TokenKind) -> Result<bool, Error> {
+    fn parse_unary_op(&mut self, kind: lexer::TokenKind, span: Span, arg: Node) -> Result<Node, Error> {
         let op = match kind {
             lexer::TokenKind::Add => Op::Add,
             lexer::TokenKind::Sub => Op::Sub,
+            lexer::TokenKind::Not => Op::Not,
+            lexer::TokenKind::BitNot => Op::BitNot,
             _ => return self.error("Invalid unary operator")
         };
-        self.program.push(Expression::UnaryOp(op));
-        Ok(true)
+        let full = span.to(&arg.span);
+        Ok(Node::new(NodeKind::Unary(op, span, Box::new(arg)), full))
     }
-}
\ No newline at end of file
+}
+
+// variable given to a special form, like x in diff(x^2, x)
+fn bound_var(node: Node, msg: &str) -> Result<Ident, Error> {
+    match node.kind {
+        NodeKind::Var(id) => Ok(id),
+        _ => Err(Error::new(msg, node.span))
+    }
+}
@@ -1,5 +1,5 @@
 use super::*;
-use crate::eval::Expression;
+use crate::eval::{Expression, UserFunction};
 use crate::number::Number;
 
 #[test]
@@ -19,7 +19,7 @@ fn test_add() {
     let expected = vec![
         Expression::Val(Number::Int(1)),
         Expression::Val(Number::Int(2)),
-        Expression::BinaryOp(Op::Add)
+        Expression::BinaryOp(Op::Add, Span::new(1, 1))
     ];
     assert_eq!(expected, p.program);
 }
@@ -31,11 +31,11 @@ fn test_precedence() {
     let expected = vec![
         Expression::Val(Number::Int(1)),
         Expression::Val(Number::Int(2)),
+        Expression::BinaryOp(Op::Mul, Span::new(1, 1)),
         Expression::Val(Number::Int(3)),
-        Expression::BinaryOp(Op::Mul),
-        Expression::BinaryOp(Op::Mul),
+        Expression::BinaryOp(Op::Mul, Span::new(6, 1)),
         Expression::Val(Number::Int(4)),
-        Expression::BinaryOp(Op::Add)
+        Expression::BinaryOp(Op::Add, Span::new(8, 1))
     ];
     assert_eq!(expected, p.program);
 }
@@ -48,10 +48,10 @@ fn test_precedence2() {
         Expression::Val(Number::Int(1)),
         Expression::Val(Number::Int(2)),
         Expression::Val(Number::Int(3)),
+        Expression::BinaryOp(Op::Mul, Span::new(3, 1)),
         Expression::Val(Number::Int(4)),
-        Expression::BinaryOp(Op::Mul),
-        Expression::BinaryOp(Op::Mul),
-        Expression::BinaryOp(Op::Add)
+        Expression::BinaryOp(Op::Mul, Span::new(5, 1)),
+        Expression::BinaryOp(Op::Add, Span::new(1, 1))
     ];
     assert_eq!(expected, p.program);
 }
@@ -63,11 +63,29 @@ fn test_precedence3() {
     let expected = vec![
         Expression::Val(Number::Int(1)),
         Expression::Val(Number::Int(2)),
-        Expression::BinaryOp(Op::Mul),
+        Expression::BinaryOp(Op::Mul, Span::new(1, 1)),
         Expression::Val(Number::Int(3)),
         Expression::Val(Number::Int(4)),
-        Expression::BinaryOp(Op::Mul),
-        Expression::BinaryOp(Op::Add)
+        Expression::BinaryOp(Op::Mul, Span::new(5, 1)),
+        Expression::BinaryOp(Op::Add, Span::new(3, 1))
+    ];
+    assert_eq!(expected, p.program);
+}
+
+#[test]
+fn test_left_associativity() {
+    let mut p = Parser::new("1/3*3-1-2");
+    p.parse().unwrap();
+    let expected = vec![
+        Expression::Val(Number::Int(1)),
+        Expression::Val(Number::Int(3)),
+        Expression::BinaryOp(Op::Div, Span::new(1, 1)),
+        Expression::Val(Number::Int(3)),
+        Expression::BinaryOp(Op::Mul, Span::new(3, 1)),
+        Expression::Val(Number::Int(1)),
+        Expression::BinaryOp(Op::Sub, Span::new(5, 1)),
+        Expression::Val(Number::Int(2)),
+        Expression::BinaryOp(Op::Sub, Span::new(7, 1))
     ];
     assert_eq!(expected, p.program);
 }
@@ -78,7 +96,7 @@ fn test_unary() {
     p.parse().unwrap();
     let expected = vec![
         Expression::Val(Number::Int(1)),
-        Expression::UnaryOp(Op::Sub)
+        Expression::UnaryOp(Op::Sub, Span::new(0, 1))
     ];
     assert_eq!(expected, p.program);
 }
@@ -90,15 +108,16 @@ fn test_paren_and_whitespaces() {
     let expected = vec![
         Expression::Val(Number::Int(2)),
         Expression::Val(Number::Int(1)),
-        Expression::UnaryOp(Op::Sub),
+        Expression::UnaryOp(Op::Sub, Span::new(8, 1)),
         Expression::Val(Number::Int(3)),
-        Expression::BinaryOp(Op::Add),
-        Expression::BinaryOp(Op::Add)
+        Expression::BinaryOp(Op::Add, Span::new(13, 1)),
+        Expression::BinaryOp(Op::Add, Span::new(3, 1))
     ];
     assert_eq!(expected, p.program);
 }
 
 #[test]
+#[allow(clippy::approx_constant)]
 fn test_const() {
     let mut p = Parser::new("pi");
     p.parse().unwrap();
@@ -118,8 +137,8 @@ fn test_functions() {
     let expected = vec![
         Expression::Val(Number::Int(1)),
         Expression::Val(Number::Int(1)),
-        Expression::BinaryOp(Op::Sub),
-        Expression::FuncCall(function::parse_function("sin").unwrap())
+        Expression::BinaryOp(Op::Sub, Span::new(5, 1)),
+        Expression::FuncCall(function::parse_function("sin").unwrap(), 1, Span::new(0, 3))
     ];
     assert_eq!(expected, p.program);
 }
@@ -131,10 +150,15 @@ fn test_assign() {
     let expected = vec![
         Expression::Val(1.into()),
         Expression::Val(2.into()),
-        Expression::BinaryOp(Op::Add),
-        Expression::Assign(Ident::new("x"))
+        Expression::BinaryOp(Op::Add, Span::new(6, 1)),
+        Expression::Assign(Ident::new("x"), Span::new(0, 1))
     ];
     assert_eq!(expected, p.program);
+
+    for name in ["i", "e", "pi"] {
+        let err = Parser::new(&format!("{name} = 5")).parse().unwrap_err();
+        assert_eq!((err.message, err.span), (format!("Cannot assign to constant {name}"), Span::new(0, name.len())));
+    }
 }
 
 #[test]
@@ -143,8 +167,440 @@ fn test_ident() {
     p.parse().unwrap();
     let expected = vec![
         Expression::Val(1.into()),
-        Expression::Ref(Ident::new("x")),
-        Expression::BinaryOp(Op::Add),
+        Expression::Ref(Ident::new("x"), Span::new(4, 1)),
+        Expression::BinaryOp(Op::Add, Span::new(2, 1)),
+    ];
+    assert_eq!(expected, p.program);
+}
+
+#[test]
+fn test_units() {
+    let mut p = Parser::new("2 km/h^2 to m/s^2");
+    p.parse().unwrap();
+    let km_h2 = unit::parse_unit("km").unwrap().div(&unit::parse_unit("h").unwrap().powi(2));
+    let m_s2 = unit::parse_unit("m").unwrap().div(&unit::parse_unit("s").unwrap().powi(2));
+    let expected = vec![
+        Expression::Val(Number::Int(2)),
+        Expression::WithUnit(km_h2, Span::new(2, 6)),
+        Expression::Convert(m_s2, Span::new(9, 2))
+    ];
+    assert_eq!(expected, p.program);
+}
+
+#[test]
+fn test_unit_is_not_variable() {
+    let mut p = Parser::new("m * 2 m");
+    p.parse().unwrap();
+    let expected = vec![
+        Expression::Ref(Ident::new("m"), Span::new(0, 1)),
+        Expression::Val(Number::Int(2)),
+        Expression::WithUnit(unit::parse_unit("m").unwrap(), Span::new(6, 1)),
+        Expression::BinaryOp(Op::Mul, Span::new(2, 1))
+    ];
+    assert_eq!(expected, p.program);
+}
+
+#[test]
+fn test_variable_named_like_unit() {
+    // the calculator decides whether g is a variable or grams
+    let mut p = Parser::new("g = 9.81");
+    p.parse().unwrap();
+    assert_eq!(p.program.last(), Some(&Expression::Assign(Ident::new("g"), Span::new(0, 1))));
+    let mut p = Parser::new("2g");
+    p.parse().unwrap();
+    let expected = vec![
+        Expression::Val(Number::Int(2)),
+        Expression::WithUnit(unit::parse_unit("g").unwrap(), Span::new(1, 1))
+    ];
+    assert_eq!(expected, p.program);
+    assert_eq!(unit::parse_unit("g").unwrap().symbol(), Some("g"));
+    assert_eq!(unit::parse_unit("km").unwrap().powi(2).symbol(), None);
+}
+
+#[test]
+fn test_trailing_tokens() {
+    let mut p = Parser::new("2 + x foo");
+    assert!(p.parse().is_err());
+    let mut p = Parser::new("(5) 3");
+    assert!(p.parse().is_err());
+}
+
+#[test]
+fn test_implicit_mul() {
+    let ast = |input: &str| {
+        let mut p = Parser::new(input);
+        p.parse().unwrap();
+        p.ast.unwrap().to_string()
+    };
+    assert_eq!(ast("2pi"), "2*pi");
+    assert_eq!(ast("3(x + 1)"), "3*(x + 1)");
+    assert_eq!(ast("(a)(b)"), "a*b");
+    assert_eq!(ast("(a + 1)(b - 1)"), "(a + 1)*(b - 1)");
+    assert_eq!(ast("(x + 1)y"), "(x + 1)*y");
+    assert_eq!(ast("2 sin(x)cos(x)"), "2*sin(x)*cos(x)");
+    // same precedence as *, below ^ and unary minus
+    assert_eq!(ast("2x^2"), "2*x^2");
+    assert_eq!(ast("x^2y"), "x^2*y");
+    assert_eq!(ast("-2x"), "-2*x");
+    assert_eq!(ast("1/2x"), "1/2*x");
+    assert_eq!(ast("2x + 1"), "2*x + 1");
+    // units, calls, keywords and other names are not multiplied
+    assert_eq!(ast("2 km"), "2 km");
+    assert_eq!(ast("f(x)"), "f(x)");
+    assert_eq!(ast("2x to m"), "2*x to m");
+    assert_eq!(ast("50% of 2x"), "50%*2*x");
+
+    let mut p = Parser::new("2x in hex");
+    p.parse().unwrap();
+    assert_eq!(p.radix, Some(Radix::Hex));
+    let mut p = Parser::new("2 3(x)");
+    p.parse().unwrap();
+    assert_eq!(p.ast.unwrap().to_string(), "23*x");
+    let mut p = Parser::new("x y");
+    assert_eq!(p.parse().unwrap_err().message, "Unexpected token");
+    let mut p = Parser::new("(2)\"a\"");
+    assert!(p.parse().is_err());
+
+    let mut p = Parser::new("3(x + 1)");
+    p.parse().unwrap();
+    match p.ast.unwrap().kind {
+        NodeKind::Binary(Op::Mul, span, _, rhs) => {
+            assert_eq!(span, Span::new(1, 1));
+            assert_eq!(rhs.span, Span::new(1, 7));
+        },
+        kind => panic!("Expected multiplication, got {kind:?}")
+    }
+}
+
+#[test]
+fn test_define() {
+    let mut p = Parser::new("f(x, y) = x * y");
+    p.parse().unwrap();
+    let body = vec![
+        Expression::Ref(Ident::new("x"), Span::new(10, 1)),
+        Expression::Ref(Ident::new("y"), Span::new(14, 1)),
+        Expression::BinaryOp(Op::Mul, Span::new(12, 1))
+    ];
+    match p.program.as_slice() {
+        [Expression::Define(id, UserFunction { params, body: node })] => {
+            assert_eq!(id, &Ident::new("f"));
+            assert_eq!(params, &vec![Ident::new("x"), Ident::new("y")]);
+            assert_eq!(node.lower(), body);
+        },
+        program => panic!("Expected definition, got {program:?}")
+    }
+
+    let mut p = Parser::new("sin(x) = x");
+    assert!(p.parse().is_err());
+    let mut p = Parser::new("diff(x) = x");
+    assert!(p.parse().is_err());
+}
+
+#[test]
+fn test_diff() {
+    let mut p = Parser::new("diff(x^3, x, 2)");
+    p.parse().unwrap();
+    match &p.program[..] {
+        [Expression::Diff(node, var, 2, span)] => {
+            assert_eq!(node.to_string(), "x^3");
+            assert_eq!(var, &Ident::new("x"));
+            assert_eq!(span, &Span::new(0, 4));
+        },
+        program => panic!("Expected derivative, got {program:?}")
+    }
+
+    let mut p = Parser::new("diff(x^2, 2)");
+    assert_eq!(p.parse().unwrap_err().span, Span::new(10, 1));
+    let mut p = Parser::new("diff(x^2, x, 0)");
+    assert_eq!(p.parse().unwrap_err().span, Span::new(13, 1));
+    let mut p = Parser::new("diff(x^2)");
+    assert_eq!(p.parse().unwrap_err().span, Span::new(0, 4));
+}
+
+#[test]
+fn test_integrate_solve() {
+    let mut p = Parser::new("integrate(x^2, x, 0, 1)");
+    p.parse().unwrap();
+    match &p.program[..] {
+        [Expression::Val(a), Expression::Val(b), Expression::Integrate(body, var, span)] => {
+            assert_eq!((a, b), (&Number::Int(0), &Number::Int(1)));
+            assert_eq!(body.len(), 3);
+            assert_eq!(var, &Ident::new("x"));
+            assert_eq!(span, &Span::new(0, 9));
+        },
+        program => panic!("Expected integral, got {program:?}")
+    }
+    let mut p = Parser::new("solve(x^2 - 2, x, 1)");
+    p.parse().unwrap();
+    assert!(matches!(&p.program[..], [Expression::Val(_), Expression::Solve(..)]));
+
+    let mut p = Parser::new("integrate(x, 2, 0, 1)");
+    assert_eq!(p.parse().unwrap_err().span, Span::new(13, 1));
+    let mut p = Parser::new("solve(x)");
+    assert_eq!(p.parse().unwrap_err().span, Span::new(0, 5));
+    let mut p = Parser::new("solve(a, b)");
+    p.parse().unwrap();
+    assert!(matches!(&p.program[..], [.., Expression::FuncCall(f, 2, _)] if f.name == "solve"));
+    let mut p = Parser::new("solve(x) = x");
+    assert!(p.parse().is_err());
+}
+
+#[test]
+fn test_user_call() {
+    let mut p = Parser::new("f(1, 2) + g()");
+    p.parse().unwrap();
+    let expected = vec![
+        Expression::Val(1.into()),
+        Expression::Val(2.into()),
+        Expression::Call(Ident::new("f"), 2, Span::new(0, 1)),
+        Expression::Call(Ident::new("g"), 0, Span::new(10, 1)),
+        Expression::BinaryOp(Op::Add, Span::new(8, 1))
     ];
     assert_eq!(expected, p.program);
-}
\ No newline at end of file
+}
+
+#[test]
+fn test_function_registry() {
+    use crate::function::{Arity, FunctionRegistry};
+    let mut functions = FunctionRegistry::new();
+    functions.register("clamp", Arity::Exact(3), |args| args[1].clone());
+    functions.register("sum", Arity::Variadic(1), |_| Number::NaN);
+    let functions = std::rc::Rc::new(functions);
+
+    let mut p = Parser::with_functions("clamp(1, 2, 3)", &Config::default(), functions.clone());
+    p.parse().unwrap();
+    let clamp = functions.get("clamp").unwrap().clone();
+    assert_eq!(p.program.last(), Some(&Expression::FuncCall(clamp, 3, Span::new(0, 5))));
+
+    let mut p = Parser::with_functions("sum(1, 2, 3, 4)", &Config::default(), functions.clone());
+    p.parse().unwrap();
+    let sum = functions.get("sum").unwrap().clone();
+    assert_eq!(p.program.last(), Some(&Expression::FuncCall(sum, 4, Span::new(0, 3))));
+
+    let mut p = Parser::with_functions("2 + clamp(1, 2)", &Config::default(), functions.clone());
+    let err = p.parse().unwrap_err();
+    assert_eq!(err.message, "Function clamp expects 3 arguments, got 2");
+    assert_eq!(err.span, Span::new(4, 5));
+
+    let mut p = Parser::with_functions("1 + sum()", &Config::default(), functions);
+    let err = p.parse().unwrap_err();
+    assert_eq!(err.message, "Function sum expects at least 1 argument, got 0");
+}
+
+#[test]
+fn test_logic_precedence() {
+    let ast = |input: &str| {
+        let mut p = Parser::new(input);
+        p.parse().unwrap();
+        p.ast.unwrap()
+    };
+    let node = ast("a || b && c == 1 + 2 < d");
+    match &node.kind {
+        NodeKind::Binary(Op::Or, _, _, rhs) => match &rhs.kind {
+            NodeKind::Binary(Op::And, _, _, rhs) => match &rhs.kind {
+                NodeKind::Binary(Op::Eq, _, _, rhs) => assert_eq!(rhs.to_string(), "1 + 2 < d"),
+                kind => panic!("Expected ==, got {kind:?}")
+            },
+            kind => panic!("Expected &&, got {kind:?}")
+        },
+        kind => panic!("Expected ||, got {kind:?}")
+    }
+    assert_eq!(ast("!a && 1 km to m > 5 m").to_string(), "!a && 1 km to m > (5 m)");
+    assert_eq!(ast("(1 < 2) < 3").to_string(), "(1 < 2) < 3");
+    assert_eq!(ast("(~a << 2 | b) xor c & d > 1").to_string(), "(~a << 2 | b) xor c & d > 1");
+    assert_eq!(ast("a | (b | c)").to_string(), "a | (b | c)");
+
+    let mut p = Parser::new("1 < x < 3");
+    let err = p.parse().unwrap_err();
+    assert_eq!((err.message.as_str(), err.span), ("Comparisons cannot be chained, use &&", Span::new(6, 1)));
+    let mut p = Parser::new("if(1, 2)");
+    assert_eq!(p.parse().unwrap_err().span, Span::new(0, 2));
+}
+
+#[test]
+fn test_radix_and_cast() {
+    let mut p = Parser::new("0xff + 1 in hex");
+    p.parse().unwrap();
+    assert_eq!(p.radix, Some(Radix::Hex));
+    assert_eq!(p.program.len(), 3);
+
+    let mut p = Parser::new("-1 as u32");
+    p.parse().unwrap();
+    match &p.ast.as_ref().unwrap().kind {
+        NodeKind::Cast(_, word, span) => {
+            assert_eq!(word, &Word { bits: 32, signed: false });
+            assert_eq!(span, &Span::new(3, 2));
+        },
+        node => panic!("Expected cast, got {node:?}")
+    }
+    // without a type `as` is attosecond
+    let mut p = Parser::new("5 as");
+    p.parse().unwrap();
+    assert!(matches!(p.ast.unwrap().kind, NodeKind::WithUnit(..)));
+
+    let mut p = Parser::new("1 in base");
+    assert_eq!(p.parse().unwrap_err().span, Span::new(5, 4));
+    let mut p = Parser::new("x as u7");
+    assert_eq!(p.parse().unwrap_err().message, "Expected integer type like u32 or i8");
+}
+
+#[test]
+fn test_factorial() {
+    let ast = |input: &str| {
+        let mut p = Parser::new(input);
+        p.parse().unwrap();
+        p.ast.unwrap()
+    };
+    let node = ast("-3!");
+    match &node.kind {
+        NodeKind::Unary(Op::Sub, _, arg) => {
+            assert!(matches!(arg.kind, NodeKind::Unary(Op::Fact, _, _)));
+            assert_eq!(arg.span, Span::new(1, 2));
+        },
+        kind => panic!("Expected negation, got {kind:?}")
+    }
+    assert_eq!(ast("2^n!").to_string(), "2^n!");
+    assert_eq!(ast("(-3)!").to_string(), "(-3)!");
+    assert_eq!(ast("(n - 1)! * n").to_string(), "(n - 1)!*n");
+    assert_eq!(ast("x!!").to_string(), "x!!");
+    assert_eq!(ast("a != b").to_string(), "a != b");
+    assert_eq!(ast("factor(12)").to_string(), "factor(12)");
+
+    let mut p = Parser::new("factor(1, 2)");
+    assert_eq!(p.parse().unwrap_err().message, "Function factor expects 1 argument, got 2");
+    let mut p = Parser::new("factor(x) = x");
+    assert!(p.parse().is_err());
+}
+
+#[test]
+fn test_percent() {
+    let ast = |input: &str| {
+        let mut p = Parser::new(input);
+        p.parse().unwrap();
+        p.ast.unwrap()
+    };
+    match &ast("200 + 15%").kind {
+        NodeKind::Binary(Op::Add, _, _, rhs) => {
+            assert!(matches!(rhs.kind, NodeKind::Unary(Op::Percent, _, _)));
+            assert_eq!(rhs.span, Span::new(6, 3));
+        },
+        kind => panic!("Expected addition, got {kind:?}")
+    }
+    assert_eq!(ast("200 + 15%").to_string(), "200 + 15%");
+    assert_eq!(ast("7 % 3").to_string(), "7 % 3");
+    assert_eq!(ast("7 % x").to_string(), "7 % x");
+    assert_eq!(ast("7 % (-2)").to_string(), "7 % (-2)");
+    assert_eq!(ast("200 + 15% + 10").to_string(), "200 + 15% + 10");
+    assert_eq!(ast("200 + 15% - 10").to_string(), "200 + 15% - 10");
+    assert_eq!(ast("200 - 15% * 2").to_string(), "200 - 15%*2");
+    assert_eq!(ast("50% of 80").to_string(), "50%*80");
+    assert_eq!(ast("(x + 1)%*2").to_string(), "(x + 1)%*2");
+    assert_eq!(ast("x as % of y + 1").to_string(), "x/(y + 1)%");
+    assert_eq!(ast("5 as").to_string(), "5 as");
+
+    let mut p = Parser::new("x as % y");
+    assert_eq!(p.parse().unwrap_err().message, "Expected of");
+    let mut p = Parser::new("x of 5");
+    assert!(p.parse().is_err());
+}
+
+#[test]
+fn test_list() {
+    let ast = |input: &str| {
+        let mut p = Parser::new(input);
+        p.parse().unwrap();
+        p.ast.unwrap()
+    };
+    let node = ast("-x[i + 1]!");
+    match &node.kind {
+        NodeKind::Unary(Op::Sub, _, arg) => match &arg.kind {
+            NodeKind::Unary(Op::Fact, _, index) => {
+                assert!(matches!(index.kind, NodeKind::Index(..)));
+                assert_eq!(index.span, Span::new(1, 8));
+            },
+            kind => panic!("Expected factorial, got {kind:?}")
+        },
+        kind => panic!("Expected negation, got {kind:?}")
+    }
+    assert_eq!(ast("[1, 2 + 3, []]").to_string(), "[1, 2 + 3, []]");
+    assert_eq!(ast("[1, 2] km").to_string(), "[1, 2] km");
+    assert_eq!(ast("(a + b)[0][1]").to_string(), "(a + b)[0][1]");
+    assert_eq!(ast("map(x^2, x, range(1, 4))").to_string(), "map(x^2, x, range(1, 4))");
+    assert_eq!(ast("filter(x > 0, x, sort(y))").to_string(), "filter(x > 0, x, sort(y))");
+
+    let mut p = Parser::new("[1, 2");
+    let err = p.parse().unwrap_err();
+    assert_eq!((err.message.as_str(), err.span), ("Missing closing bracket", Span::new(5, 0)));
+    let mut p = Parser::new("[1, ");
+    assert_eq!(p.parse().unwrap_err().message, "Element 2 is empty");
+    let mut p = Parser::new("x[]");
+    assert_eq!(p.parse().unwrap_err().message, "Missing index");
+    let mut p = Parser::new("range(1, 2, 3, 4)");
+    assert_eq!(p.parse().unwrap_err().message, "Function range expects 1 to 3 arguments, got 4");
+    let mut p = Parser::new("map(x, 2, [1])");
+    assert_eq!(p.parse().unwrap_err().message, "Expected variable for elements of the list");
+    let mut p = Parser::new("len(x) = 1");
+    assert!(p.parse().is_err());
+}
+
+#[test]
+fn test_string() {
+    let mut p = Parser::new(" \"a \\\"b\\\"\\n\" + x");
+    p.parse().unwrap();
+    match &p.ast.as_ref().unwrap().kind {
+        NodeKind::Binary(Op::Add, _, lhs, _) => {
+            assert_eq!(lhs.kind, NodeKind::Str(String::from("a \"b\"\n")));
+            assert_eq!(lhs.span, Span::new(1, 11));
+        },
+        kind => panic!("Expected addition, got {kind:?}")
+    }
+    assert_eq!(p.ast.unwrap().to_string(), "\"a \\\"b\\\"\\n\" + x");
+
+    let mut p = Parser::new("\"abc");
+    let err = p.parse().unwrap_err();
+    assert_eq!((err.message.as_str(), err.span), ("Missing closing quote", Span::new(0, 4)));
+    let mut p = Parser::new("\"a\\q\"");
+    assert_eq!(p.parse().unwrap_err().message, "Unknown escape \\q");
+    let mut p = Parser::new("upper(s) = s");
+    assert!(p.parse().is_err());
+}
+
+#[test]
+fn test_date() {
+    let mut p = Parser::new("2026-10-18T09:10 + 17:45");
+    p.parse().unwrap();
+    assert_eq!(p.ast.unwrap().to_string(), "2026-10-18T09:10 + 17:45");
+
+    let mut p = Parser::new("1 + 2026-02-30");
+    let err = p.parse().unwrap_err();
+    assert_eq!((err.message.as_str(), err.span), ("Invalid date or time 2026-02-30", Span::new(4, 10)));
+    let mut p = Parser::new("12:75");
+    assert_eq!(p.parse().unwrap_err().message, "Invalid date or time 12:75");
+}
+
+#[test]
+fn test_angle() {
+    let ast = |input: &str| {
+        let mut p = Parser::new(input);
+        p.parse().unwrap();
+        p.ast.unwrap()
+    };
+    assert_eq!(ast("30°").to_string(), "30°");
+    assert_eq!(ast("30 deg").to_string(), "30°");
+    assert_eq!(ast("sin(x rad)").to_string(), "sin(x rad)");
+    assert_eq!(ast("(a + b) grad^2").to_string(), "(a + b) grad^2");
+    assert_eq!(ast("-90°").to_string(), "-90°");
+    // the suffix belongs to the nearest value, like units
+    assert_eq!(ast("pi/2 rad").to_string(), "pi/2 rad");
+    match &ast("2x°").kind {
+        NodeKind::Binary(Op::Mul, _, _, rhs) => {
+            assert!(matches!(rhs.kind, NodeKind::Unary(Op::Angle(Angle::Deg), _, _)));
+            assert_eq!(rhs.span, Span::new(1, 3));
+        },
+        kind => panic!("Expected product, got {kind:?}")
+    }
+    // names followed by ( or = are functions and variables
+    assert_eq!(ast("2 deg(x)").to_string(), "2*deg(x)");
+    assert!(matches!(ast("rad = 5").kind, NodeKind::Assign(..)));
+    assert!(matches!(ast("grad").kind, NodeKind::Var(_)));
+}
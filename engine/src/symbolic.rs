@@ -0,0 +1,681 @@
+use crate::ast::{Node, NodeKind};
+use crate::config::Config;
+use crate::errors::Span;
+use crate::eval::{number_op, Ident, Op};
+use crate::function::{self, integer, Function};
+use crate::function::angle::Angle;
+use crate::number::Number;
+use std::cmp::Ordering;
+
+#[cfg(test)]
+mod tests;
+
+fn num(n: i128, span: &Span) -> Node {
+    Node::new(NodeKind::Num(Number::Int(n)), span.clone())
+}
+
+fn binary(op: Op, lhs: Node, rhs: Node, span: &Span) -> Node {
+    Node::new(NodeKind::Binary(op, span.clone(), Box::new(lhs), Box::new(rhs)), span.clone())
+}
+
+fn neg(arg: Node, span: &Span) -> Node {
+    Node::new(NodeKind::Unary(Op::Sub, span.clone(), Box::new(arg)), span.clone())
+}
+
+// call of built-in function
+fn call(name: &str, args: Vec<Node>, span: &Span) -> Node {
+    let f = function::builtins().get(name).cloned().expect("built-in function");
+    Node::new(NodeKind::Func(f, span.clone(), args), span.clone())
+}
+
+/// True if the expression refers to the variable
+pub fn depends_on(node: &Node, var: &Ident) -> bool {
+    match &node.kind {
+        NodeKind::Num(_) | NodeKind::Const(..) | NodeKind::Str(_) | NodeKind::Date(_) | NodeKind::Duration(_) => false,
+        NodeKind::Var(id) => id == var,
+        NodeKind::Unary(_, _, arg) => depends_on(arg, var),
+        NodeKind::Binary(_, _, lhs, rhs) => depends_on(lhs, var) || depends_on(rhs, var),
+        NodeKind::Func(_, _, args) | NodeKind::Call(_, _, args) => args.iter().any(|a| depends_on(a, var)),
+        NodeKind::WithUnit(node, ..) | NodeKind::Convert(node, ..) | NodeKind::Cast(node, ..) => depends_on(node, var),
+        NodeKind::Assign(_, _, value) => depends_on(value, var),
+        NodeKind::Define(..) => false,
+        NodeKind::Diff(node, id, ..) => id != var && depends_on(node, var),
+        NodeKind::Integrate(body, id, a, b, _) => {
+            depends_on(a, var) || depends_on(b, var) || (id != var && depends_on(body, var))
+        },
+        NodeKind::Solve(body, id, guess, _) => depends_on(guess, var) || (id != var && depends_on(body, var)),
+        NodeKind::If(cond, then, otherwise, _) => {
+            depends_on(cond, var) || depends_on(then, var) || depends_on(otherwise, var)
+        },
+        NodeKind::Factor(node, _) => depends_on(node, var),
+        NodeKind::List(items) => items.iter().any(|i| depends_on(i, var)),
+        NodeKind::Index(list, i, _) => depends_on(list, var) || depends_on(i, var),
+        NodeKind::Map(body, id, list, _) | NodeKind::Filter(body, id, list, _) => {
+            depends_on(list, var) || (id != var && depends_on(body, var))
+        }
+    }
+}
+
+/// Variables the expression refers to, in order of appearance
+pub fn free_vars(node: &Node) -> Vec<Ident> {
+    let mut vars = vec![];
+    collect_vars(node, &mut vars);
+    vars
+}
+
+fn collect_vars(node: &Node, vars: &mut Vec<Ident>) {
+    match &node.kind {
+        NodeKind::Var(id) if !vars.contains(id) => vars.push(id.clone()),
+        NodeKind::Unary(_, _, arg) => collect_vars(arg, vars),
+        NodeKind::Binary(_, _, lhs, rhs) => {
+            collect_vars(lhs, vars);
+            collect_vars(rhs, vars);
+        },
+        NodeKind::Func(_, _, args) | NodeKind::Call(_, _, args) | NodeKind::List(args) => {
+            args.iter().for_each(|arg| collect_vars(arg, vars));
+        },
+        NodeKind::Index(list, i, _) => {
+            collect_vars(list, vars);
+            collect_vars(i, vars);
+        },
+        NodeKind::Map(body, id, list, _) | NodeKind::Filter(body, id, list, _) => {
+            collect_vars(list, vars);
+            collect_bound(body, id, vars);
+        },
+        NodeKind::WithUnit(node, ..) | NodeKind::Convert(node, ..) | NodeKind::Cast(node, ..)
+            | NodeKind::Assign(_, _, node) | NodeKind::Factor(node, _) => collect_vars(node, vars),
+        NodeKind::Diff(node, id, ..) => collect_bound(node, id, vars),
+        NodeKind::Integrate(body, id, a, b, _) => {
+            collect_vars(a, vars);
+            collect_vars(b, vars);
+            collect_bound(body, id, vars);
+        },
+        NodeKind::Solve(body, id, guess, _) => {
+            collect_vars(guess, vars);
+            collect_bound(body, id, vars);
+        },
+        NodeKind::If(cond, then, otherwise, _) => {
+            collect_vars(cond, vars);
+            collect_vars(then, vars);
+            collect_vars(otherwise, vars);
+        },
+        _ => ()
+    }
+}
+
+// variables of the body except the one bound by the enclosing form
+fn collect_bound(body: &Node, bound: &Ident, vars: &mut Vec<Ident>) {
+    let mut inner = vec![];
+    collect_vars(body, &mut inner);
+    inner.retain(|v| v != bound && !vars.contains(v));
+    vars.extend(inner);
+}
+
+/// Replaces variables with expressions, all of them at once
+pub fn substitute(node: &Node, vars: &[(Ident, Node)]) -> Node {
+    let sub = |n: &Node| Box::new(substitute(n, vars));
+    let kind = match &node.kind {
+        NodeKind::Var(id) => match vars.iter().find(|(v, _)| v == id) {
+            Some((_, value)) => return value.clone(),
+            None => NodeKind::Var(id.clone())
+        },
+        NodeKind::Unary(op, span, arg) => NodeKind::Unary(*op, span.clone(), sub(arg)),
+        NodeKind::Binary(op, span, lhs, rhs) => NodeKind::Binary(*op, span.clone(), sub(lhs), sub(rhs)),
+        NodeKind::Func(f, span, args) => {
+            NodeKind::Func(f.clone(), span.clone(), args.iter().map(|a| substitute(a, vars)).collect())
+        },
+        NodeKind::Call(id, span, args) => {
+            NodeKind::Call(id.clone(), span.clone(), args.iter().map(|a| substitute(a, vars)).collect())
+        },
+        NodeKind::WithUnit(node, unit, span) => NodeKind::WithUnit(sub(node), unit.clone(), span.clone()),
+        NodeKind::Convert(node, unit, span) => NodeKind::Convert(sub(node), unit.clone(), span.clone()),
+        NodeKind::Cast(node, word, span) => NodeKind::Cast(sub(node), *word, span.clone()),
+        NodeKind::Assign(id, span, value) => NodeKind::Assign(id.clone(), span.clone(), sub(value)),
+        // variable of differentiation is bound inside diff
+        NodeKind::Diff(node, id, order, span) => {
+            NodeKind::Diff(Box::new(substitute_bound(node, id, vars)), id.clone(), *order, span.clone())
+        },
+        NodeKind::Integrate(body, id, a, b, span) => {
+            let body = Box::new(substitute_bound(body, id, vars));
+            NodeKind::Integrate(body, id.clone(), sub(a), sub(b), span.clone())
+        },
+        NodeKind::Solve(body, id, guess, span) => {
+            NodeKind::Solve(Box::new(substitute_bound(body, id, vars)), id.clone(), sub(guess), span.clone())
+        },
+        NodeKind::If(cond, then, otherwise, span) => NodeKind::If(sub(cond), sub(then), sub(otherwise), span.clone()),
+        NodeKind::Factor(node, span) => NodeKind::Factor(sub(node), span.clone()),
+        NodeKind::List(items) => NodeKind::List(items.iter().map(|i| substitute(i, vars)).collect()),
+        NodeKind::Index(list, i, span) => NodeKind::Index(sub(list), sub(i), span.clone()),
+        NodeKind::Map(body, id, list, span) => {
+            NodeKind::Map(Box::new(substitute_bound(body, id, vars)), id.clone(), sub(list), span.clone())
+        },
+        NodeKind::Filter(body, id, list, span) => {
+            NodeKind::Filter(Box::new(substitute_bound(body, id, vars)), id.clone(), sub(list), span.clone())
+        },
+        kind => kind.clone()
+    };
+    Node::new(kind, node.span.clone())
+}
+
+fn substitute_bound(body: &Node, bound: &Ident, vars: &[(Ident, Node)]) -> Node {
+    let vars: Vec<_> = vars.iter().filter(|(v, _)| v != bound).cloned().collect();
+    substitute(body, &vars)
+}
+
+/// Derivative of given order, simplified after each step
+pub fn diff(node: &Node, var: &Ident, order: u32, config: &Config) -> Result<Node, String> {
+    let mut res = simplify(node.clone(), config);
+    for _ in 0..order {
+        res = simplify(derivative(&res, var, config)?, config);
+    }
+    Ok(res)
+}
+
+/// Derivative of the expression with respect to the variable, other
+/// variables are constants. Calls of user functions must be inlined first.
+pub fn derivative(node: &Node, var: &Ident, config: &Config) -> Result<Node, String> {
+    if let Some(node) = node.percent_change() {
+        return derivative(&node, var, config);
+    }
+    let span = &node.span;
+    let res = match &node.kind {
+        NodeKind::Num(_) | NodeKind::Const(..) => num(0, span),
+        NodeKind::Str(_) => return Err(String::from("Cannot differentiate strings")),
+        NodeKind::Date(_) | NodeKind::Duration(_) => return Err(String::from("Cannot differentiate dates")),
+        NodeKind::Var(id) => num((id == var) as i128, span),
+        NodeKind::Unary(Op::Not, ..) => num(0, span),
+        NodeKind::Unary(Op::BitNot, ..) => return Err(String::from("Cannot differentiate bitwise operators")),
+        NodeKind::Unary(Op::Fact, ..) if depends_on(node, var) => return Err(String::from("Cannot differentiate factorial")),
+        NodeKind::Unary(Op::Fact, ..) => num(0, span),
+        NodeKind::Unary(Op::Percent, op_span, arg) => binary(Op::Div, derivative(arg, var, config)?, num(100, op_span), span),
+        NodeKind::Unary(op, op_span, arg) => {
+            Node::new(NodeKind::Unary(*op, op_span.clone(), Box::new(derivative(arg, var, config)?)), span.clone())
+        },
+        NodeKind::Binary(op, _, lhs, rhs) => binary_derivative(*op, lhs, rhs, var, config, span)?,
+        // functions of lists like len are constant when their arguments are
+        NodeKind::Func(f, ..) if f.takes_values() && !depends_on(node, var) => num(0, span),
+        NodeKind::Func(f, _, args) => {
+            if function::builtins().get(&f.name) != Some(f) {
+                return Err(format!("Cannot differentiate {}", f.name));
+            }
+            func_derivative(&f.name, args, var, config, span)?
+        },
+        NodeKind::Call(id, ..) => return Err(format!("Unknown function {id}")),
+        NodeKind::WithUnit(..) | NodeKind::Convert(..) => {
+            return Err(String::from("Cannot differentiate quantities with units"))
+        },
+        NodeKind::Assign(..) | NodeKind::Define(..) => {
+            return Err(String::from("Cannot differentiate assignment"))
+        },
+        NodeKind::Cast(_, word, _) => return Err(format!("Cannot differentiate conversion to {word}")),
+        NodeKind::Diff(node, id, order, _) => derivative(&diff(node, id, *order, config)?, var, config)?,
+        NodeKind::Integrate(..) | NodeKind::Solve(..) if !depends_on(node, var) => num(0, span),
+        NodeKind::Integrate(..) => return Err(String::from("Cannot differentiate integrate")),
+        NodeKind::Solve(..) => return Err(String::from("Cannot differentiate solve")),
+        NodeKind::If(cond, then, otherwise, op_span) => {
+            let (then, otherwise) = (derivative(then, var, config)?, derivative(otherwise, var, config)?);
+            Node::new(NodeKind::If(cond.clone(), Box::new(then), Box::new(otherwise), op_span.clone()), span.clone())
+        },
+        NodeKind::Factor(..) => return Err(String::from("Cannot differentiate factor")),
+        // lists are differentiated element by element
+        NodeKind::List(items) => {
+            let items = items.iter().map(|i| derivative(i, var, config)).collect::<Result<_, _>>()?;
+            Node::new(NodeKind::List(items), span.clone())
+        },
+        NodeKind::Index(list, i, op_span) if !depends_on(i, var) => {
+            Node::new(NodeKind::Index(Box::new(derivative(list, var, config)?), i.clone(), op_span.clone()), span.clone())
+        },
+        NodeKind::Index(..) => return Err(String::from("Cannot differentiate index that depends on the variable")),
+        NodeKind::Map(..) | NodeKind::Filter(..) if !depends_on(node, var) => num(0, span),
+        NodeKind::Map(..) => return Err(String::from("Cannot differentiate map")),
+        NodeKind::Filter(..) => return Err(String::from("Cannot differentiate filter"))
+    };
+    Ok(res)
+}
+
+fn binary_derivative(op: Op, l: &Node, r: &Node, var: &Ident, config: &Config, span: &Span) -> Result<Node, String> {
+    let (dl, dr) = (derivative(l, var, config)?, derivative(r, var, config)?);
+    let (l, r) = (l.clone(), r.clone());
+    let res = match op {
+        // comparisons and logical operators are constant where they are continuous
+        Op::Lt | Op::Le | Op::Gt | Op::Ge | Op::Eq | Op::Ne | Op::And | Op::Or | Op::Not => num(0, span),
+        Op::Fact => return Err(String::from("Cannot differentiate factorial")),
+        Op::Percent => binary(Op::Div, dl, num(100, span), span),
+        Op::Angle(_) => Node::new(NodeKind::Unary(op, span.clone(), Box::new(dl)), span.clone()),
+        Op::BitAnd | Op::BitOr | Op::Xor | Op::BitNot | Op::Shl | Op::Shr | Op::Ushr => {
+            return Err(String::from("Cannot differentiate bitwise operators"))
+        },
+        Op::Add | Op::Sub => binary(op, dl, dr, span),
+        // (lr)' = l'r + lr'
+        Op::Mul => binary(Op::Add, binary(Op::Mul, dl, r, span), binary(Op::Mul, l, dr, span), span),
+        // (l/c)' = l'/c
+        Op::Div if !depends_on(&r, var) => binary(Op::Div, dl, r, span),
+        // (l/r)' = (l'r - lr')/r^2
+        Op::Div => {
+            let top = binary(Op::Sub, binary(Op::Mul, dl, r.clone(), span), binary(Op::Mul, l, dr, span), span);
+            binary(Op::Div, top, binary(Op::Pow, r, num(2, span), span), span)
+        },
+        // l % r = l - r*q, where q = (l - l % r)/r is constant between jumps
+        Op::Mod => {
+            let rem = binary(Op::Mod, l.clone(), r.clone(), span);
+            let q = binary(Op::Div, binary(Op::Sub, l, rem, span), r, span);
+            binary(Op::Sub, dl, binary(Op::Mul, dr, q, span), span)
+        },
+        // (l^n)' = n*l^(n-1)*l'
+        Op::Pow if !depends_on(&r, var) => {
+            let pow = binary(Op::Pow, l, binary(Op::Sub, r.clone(), num(1, span), span), span);
+            binary(Op::Mul, binary(Op::Mul, r, pow, span), dl, span)
+        },
+        // (a^r)' = a^r*ln(a)*r'
+        Op::Pow if !depends_on(&l, var) => {
+            let ln = call("ln", vec![l.clone()], span);
+            binary(Op::Mul, binary(Op::Mul, binary(Op::Pow, l, r, span), ln, span), dr, span)
+        },
+        // (l^r)' = l^r*(r'*ln(l) + r*l'/l)
+        Op::Pow => {
+            let ln = call("ln", vec![l.clone()], span);
+            let sum = binary(Op::Add,
+                binary(Op::Mul, dr, ln, span),
+                binary(Op::Div, binary(Op::Mul, r.clone(), dl, span), l.clone(), span),
+                span);
+            binary(Op::Mul, binary(Op::Pow, l, r, span), sum, span)
+        }
+    };
+    Ok(res)
+}
+
+// trigonometric functions take and return angles in the unit of the angle mode,
+// their derivatives in degrees are those in radians times pi/180 or 180/pi
+fn angle_scale(d: Node, to_radians: bool, config: &Config, span: &Span) -> Node {
+    let (from, to) = if to_radians { (config.angle, Angle::Rad) } else { (Angle::Rad, config.angle) };
+    match from.convert(Number::Int(1), to, config.precision) {
+        Number::Int(1) => d,
+        factor => binary(Op::Mul, Node::new(NodeKind::Num(factor), span.clone()), d, span)
+    }
+}
+
+fn func_derivative(name: &str, args: &[Node], var: &Ident, config: &Config, span: &Span) -> Result<Node, String> {
+    let res = match (name, args) {
+        ("sin", [u]) => chain(angle_scale(call("cos", vec![u.clone()], span), true, config, span), u, var, config)?,
+        ("cos", [u]) => chain(angle_scale(neg(call("sin", vec![u.clone()], span), span), true, config, span), u, var, config)?,
+        ("tan", [u]) => {
+            let cos2 = binary(Op::Pow, call("cos", vec![u.clone()], span), num(2, span), span);
+            chain(angle_scale(binary(Op::Div, num(1, span), cos2, span), true, config, span), u, var, config)?
+        },
+        ("asin", [u]) | ("acos", [u]) => {
+            let one_minus = binary(Op::Sub, num(1, span), binary(Op::Pow, u.clone(), num(2, span), span), span);
+            let sign = if name == "asin" { 1 } else { -1 };
+            let d = binary(Op::Div, num(sign, span), call("sqrt", vec![one_minus], span), span);
+            chain(angle_scale(d, false, config, span), u, var, config)?
+        },
+        ("atan", [u]) => {
+            let one_plus = binary(Op::Add, num(1, span), binary(Op::Pow, u.clone(), num(2, span), span), span);
+            chain(angle_scale(binary(Op::Div, num(1, span), one_plus, span), false, config, span), u, var, config)?
+        },
+        // conversions of angles are linear
+        ("deg", [u]) | ("rad", [u]) => call(name, vec![derivative(u, var, config)?], span),
+        ("ln", [u]) => chain(binary(Op::Div, num(1, span), u.clone(), span), u, var, config)?,
+        ("log10", [u]) => {
+            let den = binary(Op::Mul, u.clone(), call("ln", vec![num(10, span)], span), span);
+            chain(binary(Op::Div, num(1, span), den, span), u, var, config)?
+        },
+        ("sqrt", [u]) => {
+            let den = binary(Op::Mul, num(2, span), call("sqrt", vec![u.clone()], span), span);
+            chain(binary(Op::Div, num(1, span), den, span), u, var, config)?
+        },
+        ("abs", [u]) => chain(binary(Op::Div, u.clone(), call("abs", vec![u.clone()], span), span), u, var, config)?,
+        // derivative with respect to real variable
+        ("re", [u]) | ("im", [u]) | ("conj", [u]) => call(name, vec![derivative(u, var, config)?], span),
+        ("arg", [u]) => call("im", vec![binary(Op::Div, derivative(u, var, config)?, u.clone(), span)], span),
+        ("root", [u, n]) => {
+            let pow = binary(Op::Pow, u.clone(), binary(Op::Div, num(1, span), n.clone(), span), span);
+            derivative(&pow, var, config)?
+        },
+        ("log", [u, base]) if !depends_on(base, var) => {
+            let den = binary(Op::Mul, u.clone(), call("ln", vec![base.clone()], span), span);
+            chain(binary(Op::Div, num(1, span), den, span), u, var, config)?
+        },
+        ("log", [u, base]) => {
+            let ln = |n: &Node| call("ln", vec![n.clone()], span);
+            derivative(&binary(Op::Div, ln(u), ln(base), span), var, config)?
+        },
+        ("sum", args) => {
+            let mut res = derivative(&args[0], var, config)?;
+            for arg in &args[1..] {
+                res = binary(Op::Add, res, derivative(arg, var, config)?, span);
+            }
+            res
+        },
+        ("avg", args) | ("mean", args) => {
+            let sum = call("sum", args.to_vec(), span);
+            derivative(&binary(Op::Div, sum, num(args.len() as i128, span), span), var, config)?
+        },
+        ("min", [u]) | ("max", [u]) | ("median", [u]) => derivative(u, var, config)?,
+        // max(u, v) = (u + v + abs(u - v))/2, min(u, v) = (u + v - abs(u - v))/2
+        ("min", [u, v, rest @ ..]) | ("max", [u, v, rest @ ..]) => {
+            let op = if name == "max" { Op::Add } else { Op::Sub };
+            let abs = call("abs", vec![binary(Op::Sub, u.clone(), v.clone(), span)], span);
+            let sum = binary(op, binary(Op::Add, u.clone(), v.clone(), span), abs, span);
+            let pair = binary(Op::Div, sum, num(2, span), span);
+            if rest.is_empty() {
+                derivative(&pair, var, config)?
+            } else {
+                let args = [vec![pair], rest.to_vec()].concat();
+                func_derivative(name, &args, var, config, span)?
+            }
+        },
+        ("median", [u, v]) => {
+            derivative(&binary(Op::Div, binary(Op::Add, u.clone(), v.clone(), span), num(2, span), span), var, config)?
+        },
+        ("median", [_, _, _]) => {
+            let sum = call("sum", args.to_vec(), span);
+            let max = call("max", args.to_vec(), span);
+            let min = call("min", args.to_vec(), span);
+            derivative(&binary(Op::Sub, binary(Op::Sub, sum, max, span), min, span), var, config)?
+        },
+        ("median", _) => return Err(String::from("Cannot differentiate median of more than 3 arguments")),
+        // integer valued, constant between jumps
+        ("gcd", _) | ("lcm", _) => num(0, span),
+        _ => return Err(format!("Cannot differentiate {name}"))
+    };
+    Ok(res)
+}
+
+// chain rule, f(u)' = f'(u)*u'
+fn chain(outer: Node, u: &Node, var: &Ident, config: &Config) -> Result<Node, String> {
+    let span = outer.span.clone();
+    Ok(binary(Op::Mul, outer, derivative(u, var, config)?, &span))
+}
+
+fn as_num(node: &Node) -> Option<&Number> {
+    match &node.kind {
+        NodeKind::Num(n) => Some(n),
+        _ => None
+    }
+}
+
+fn is_num(node: &Node, value: i128) -> bool {
+    as_num(node) == Some(&Number::Int(value))
+}
+
+fn is_exact(n: &Number) -> bool {
+    matches!(n, Number::Int(_) | Number::BigInt(_) | Number::Rational(_))
+}
+
+// expressions are the same if they print the same
+fn same(a: &Node, b: &Node) -> bool {
+    a.to_string() == b.to_string()
+}
+
+/// Folds constants, removes operations that do nothing, like x*1 or x + 0,
+/// and collects like factors and terms, so x*x is x^2 and x + 2*x is 3*x
+pub fn simplify(node: Node, config: &Config) -> Node {
+    if let Some(node) = node.percent_change() {
+        return simplify(node, config);
+    }
+    let span = node.span.clone();
+    let simplify = |node: Node| simplify(node, config);
+    match node.kind {
+        NodeKind::Unary(op, op_span, arg) => simplify_unary(op, op_span, simplify(*arg), config, &span),
+        NodeKind::Binary(op, _, lhs, rhs) => simplify_binary(op, simplify(*lhs), simplify(*rhs), config, &span),
+        NodeKind::Func(f, op_span, args) => simplify_func(f, op_span, args.into_iter().map(simplify).collect(), config, &span),
+        NodeKind::If(cond, then, otherwise, op_span) => {
+            let cond = simplify(*cond);
+            match as_num(&cond) {
+                Some(n) if n.is_zero() => simplify(*otherwise),
+                Some(_) => simplify(*then),
+                None => {
+                    let (then, otherwise) = (Box::new(simplify(*then)), Box::new(simplify(*otherwise)));
+                    Node::new(NodeKind::If(Box::new(cond), then, otherwise, op_span), span)
+                }
+            }
+        },
+        NodeKind::List(items) => Node::new(NodeKind::List(items.into_iter().map(simplify).collect()), span),
+        NodeKind::Index(list, i, op_span) => {
+            Node::new(NodeKind::Index(Box::new(simplify(*list)), Box::new(simplify(*i)), op_span), span)
+        },
+        kind => Node::new(kind, span)
+    }
+}
+
+fn simplify_func(f: Function, op_span: Span, args: Vec<Node>, config: &Config, span: &Span) -> Node {
+    // ln(e) is 1, named constants are not folded otherwise
+    let is_e = |n: &Node| matches!(&n.kind, NodeKind::Const(name, _) if name == "e");
+    if f.name == "ln" && function::builtins().get("ln") == Some(&f) && args.len() == 1 && is_e(&args[0]) {
+        return num(1, span);
+    }
+    let mut values: Vec<Number> = args.iter().filter_map(as_num).cloned().collect();
+    if values.len() == args.len() {
+        let inexact = values.iter().any(|n| !is_exact(n));
+        let res = f.exec(&mut values, config);
+        if res != Number::NaN && (is_exact(&res) || inexact) {
+            return Node::new(NodeKind::Num(res), span.clone());
+        }
+    }
+    Node::new(NodeKind::Func(f, op_span, args), span.clone())
+}
+
+fn simplify_unary(op: Op, op_span: Span, arg: Node, config: &Config, span: &Span) -> Node {
+    match (op, arg.kind) {
+        (Op::Add, kind) => Node::new(kind, span.clone()),
+        (Op::Sub, NodeKind::Num(n)) => Node::new(NodeKind::Num(-n), span.clone()),
+        (Op::Not, NodeKind::Num(n)) => Node::new(NodeKind::Num(n.is_zero().into()), span.clone()),
+        (Op::BitNot, NodeKind::Num(n)) if n.bit_not() != Number::NaN => Node::new(NodeKind::Num(n.bit_not()), span.clone()),
+        // only exact factorials are folded, 0.5! stays as it is
+        (Op::Fact, NodeKind::Num(n)) if n.to_bigint().is_some() => match integer::factorial(&n) {
+            Number::NaN => Node::new(NodeKind::Unary(op, op_span, Box::new(Node::new(NodeKind::Num(n), arg.span))), span.clone()),
+            res => Node::new(NodeKind::Num(res), span.clone())
+        },
+        // x% is x/100, so that simplified expressions have no percent changes like a + b%
+        (Op::Percent, kind) => simplify_binary(Op::Div, Node::new(kind, arg.span), num(100, &op_span), config, span),
+        // 30° is a number in the unit of the angle mode
+        (Op::Angle(unit), NodeKind::Num(n)) => Node::new(NodeKind::Num(unit.convert(n, config.angle, config.precision)), span.clone()),
+        (Op::Sub, kind) => canonical(neg(Node::new(kind, arg.span), &op_span), config, span),
+        (op, kind) => Node::new(NodeKind::Unary(op, op_span, Box::new(Node::new(kind, arg.span))), span.clone())
+    }
+}
+
+fn simplify_binary(op: Op, l: Node, r: Node, config: &Config, span: &Span) -> Node {
+    if let (Some(a), Some(b)) = (as_num(&l), as_num(&r)) {
+        let res = number_op(op, a.clone(), b.clone(), config);
+        if res != Number::NaN && (is_exact(&res) || !is_exact(a) || !is_exact(b)) {
+            return Node::new(NodeKind::Num(res), span.clone());
+        }
+    }
+    match op {
+        Op::Add | Op::Sub | Op::Mul | Op::Div => canonical(binary(op, l, r, span), config, span),
+        Op::Pow if is_num(&r, 0) || is_num(&l, 1) => num(1, span),
+        Op::Pow if is_num(&r, 1) => l,
+        // integer powers of products are multiplied out, (2*x)^2 is 4*x^2
+        Op::Pow if matches!(as_num(&r), Some(Number::Int(_))) => canonical(binary(op, l, r, span), config, span),
+        _ => binary(op, l, r, span)
+    }
+}
+
+// Sum is kept as terms, each of them is a coefficient times factors
+// raised to exponents. Factors of a term are in canonical order, so
+// that like terms print the same.
+struct Term {
+    coef: Number,
+    factors: Vec<(Node, Node)>
+}
+
+impl Term {
+    // term of a single node, the node is split into factors
+    fn of(node: Node, config: &Config) -> Term {
+        let mut term = Term { coef: Number::Int(1), factors: vec![] };
+        term.mul(node, 1, config);
+        term.factors.retain(|(_, exp)| !is_num(exp, 0));
+        term.factors.sort_by_key(|(base, exp)| factor_order(base, exp));
+        // 1.0*x is x
+        for unit in [1, -1] {
+            if !is_exact(&term.coef) && term.coef.compare(&Number::Int(unit)) == Some(Ordering::Equal) {
+                term.coef = Number::Int(unit);
+            }
+        }
+        term
+    }
+
+    // multiplies the term by node raised to integer exponent
+    fn mul(&mut self, node: Node, exp: i128, config: &Config) {
+        let span = node.span.clone();
+        match node.kind {
+            NodeKind::Num(n) => match number_op(Op::Pow, n.clone(), Number::Int(exp), config) {
+                Number::NaN => self.push(Node::new(NodeKind::Num(n), span.clone()), num(exp, &span), config),
+                res => self.coef = number_op(Op::Mul, self.coef.clone(), res, config)
+            },
+            NodeKind::Unary(Op::Sub, _, arg) => {
+                if exp % 2 != 0 {
+                    self.coef = -self.coef.clone();
+                }
+                self.mul(*arg, exp, config);
+            },
+            NodeKind::Binary(Op::Mul, _, l, r) => {
+                self.mul(*l, exp, config);
+                self.mul(*r, exp, config);
+            },
+            NodeKind::Binary(Op::Div, _, l, r) => {
+                self.mul(*l, exp, config);
+                self.mul(*r, -exp, config);
+            },
+            NodeKind::Binary(Op::Pow, _, base, k) => match as_num(&k) {
+                Some(Number::Int(k)) if k.checked_mul(exp).is_some() => self.mul(*base, k * exp, config),
+                // (a^r)^n is a^(r*n) for integer n
+                _ => self.push(*base, simplify_binary(Op::Mul, num(exp, &span), *k, config, &span), config)
+            },
+            kind => self.push(Node::new(kind, span.clone()), num(exp, &span), config)
+        }
+    }
+
+    // multiplies by base^exp, exponents of the same base add up
+    fn push(&mut self, base: Node, exp: Node, config: &Config) {
+        match self.factors.iter_mut().find(|(b, _)| same(b, &base)) {
+            Some((_, e)) => {
+                let span = e.span.clone();
+                *e = simplify_binary(Op::Add, e.clone(), exp, config, &span);
+            },
+            None => self.factors.push((base, exp))
+        }
+    }
+
+    // factors without the coefficient, same for like terms
+    fn key(&self) -> String {
+        let factors: Vec<_> = self.factors.iter().map(|(base, exp)| format!("({base})^({exp})")).collect();
+        factors.join("*")
+    }
+
+    // factors with negative exponents go to the denominator, rational
+    // coefficient is split between them, like 3*x/(2*y)
+    fn to_node(&self, span: &Span) -> Node {
+        let power = |base: &Node, exp: Node| if is_num(&exp, 1) { base.clone() } else { binary(Op::Pow, base.clone(), exp, span) };
+        let (mut top, mut bottom) = (vec![], vec![]);
+        for (base, exp) in &self.factors {
+            match as_num(exp) {
+                Some(n) if n.is_negative() => bottom.push(power(base, Node::new(NodeKind::Num(-n.clone()), exp.span.clone()))),
+                _ => top.push(power(base, exp.clone()))
+            }
+        }
+        let (p, q) = match &self.coef {
+            Number::Rational(r) => (Number::from(r.numer().clone()), Number::from(r.denom().clone())),
+            n => (n.clone(), Number::Int(1))
+        };
+        let number = |n: Number| Node::new(NodeKind::Num(n), span.clone());
+        // sign goes to the leading number or to the first factor, -x*y,
+        // -x^2 would be (-x)^2 so a power is negated with the rest, -(x^2*y)
+        let unit = p == Number::Int(1) || p == Number::Int(-1);
+        let negate = unit && p.is_negative() && !top.is_empty();
+        if !unit || top.is_empty() {
+            top.insert(0, number(p));
+        } else if negate && !matches!(top[0].kind, NodeKind::Binary(Op::Pow, ..)) {
+            top[0] = neg(top[0].clone(), span);
+        }
+        let power_first = negate && matches!(top[0].kind, NodeKind::Binary(Op::Pow, ..));
+        if q != Number::Int(1) {
+            bottom.insert(0, number(q));
+        }
+        let product = |factors: Vec<Node>| factors.into_iter().reduce(|l, r| binary(Op::Mul, l, r, span));
+        let top = product(top).unwrap_or_else(|| num(1, span));
+        let top = if power_first { neg(top, span) } else { top };
+        match product(bottom) {
+            Some(bottom) => binary(Op::Div, top, bottom, span),
+            None => top
+        }
+    }
+}
+
+// order of factors of a term, numbers go first, then named constants,
+// variables, powers with symbolic exponents and functions, sin before cos,
+// like 2*pi*x*e^x*sin(x)*cos(x)
+fn factor_order(base: &Node, exp: &Node) -> (u8, usize, String) {
+    const TRIG: [&str; 3] = ["sin", "cos", "tan"];
+    let (rank, order) = match &base.kind {
+        NodeKind::Num(_) if as_num(exp).is_some() => (0, 0),
+        _ if as_num(exp).is_none() => (3, 0),
+        NodeKind::Const(..) => (1, 0),
+        NodeKind::Var(_) => (2, 0),
+        NodeKind::Func(f, ..) => (4, TRIG.iter().position(|name| *name == f.name).unwrap_or(TRIG.len())),
+        _ => (5, 0)
+    };
+    (rank, order, base.to_string())
+}
+
+// splits sum into terms, the terms of a difference are negated
+fn add_terms(node: Node, negate: bool, terms: &mut Vec<Term>, config: &Config) {
+    match node.kind {
+        NodeKind::Binary(Op::Add, _, l, r) => {
+            add_terms(*l, negate, terms, config);
+            add_terms(*r, negate, terms, config);
+        },
+        NodeKind::Binary(Op::Sub, _, l, r) => {
+            add_terms(*l, negate, terms, config);
+            add_terms(*r, !negate, terms, config);
+        },
+        NodeKind::Unary(Op::Sub, _, arg) => add_terms(*arg, !negate, terms, config),
+        kind => {
+            let mut term = Term::of(Node::new(kind, node.span), config);
+            if negate {
+                term.coef = -term.coef;
+            }
+            // -(a + b) is a sum too
+            let is_unit = term.coef == Number::Int(1) || term.coef == Number::Int(-1);
+            match &term.factors[..] {
+                [(base, exp)] if is_unit && is_num(exp, 1) && is_sum(base) => {
+                    let (base, negate) = (base.clone(), term.coef.is_negative());
+                    add_terms(base, negate, terms, config);
+                },
+                _ => terms.push(term)
+            }
+        }
+    }
+}
+
+fn is_sum(node: &Node) -> bool {
+    matches!(node.kind, NodeKind::Binary(Op::Add | Op::Sub, ..) | NodeKind::Unary(Op::Sub, ..))
+}
+
+// sum or product with like terms and factors collected, terms stay
+// in the order they first appear
+fn canonical(node: Node, config: &Config, span: &Span) -> Node {
+    let mut terms = vec![];
+    add_terms(node, false, &mut terms, config);
+    let mut collected: Vec<Term> = vec![];
+    for term in terms {
+        match collected.iter_mut().find(|t| t.key() == term.key()) {
+            Some(like) => like.coef = number_op(Op::Add, like.coef.clone(), term.coef, config),
+            None => collected.push(term)
+        }
+    }
+    collected.retain(|term| !term.coef.is_zero());
+    let mut res: Option<Node> = None;
+    for mut term in collected {
+        res = Some(match res {
+            None => term.to_node(span),
+            Some(sum) if term.coef.is_negative() => {
+                term.coef = -term.coef;
+                binary(Op::Sub, sum, term.to_node(span), span)
+            },
+            Some(sum) => binary(Op::Add, sum, term.to_node(span), span)
+        });
+    }
+    res.unwrap_or_else(|| num(0, span))
+}
@@ -0,0 +1,114 @@
+use super::*;
+use crate::Parser;
+
+fn parse(input: &str) -> Node {
+    let mut p = Parser::new(input);
+    p.parse().unwrap();
+    p.ast.unwrap()
+}
+
+fn d(input: &str) -> String {
+    diff(&parse(input), &Ident::new("x"), 1, &Config::default()).unwrap().to_string()
+}
+
+#[test]
+fn test_rules() {
+    assert_eq!(d("5"), "0");
+    assert_eq!(d("x"), "1");
+    assert_eq!(d("y"), "0");
+    assert_eq!(d("-x"), "-1");
+    assert_eq!(d("x + y"), "1");
+    assert_eq!(d("x - 3*x"), "-2");
+    assert_eq!(d("x^2*sin(x)"), "2*x*sin(x) + x^2*cos(x)");
+    assert_eq!(d("1/x"), "-1/x^2");
+    assert_eq!(d("x^y"), "y*x^(y - 1)");
+    assert_eq!(d("2^x"), "2^x*ln(2)");
+    assert_eq!(d("x^x"), "x^x*(ln(x) + 1)");
+    assert_eq!(d("x % 3"), "1");
+    assert_eq!(d("x%"), "1/100");
+    assert_eq!(d("x + 15%"), "23/20");
+    assert_eq!(d("x^2 - 50%"), "x");
+}
+
+#[test]
+fn test_functions() {
+    assert_eq!(d("sin(2*x)"), "2*cos(2*x)");
+    assert_eq!(d("cos(x)"), "-sin(x)");
+    assert_eq!(d("tan(x)"), "1/cos(x)^2");
+    assert_eq!(d("asin(x)"), "1/sqrt(1 - x^2)");
+    assert_eq!(d("acos(x)"), "-1/sqrt(1 - x^2)");
+    assert_eq!(d("atan(x)"), "1/(1 + x^2)");
+    assert_eq!(d("ln(x)"), "1/x");
+    assert_eq!(d("log10(x)"), "1/(x*ln(10))");
+    assert_eq!(d("log(x, 2)"), "1/(x*ln(2))");
+    assert_eq!(d("log(2, x)"), "-ln(2)/(x*ln(x)^2)");
+    assert_eq!(d("sqrt(x)"), "1/(2*sqrt(x))");
+    assert_eq!(d("root(x, 3)"), "1/(3*x^(2/3))");
+    assert_eq!(d("abs(x)"), "x/abs(x)");
+    assert_eq!(d("re(x)"), "1");
+    assert_eq!(d("im(x)"), "0");
+    assert_eq!(d("arg(x)"), "im(1/x)");
+    assert_eq!(d("conj(x)"), "1");
+    assert_eq!(d("min(x, 1)"), "(1 - (x - 1)/abs(x - 1))/2");
+    assert_eq!(d("max(x, 1)"), "(1 + (x - 1)/abs(x - 1))/2");
+    assert_eq!(d("sum(x, x^2, 3)"), "1 + 2*x");
+    assert_eq!(d("avg(x, 3*x)"), "2");
+    assert_eq!(d("median(x)"), "1");
+    assert_eq!(d("gcd(x, 4)"), "0");
+    assert_eq!(d("lcm(x, 4)"), "0");
+}
+
+#[test]
+fn test_higher_order() {
+    let x = Ident::new("x");
+    assert_eq!(diff(&parse("x^3"), &x, 2, &Config::default()).unwrap().to_string(), "6*x");
+    assert_eq!(diff(&parse("x^3"), &x, 4, &Config::default()).unwrap().to_string(), "0");
+    assert_eq!(diff(&parse("sin(x)"), &x, 2, &Config::default()).unwrap().to_string(), "-sin(x)");
+    assert_eq!(d("diff(x^3, x)"), "6*x");
+    assert_eq!(d("diff(x*y, y)"), "1");
+    assert_eq!(diff(&parse("sin(x)^2"), &x, 3, &Config::default()).unwrap().to_string(), "-8*sin(x)*cos(x)");
+    assert_eq!(diff(&parse("sqrt(x)"), &x, 2, &Config::default()).unwrap().to_string(), "-1/(4*sqrt(x)^3)");
+}
+
+#[test]
+fn test_simplify() {
+    let simplified = |input: &str| simplify(parse(input), &Config::default()).to_string();
+    assert_eq!(simplified("x*y*x - 2*x^2*y"), "-(x^2*y)");
+    assert_eq!(simplified("y - y*2"), "-y");
+    assert_eq!(simplified("cos(x)*2*sin(x) + sin(x)*cos(x)"), "3*sin(x)*cos(x)");
+    assert_eq!(simplified("(2*x)^3/x"), "8*x^2");
+    assert_eq!(simplified("1.0*x - (y - x)"), "2*x - y");
+    assert_eq!(simplified("2*pi*r"), "2*pi*r");
+    assert_eq!(d("e^x"), "e^x");
+    assert_eq!(d("e^(2*x)"), "2*e^(2*x)");
+    assert_eq!(d("pi*x^2"), "2*pi*x");
+}
+
+#[test]
+fn test_errors() {
+    let x = Ident::new("x");
+    assert!(diff(&parse("f(x)"), &x, 1, &Config::default()).is_err());
+    assert!(diff(&parse("(x) km"), &x, 1, &Config::default()).is_err());
+    assert!(diff(&parse("median(x, 1, 2, 3)"), &x, 1, &Config::default()).is_err());
+}
+
+#[test]
+fn test_substitute() {
+    let vars = [(Ident::new("x"), parse("y + 1"))];
+    assert_eq!(substitute(&parse("x^2 + diff(x, x)"), &vars).to_string(), "(y + 1)^2 + diff(x, x)");
+    assert_eq!(free_vars(&parse("x*y + diff(x*z, x)")), vec![Ident::new("x"), Ident::new("y"), Ident::new("z")]);
+}
+
+#[test]
+fn test_lists() {
+    assert_eq!(d("[x^2, 3*x, y]"), "[2*x, 3, 0]");
+    assert_eq!(d("[x, x^2][1]"), "[1, 2*x][1]");
+    assert_eq!(d("len(y) * x"), "len(y)");
+    assert_eq!(d("sum(map(k*y, k, [1, 2]))"), "0");
+    let x = Ident::new("x");
+    assert!(diff(&parse("[1, 2][x]"), &x, 1, &Config::default()).is_err());
+    assert!(diff(&parse("sort([x, 1])"), &x, 1, &Config::default()).is_err());
+    assert_eq!(free_vars(&parse("map(k*y, k, z)")), vec![Ident::new("z"), Ident::new("y")]);
+    let vars = [(Ident::new("k"), parse("2")), (Ident::new("y"), parse("3"))];
+    assert_eq!(substitute(&parse("filter(k > y, k, [k])"), &vars).to_string(), "filter(k > 3, k, [2])");
+}
@@ -0,0 +1,322 @@
+use crate::number::Number;
+use crate::number::pattern;
+use currency::Rates;
+use std::fmt::Display;
+
+pub mod currency;
+
+#[cfg(test)]
+mod tests;
+
+/// Exponents of SI base dimensions: length, mass, time,
+/// electric current, temperature, amount of substance, luminous intensity,
+/// and of money, which is not SI but converts like them
+pub type Dimension = [i32; 8];
+
+const NONE: Dimension = [0, 0, 0, 0, 0, 0, 0, 0];
+const LENGTH: Dimension = [1, 0, 0, 0, 0, 0, 0, 0];
+const MASS: Dimension = [0, 1, 0, 0, 0, 0, 0, 0];
+const TIME: Dimension = [0, 0, 1, 0, 0, 0, 0, 0];
+const CURRENT: Dimension = [0, 0, 0, 1, 0, 0, 0, 0];
+const TEMPERATURE: Dimension = [0, 0, 0, 0, 1, 0, 0, 0];
+const AMOUNT: Dimension = [0, 0, 0, 0, 0, 1, 0, 0];
+const LUMINOSITY: Dimension = [0, 0, 0, 0, 0, 0, 1, 0];
+const FREQUENCY: Dimension = [0, 0, -1, 0, 0, 0, 0, 0];
+const SPEED: Dimension = [1, 0, -1, 0, 0, 0, 0, 0];
+const VOLUME: Dimension = [3, 0, 0, 0, 0, 0, 0, 0];
+const FORCE: Dimension = [1, 1, -2, 0, 0, 0, 0, 0];
+const ENERGY: Dimension = [2, 1, -2, 0, 0, 0, 0, 0];
+const POWER: Dimension = [2, 1, -3, 0, 0, 0, 0, 0];
+const PRESSURE: Dimension = [-1, 1, -2, 0, 0, 0, 0, 0];
+const CHARGE: Dimension = [0, 0, 1, 1, 0, 0, 0, 0];
+const VOLTAGE: Dimension = [2, 1, -3, -1, 0, 0, 0, 0];
+const RESISTANCE: Dimension = [2, 1, -3, -2, 0, 0, 0, 0];
+const MONEY: Dimension = [0, 0, 0, 0, 0, 0, 0, 1];
+
+struct UnitDef {
+    symbol: &'static str,
+    // size of the unit in SI base units, as numerator and denominator
+    factor: (i128, i128),
+    dim: Dimension,
+    // accepts SI prefixes, like km or mA
+    prefixed: bool
+}
+
+const fn unit(symbol: &'static str, factor: (i128, i128), dim: Dimension, prefixed: bool) -> UnitDef {
+    UnitDef { symbol, factor, dim, prefixed }
+}
+
+static UNITS: &[UnitDef] = &[
+    unit("m", (1, 1), LENGTH, true),
+    unit("g", (1, 1000), MASS, true),
+    unit("s", (1, 1), TIME, true),
+    unit("A", (1, 1), CURRENT, true),
+    unit("K", (1, 1), TEMPERATURE, true),
+    unit("mol", (1, 1), AMOUNT, true),
+    unit("cd", (1, 1), LUMINOSITY, true),
+    unit("Hz", (1, 1), FREQUENCY, true),
+    unit("N", (1, 1), FORCE, true),
+    unit("J", (1, 1), ENERGY, true),
+    unit("W", (1, 1), POWER, true),
+    unit("Wh", (3600, 1), ENERGY, true),
+    unit("Pa", (1, 1), PRESSURE, true),
+    unit("bar", (100_000, 1), PRESSURE, true),
+    unit("C", (1, 1), CHARGE, true),
+    unit("V", (1, 1), VOLTAGE, true),
+    unit("Ohm", (1, 1), RESISTANCE, true),
+    unit("L", (1, 1000), VOLUME, true),
+    unit("cal", (4184, 1000), ENERGY, true),
+    unit("min", (60, 1), TIME, false),
+    unit("h", (3600, 1), TIME, false),
+    unit("day", (86400, 1), TIME, false),
+    unit("days", (86400, 1), TIME, false),
+    unit("week", (604_800, 1), TIME, false),
+    unit("weeks", (604_800, 1), TIME, false),
+    unit("t", (1000, 1), MASS, false),
+    unit("inch", (254, 10_000), LENGTH, false),
+    unit("ft", (3048, 10_000), LENGTH, false),
+    unit("yd", (9144, 10_000), LENGTH, false),
+    unit("mi", (1_609_344, 1000), LENGTH, false),
+    unit("nmi", (1852, 1), LENGTH, false),
+    unit("lb", (45_359_237, 100_000_000), MASS, false),
+    unit("oz", (45_359_237, 1_600_000_000), MASS, false),
+    unit("mph", (1_609_344, 3_600_000), SPEED, false),
+    unit("kn", (1852, 3600), SPEED, false),
+];
+
+// "da" goes before "d" so that dam is decametre, not decimetre
+static PREFIXES: &[(&str, i128)] = &[
+    ("da", 1), ("Y", 24), ("Z", 21), ("E", 18), ("P", 15), ("T", 12), ("G", 9),
+    ("M", 6), ("k", 3), ("h", 2), ("d", -1), ("c", -2), ("m", -3), ("u", -6),
+    ("µ", -6), ("n", -9), ("p", -12), ("f", -15), ("a", -18),
+];
+
+/// Product of unit symbols raised to integer powers, e.g. km/h or kg*m/s^2
+#[derive(Clone, Debug, PartialEq)]
+pub struct Unit {
+    terms: Vec<(String, i32)>,
+    // size of the unit in SI base units
+    factor: Number,
+    dim: Dimension
+}
+
+fn find_unit(symbol: &str) -> Option<(&UnitDef, i128)> {
+    if let Some(def) = UNITS.iter().find(|u| u.symbol == symbol) {
+        return Some((def, 0));
+    }
+    PREFIXES.iter().find_map(|(prefix, exp)| {
+        let rest = symbol.strip_prefix(prefix)?;
+        let def = UNITS.iter().find(|u| u.prefixed && u.symbol == rest)?;
+        Some((def, *exp))
+    })
+}
+
+/// Returns unit for symbol like `m`, `km`, `mph` or `EUR`
+pub fn parse_unit(symbol: &str) -> Option<Unit> {
+    // value of currencies comes from exchange rates when they are converted
+    if currency::is_currency(symbol) {
+        return Some(Unit {
+            terms: vec![(String::from(symbol), 1)],
+            factor: Number::Int(1),
+            dim: MONEY
+        });
+    }
+    let (def, exp) = find_unit(symbol)?;
+    let factor = Number::Int(def.factor.0) / Number::Int(def.factor.1);
+    let factor = factor * (Number::Int(10) ^ Number::Int(exp));
+    Some(Unit {
+        terms: vec![(String::from(symbol), 1)],
+        factor,
+        dim: def.dim
+    })
+}
+
+pub fn is_unit(symbol: &str) -> bool {
+    find_unit(symbol).is_some() || currency::is_currency(symbol)
+}
+
+impl Unit {
+    pub fn dimensionless() -> Unit {
+        Unit {
+            terms: vec![],
+            factor: Number::Int(1),
+            dim: NONE
+        }
+    }
+
+    pub fn is_dimensionless(&self) -> bool {
+        self.dim == NONE
+    }
+
+    pub fn same_dimension(&self, other: &Unit) -> bool {
+        self.dim == other.dim
+    }
+
+    pub fn factor(&self) -> &Number {
+        &self.factor
+    }
+
+    /// Size of the unit in SI base units and the base currency of exchange
+    /// rates. Fails when a rate is missing, currencies that cancel out,
+    /// like in EUR/EUR, need no rates.
+    pub fn exchange_factor(&self, rates: &Rates) -> Result<Number, String> {
+        let mut factor = self.factor.clone();
+        for (symbol, pow) in self.terms.iter().filter(|(s, _)| currency::is_currency(s)) {
+            factor = factor * (rates.value(symbol)? ^ Number::Int(*pow as i128));
+        }
+        Ok(factor)
+    }
+
+    /// Code of the currency if the unit is just a currency, like EUR
+    pub fn currency(&self) -> Option<&str> {
+        match &self.terms[..] {
+            [(symbol, 1)] if self.dim == MONEY => Some(symbol),
+            _ => None
+        }
+    }
+
+    /// Symbol of the unit if it is a single unit without a power, like g
+    pub fn symbol(&self) -> Option<&str> {
+        match &self.terms[..] {
+            [(symbol, 1)] => Some(symbol),
+            _ => None
+        }
+    }
+
+    /// Symbols of the unit with their powers, km/h is [(km, 1), (h, -1)]
+    pub fn terms(&self) -> &[(String, i32)] {
+        &self.terms
+    }
+
+    /// Unit of the terms for which `keep` is true, m/s without s is m
+    pub fn retain<F>(&self, keep: F) -> Unit
+        where F: Fn(&str) -> bool
+    {
+        self.terms.iter()
+            .filter(|(s, _)| keep(s))
+            .filter_map(|(s, p)| Some(parse_unit(s)?.powi(*p)))
+            .fold(Unit::dimensionless(), |acc, u| acc.mul(&u))
+    }
+
+    pub fn mul(&self, rhs: &Unit) -> Unit {
+        let mut terms = self.terms.clone();
+        for (symbol, pow) in &rhs.terms {
+            match terms.iter_mut().find(|(s, _)| s == symbol) {
+                Some((_, p)) => *p += pow,
+                None => terms.push((symbol.clone(), *pow))
+            }
+        }
+        terms.retain(|(_, p)| *p != 0);
+        let mut dim = self.dim;
+        for (d, r) in dim.iter_mut().zip(rhs.dim) {
+            *d += r;
+        }
+        Unit {
+            terms,
+            factor: self.factor.clone() * rhs.factor.clone(),
+            dim
+        }
+    }
+
+    pub fn powi(&self, n: i32) -> Unit {
+        Unit {
+            terms: self.terms.iter()
+                .map(|(s, p)| (s.clone(), p * n))
+                .filter(|(_, p)| *p != 0)
+                .collect(),
+            factor: self.factor.clone() ^ Number::Int(n as i128),
+            dim: self.dim.map(|d| d * n)
+        }
+    }
+
+    pub fn div(&self, rhs: &Unit) -> Unit {
+        self.mul(&rhs.powi(-1))
+    }
+}
+
+fn fmt_terms<'a, I>(terms: I) -> String
+    where I: Iterator<Item = (&'a String, i32)>
+{
+    terms.map(|(s, p)| if p == 1 { s.clone() } else { format!("{s}^{p}") })
+        .collect::<Vec<_>>()
+        .join("*")
+}
+
+/// Prints unit as `kg*m/s^2`, or `m/(kg*s)` if there is more than one term below the line
+impl Display for Unit {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let num: Vec<_> = self.terms.iter().filter(|(_, p)| *p > 0).map(|(s, p)| (s, *p)).collect();
+        let den: Vec<_> = self.terms.iter().filter(|(_, p)| *p < 0).map(|(s, p)| (s, -*p)).collect();
+        let num_str = if num.is_empty() { String::from("1") } else { fmt_terms(num.into_iter()) };
+        match den.len() {
+            0 if self.terms.is_empty() => Ok(()),
+            0 => write!(f, "{num_str}"),
+            1 => write!(f, "{num_str}/{}", fmt_terms(den.into_iter())),
+            _ => write!(f, "{num_str}/({})", fmt_terms(den.into_iter()))
+        }
+    }
+}
+
+/// Number with a unit, e.g. 5 km
+#[derive(Clone, Debug, PartialEq)]
+pub struct Quantity {
+    pub value: Number,
+    pub unit: Unit
+}
+
+impl Quantity {
+    pub fn new(value: Number, unit: Unit) -> Quantity {
+        Quantity { value, unit }
+    }
+
+    /// Expresses the quantity exactly in another unit of the same dimension
+    pub fn convert(&self, unit: &Unit, rates: &Rates) -> Result<Quantity, String> {
+        if !self.unit.same_dimension(unit) {
+            return Err(format!("Cannot convert {} to {}", describe(&self.unit), describe(unit)));
+        }
+        let value = self.value.clone() * self.unit.div(unit).exchange_factor(rates)?;
+        Ok(Quantity::new(value, unit.clone()))
+    }
+
+    /// Converts like `convert`, but amounts exchanged to another currency
+    /// are rounded to its smallest unit, like cents. Only conversions asked
+    /// for with `in` are rounded, sums and comparisons use exact amounts.
+    pub fn exchange(&self, unit: &Unit, rates: &Rates) -> Result<Quantity, String> {
+        let q = self.convert(unit, rates)?;
+        match unit.currency() {
+            Some(code) if self.unit.currency() != Some(code) => {
+                let digits = currency::decimals(code).unwrap_or_default();
+                let value = pattern::round(&q.value, digits).unwrap_or(q.value);
+                Ok(Quantity::new(value, q.unit))
+            },
+            _ => Ok(q)
+        }
+    }
+
+    /// Value in SI base units
+    pub fn base_value(&self) -> Number {
+        self.value.clone() * self.unit.factor.clone()
+    }
+}
+
+/// Unit for error messages, `number` when it has no dimension
+pub fn describe(unit: &Unit) -> String {
+    if unit.is_dimensionless() {
+        String::from("number")
+    } else {
+        unit.to_string()
+    }
+}
+
+/// Rational values are printed as decimals, e.g. `5.3 km`, and amounts
+/// of money with the digits of their currency, e.g. `12.50 EUR`
+impl Display for Quantity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let digits = self.unit.currency().and_then(currency::decimals);
+        let amount = digits.and_then(|digits| pattern::format(&self.value, &format!("0.{:0<digits$}", "")).ok());
+        match amount {
+            Some(amount) => write!(f, "{} {}", amount.trim_end_matches('.'), self.unit),
+            None => write!(f, "{:#} {}", self.value, self.unit)
+        }
+    }
+}
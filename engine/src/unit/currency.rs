@@ -0,0 +1,242 @@
+use crate::number::Number;
+use crate::number::pattern;
+use num_traits::Signed;
+use std::collections::HashMap;
+
+#[cfg(test)]
+mod tests;
+
+// ISO 4217 codes with the number of digits of their smallest unit, like cents
+static CURRENCIES: &[(&str, usize)] = &[
+    ("AED", 2), ("ARS", 2), ("AUD", 2), ("BGN", 2), ("BHD", 3), ("BRL", 2), ("CAD", 2),
+    ("CHF", 2), ("CLP", 0), ("CNY", 2), ("COP", 2), ("CZK", 2), ("DKK", 2), ("EGP", 2),
+    ("EUR", 2), ("GBP", 2), ("HKD", 2), ("HUF", 2), ("IDR", 2), ("ILS", 2), ("INR", 2),
+    ("ISK", 0), ("JOD", 3), ("JPY", 0), ("KRW", 0), ("KWD", 3), ("MXN", 2), ("MYR", 2),
+    ("NGN", 2), ("NOK", 2), ("NZD", 2), ("OMR", 3), ("PHP", 2), ("PLN", 2), ("RON", 2),
+    ("SAR", 2), ("SEK", 2), ("SGD", 2), ("THB", 2), ("TND", 3), ("TRY", 2), ("TWD", 2),
+    ("UAH", 2), ("USD", 2), ("VND", 0), ("ZAR", 2),
+];
+
+pub fn is_currency(code: &str) -> bool {
+    decimals(code).is_some()
+}
+
+/// Number of fraction digits amounts in the currency are rounded to
+pub fn decimals(code: &str) -> Option<usize> {
+    CURRENCIES.iter().find(|(c, _)| *c == code).map(|(_, digits)| *digits)
+}
+
+/// Exchange rates, as amounts of each currency worth one unit of a common
+/// base currency. Rates are exact, 1.0835 is 10835/10000.
+#[derive(Clone, Debug, PartialEq, Default)]
+pub struct Rates(HashMap<String, Number>);
+
+impl Rates {
+    /// Lines like `USD,1.0835`, a first line that is not a rate is a header.
+    /// Empty lines and lines starting with # are skipped.
+    pub fn parse_csv(input: &str) -> Result<Rates, String> {
+        let mut rates = Rates::default();
+        let lines = input.lines().map(str::trim).filter(|line| !line.is_empty() && !line.starts_with('#'));
+        for (i, line) in lines.enumerate() {
+            let (code, rate) = line.split_once(',').unwrap_or((line, ""));
+            let (code, rate) = (code.trim().trim_matches('"'), rate.trim().trim_matches('"'));
+            if i == 0 && pattern::parse_decimal(rate).is_none() {
+                continue;
+            }
+            rates.insert(code, rate)?;
+        }
+        Ok(rates)
+    }
+
+    /// Object like `{"base": "EUR", "rates": {"USD": 1.0835, "PLN": 4.2675}}`,
+    /// other fields are skipped
+    pub fn parse_json(input: &str) -> Result<Rates, String> {
+        let invalid = || String::from("Invalid JSON rates");
+        let mut json = Json { input, pos: 0 };
+        let fields = json.object().ok_or_else(invalid)?;
+        json.skip_whitespace();
+        if json.pos < input.len() {
+            return Err(invalid());
+        }
+        let mut rates = Rates::default();
+        for (key, value) in fields {
+            match (key.as_str(), value) {
+                ("base", JsonValue::Str(code)) => rates.insert(&code, "1")?,
+                ("rates", JsonValue::Object(items)) => {
+                    for (code, rate) in items {
+                        match rate {
+                            JsonValue::Number(rate) => rates.insert(&code, &rate)?,
+                            _ => return Err(format!("Invalid rate for {code}"))
+                        }
+                    }
+                },
+                ("base" | "rates", _) => return Err(invalid()),
+                _ => ()
+            }
+        }
+        Ok(rates)
+    }
+
+    /// Reads CSV file, or JSON file when the name ends with .json
+    pub fn load(path: &str) -> Result<Rates, String> {
+        let input = std::fs::read_to_string(path).map_err(|err| format!("Cannot read {path}: {err}"))?;
+        if path.to_lowercase().ends_with(".json") {
+            Rates::parse_json(&input)
+        } else {
+            Rates::parse_csv(&input)
+        }
+    }
+
+    fn insert(&mut self, code: &str, rate: &str) -> Result<(), String> {
+        if !is_currency(code) {
+            return Err(format!("Unknown currency {code}"));
+        }
+        match pattern::parse_decimal(rate) {
+            Some(rate) if rate.is_positive() => {
+                self.0.insert(String::from(code), rate.into());
+                Ok(())
+            },
+            _ => Err(format!("Invalid rate for {code}: {rate}"))
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Value of one unit of the currency in the base currency
+    pub fn value(&self, code: &str) -> Result<Number, String> {
+        match self.0.get(code) {
+            Some(rate) => Ok(Number::Int(1) / rate.clone()),
+            None => Err(format!("Missing exchange rate for {code}"))
+        }
+    }
+}
+
+enum JsonValue {
+    Str(String),
+    Number(String),
+    Object(Vec<(String, JsonValue)>),
+    // arrays, booleans and null are read but not used
+    Other
+}
+
+// Just enough of JSON to read tables of rates
+struct Json<'a> {
+    input: &'a str,
+    pos: usize
+}
+
+impl Json<'_> {
+    fn skip_whitespace(&mut self) {
+        let rest = &self.input[self.pos..];
+        self.pos += rest.len() - rest.trim_start().len();
+    }
+
+    fn eat(&mut self, c: char) -> bool {
+        self.skip_whitespace();
+        let found = self.input[self.pos..].starts_with(c);
+        if found {
+            self.pos += c.len_utf8();
+        }
+        found
+    }
+
+    fn value(&mut self) -> Option<JsonValue> {
+        self.skip_whitespace();
+        let rest = &self.input[self.pos..];
+        match rest.chars().next()? {
+            '{' => self.object().map(JsonValue::Object),
+            '"' => self.string().map(JsonValue::Str),
+            '[' => {
+                self.pos += 1;
+                if !self.eat(']') {
+                    loop {
+                        self.value()?;
+                        if self.eat(']') {
+                            break;
+                        }
+                        self.eat(',').then_some(())?;
+                    }
+                }
+                Some(JsonValue::Other)
+            },
+            _ => match ["true", "false", "null"].into_iter().find(|word| rest.starts_with(word)) {
+                Some(word) => {
+                    self.pos += word.len();
+                    Some(JsonValue::Other)
+                },
+                None => self.number().map(JsonValue::Number)
+            }
+        }
+    }
+
+    // -?(0|[1-9][0-9]*)(.[0-9]+)?([eE][+-]?[0-9]+)?
+    fn number(&mut self) -> Option<String> {
+        let rest = &self.input[self.pos..];
+        let bytes = rest.as_bytes();
+        let digits = |start: usize| start + bytes[start..].iter().take_while(|b| b.is_ascii_digit()).count();
+        let start = usize::from(bytes.first() == Some(&b'-'));
+        let mut end = digits(start);
+        if end == start || (bytes[start] == b'0' && end > start + 1) {
+            return None;
+        }
+        if bytes.get(end) == Some(&b'.') {
+            let frac = digits(end + 1);
+            if frac == end + 1 {
+                return None;
+            }
+            end = frac;
+        }
+        if matches!(bytes.get(end), Some(b'e' | b'E')) {
+            let exp = end + 1 + usize::from(matches!(bytes.get(end + 1), Some(b'+' | b'-')));
+            end = digits(exp);
+            if end == exp {
+                return None;
+            }
+        }
+        self.pos += end;
+        let n = rest[..end].to_lowercase();
+        pattern::parse_decimal(&n).is_some().then_some(n)
+    }
+
+    fn object(&mut self) -> Option<Vec<(String, JsonValue)>> {
+        self.eat('{').then_some(())?;
+        let mut fields = vec![];
+        if self.eat('}') {
+            return Some(fields);
+        }
+        loop {
+            self.skip_whitespace();
+            let key = self.string()?;
+            self.eat(':').then_some(())?;
+            fields.push((key, self.value()?));
+            if self.eat('}') {
+                return Some(fields);
+            }
+            self.eat(',').then_some(())?;
+        }
+    }
+
+    // escapes other than \" and \\ do not appear in currency codes
+    fn string(&mut self) -> Option<String> {
+        self.eat('"').then_some(())?;
+        let mut res = String::new();
+        let mut chars = self.input[self.pos..].char_indices();
+        while let Some((i, c)) = chars.next() {
+            match c {
+                '"' => {
+                    self.pos += i + 1;
+                    return Some(res);
+                },
+                '\\' => res.push(chars.next()?.1),
+                c => res.push(c)
+            }
+        }
+        None
+    }
+}
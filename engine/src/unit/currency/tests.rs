@@ -0,0 +1,45 @@
+use super::*;
+
+#[test]
+fn test_parse_csv() {
+    let rates = Rates::parse_csv("currency,rate\nEUR,1\n\n# 2026-10-16\nUSD, 1.0835\n\"PLN\",\"4.25\"\n").unwrap();
+    assert_eq!(rates.len(), 3);
+    assert_eq!(rates.value("EUR"), Ok(Number::Int(1)));
+    assert_eq!(rates.value("PLN").unwrap().to_string(), "4/17");
+    assert_eq!(rates.value("USD").unwrap().to_string(), "2000/2167");
+    assert!(rates.value("GBP").is_err());
+    assert!(Rates::parse_csv("").unwrap().is_empty());
+    assert_eq!(Rates::parse_csv("EUR,1\nXYZ,2").unwrap_err(), "Unknown currency XYZ");
+    assert_eq!(Rates::parse_csv("EUR,1\nUSD,abc").unwrap_err(), "Invalid rate for USD: abc");
+    assert_eq!(Rates::parse_csv("EUR,1\nUSD,0").unwrap_err(), "Invalid rate for USD: 0");
+}
+
+#[test]
+fn test_parse_json() {
+    let input = r#"{"date": "2026-10-16", "base": "EUR", "rates": {"USD": 1.0835, "JPY": 1.62e2}, "ok": true}"#;
+    let rates = Rates::parse_json(input).unwrap();
+    assert_eq!(rates.len(), 3);
+    assert_eq!(rates.value("EUR"), Ok(Number::Int(1)));
+    assert_eq!(rates.value("JPY").unwrap().to_string(), "1/162");
+    assert_eq!(Rates::parse_json(r#"{"rates": {"USD": "1.0"}}"#).unwrap_err(), "Invalid rate for USD");
+    assert_eq!(Rates::parse_json(r#"{"rates": {"USD": 1.0}"#).unwrap_err(), "Invalid JSON rates");
+    assert_eq!(Rates::parse_json(r#"{"base": 1}"#).unwrap_err(), "Invalid JSON rates");
+    assert_eq!(Rates::parse_json("[1, 2]").unwrap_err(), "Invalid JSON rates");
+    assert_eq!(Rates::parse_json(r#"{"rates": {"USD": 1.0abc}}"#).unwrap_err(), "Invalid JSON rates");
+    assert_eq!(Rates::parse_json(r#"{"rates": {"USD": 01.5}}"#).unwrap_err(), "Invalid JSON rates");
+    assert_eq!(Rates::parse_json(r#"{"rates": {"USD": 1.}}"#).unwrap_err(), "Invalid JSON rates");
+    assert_eq!(Rates::parse_json(r#"{"rates": {"USD": nan}}"#).unwrap_err(), "Invalid JSON rates");
+    let rates = Rates::parse_json(r#"{"rates": {"USD": 1.5E+2}}"#).unwrap();
+    assert_eq!(rates.value("USD").unwrap().to_string(), "1/150");
+}
+
+#[test]
+fn test_value() {
+    assert_eq!(Rates::default().value("EUR").unwrap_err(), "Missing exchange rate for EUR");
+    let rates = Rates::parse_csv("EUR,1\nUSD,1.25").unwrap();
+    assert_eq!(rates.value("USD").unwrap().to_string(), "4/5");
+    assert_eq!(rates.len(), 2);
+    assert!(Rates::load("no/such/rates.csv").unwrap_err().starts_with("Cannot read no/such/rates.csv"));
+    assert_eq!(decimals("JPY"), Some(0));
+    assert!(!is_currency("eur"));
+}
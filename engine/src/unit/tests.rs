@@ -0,0 +1,66 @@
+use super::*;
+
+#[test]
+fn test_parse_unit() {
+    let km = parse_unit("km").unwrap();
+    assert_eq!(km.factor, Number::Int(1000));
+    assert_eq!(km.dim, LENGTH);
+    assert_eq!(parse_unit("mg").unwrap().factor.to_string(), "1/1000000");
+    assert_eq!(parse_unit("kg").unwrap().factor, Number::Int(1));
+    assert_eq!(parse_unit("dam").unwrap().factor, Number::Int(10));
+    assert_eq!(parse_unit("min").unwrap().factor, Number::Int(60));
+    assert_eq!(parse_unit("kWh").unwrap().factor, Number::Int(3_600_000));
+    assert!(parse_unit("kmin").is_none());
+    assert!(parse_unit("x").is_none());
+}
+
+#[test]
+fn test_unit_algebra() {
+    let m = parse_unit("m").unwrap();
+    let s = parse_unit("s").unwrap();
+    let speed = m.div(&s);
+    assert_eq!(speed.dim, SPEED);
+    assert_eq!(speed.to_string(), "m/s");
+    let accel = parse_unit("kg").unwrap().mul(&m).div(&s.powi(2));
+    assert_eq!(accel.to_string(), "kg*m/s^2");
+    assert!(accel.same_dimension(&parse_unit("N").unwrap()));
+    assert_eq!(m.div(&parse_unit("kg").unwrap().mul(&s)).to_string(), "m/(kg*s)");
+    assert_eq!(s.powi(-1).to_string(), "1/s");
+    assert!(m.div(&parse_unit("km").unwrap()).is_dimensionless());
+}
+
+#[test]
+fn test_convert() {
+    let mph = parse_unit("mph").unwrap();
+    let kmh = parse_unit("km").unwrap().div(&parse_unit("h").unwrap());
+    let none = Rates::default();
+    let q = Quantity::new(Number::Int(60), mph).convert(&kmh, &none).unwrap();
+    assert_eq!(q.to_string(), "96.56064 km/h");
+    let q = Quantity::new(Number::Int(5), parse_unit("km").unwrap());
+    assert_eq!(q.convert(&parse_unit("s").unwrap(), &none).unwrap_err(), "Cannot convert km to s");
+    assert_eq!(q.base_value(), Number::Int(5000));
+}
+
+#[test]
+fn test_exchange() {
+    let rates = Rates::parse_csv("EUR,1\nUSD,1.08\nJPY,162").unwrap();
+    let eur = parse_unit("EUR").unwrap();
+    let usd = parse_unit("USD").unwrap();
+    assert_eq!(eur.dim, MONEY);
+    assert_eq!(eur.currency(), Some("EUR"));
+    assert_eq!(eur.div(&parse_unit("h").unwrap()).currency(), None);
+    let q = Quantity::new(Number::Int(35), usd.clone()).exchange(&eur, &rates).unwrap();
+    assert_eq!(q.value.to_string(), "3241/100");
+    assert_eq!(q.to_string(), "32.41 EUR");
+    let q = Quantity::new(Number::Int(35), usd.clone()).convert(&eur, &rates).unwrap();
+    assert_eq!(q.value.to_string(), "875/27");
+    let q = Quantity::new(Number::Int(10), eur.clone()).exchange(&parse_unit("JPY").unwrap(), &rates).unwrap();
+    assert_eq!(q.to_string(), "1620 JPY");
+    assert_eq!(Quantity::new(Number::Float(2.5), usd.clone()).to_string(), "2.50 USD");
+    assert_eq!(eur.div(&usd).exchange_factor(&rates).unwrap(), Number::Int(27) / Number::Int(25));
+
+    let gbp = parse_unit("GBP").unwrap();
+    assert_eq!(Quantity::new(Number::Int(1), gbp.clone()).convert(&eur, &rates).unwrap_err(), "Missing exchange rate for GBP");
+    assert_eq!(gbp.div(&gbp).exchange_factor(&rates).unwrap(), Number::Int(1));
+    assert_eq!(Quantity::new(Number::Int(1), gbp.clone()).convert(&gbp, &rates).unwrap().to_string(), "1.00 GBP");
+}
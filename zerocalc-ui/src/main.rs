@@ -1,7 +1,16 @@
-use engine::{Calculator, Parser};
+use engine::{Calculator, Config, Parser, Value};
+use engine::number::Number;
+use engine::number::decimal;
+use engine::number::radix::{Radix, Word};
+use engine::unit::currency::Rates;
+use engine::function::angle::Angle;
 use iced::widget::{container, row, text, text_editor, Rule};
 use iced::{self, Application, Command, Element, Length, Settings, Subscription};
 use iced::time::Duration;
+use std::collections::HashMap;
+use std::fs;
+use std::rc::Rc;
+use std::time::SystemTime;
 
 #[derive(Debug, Clone)]
 enum Message {
@@ -9,21 +18,89 @@ enum Message {
     Evaluate,
 }
 
+// exchange rates read from a file, or the error of reading it,
+// with the modification time of the file when it was read
+struct RatesFile {
+    modified: Option<SystemTime>,
+    rates: Result<Rc<Rates>, String>
+}
+
 struct Editor {
     content: text_editor::Content,
     result: String,
+    // rates files of "rates" lines by their paths
+    rates: HashMap<String, RatesFile>,
 }
 
 impl Editor {
+    // every evaluation starts with a new calculator with default settings,
+    // mode lines change its config for the lines below them
     fn update_result(&mut self) {
         let mut calc = Calculator::new();
         self.result = String::new();
         for line in self.content.lines() {
-            let mut parser = Parser::new(&line);
+            // "precision 50" line switches following lines to 50 digit floats
+            if let ["precision", digits] = line.split_whitespace().collect::<Vec<_>>()[..] {
+                match digits {
+                    "off" => calc.config_mut().precision = None,
+                    digits => match digits.parse() {
+                        Ok(p) if p > 0 && p <= decimal::MAX_PRECISION => calc.config_mut().precision = Some(p),
+                        _ => self.result.push_str("Error")
+                    }
+                }
+                self.result.push('\n');
+                continue;
+            }
+            // "rates rates.csv" loads exchange rates for following lines
+            if let ["rates", path] = line.split_whitespace().collect::<Vec<_>>()[..] {
+                match load_rates(&mut self.rates, path) {
+                    Ok(rates) => calc.config_mut().rates = rates,
+                    Err(_) => self.result.push_str("Error")
+                }
+                self.result.push('\n');
+                continue;
+            }
+            // "angle deg" switches trigonometric functions of following lines to degrees,
+            // the result of the line shows the angle mode, also for "angle" alone
+            let angle = match line.split_whitespace().collect::<Vec<_>>()[..] {
+                ["angle"] => Some(Some(calc.angle())),
+                ["angle", name] => Some(Angle::parse(name)),
+                _ => None
+            };
+            if let Some(angle) = angle {
+                match angle {
+                    Some(angle) => {
+                        calc.set_angle(angle);
+                        self.result.push_str(&angle.to_string());
+                    },
+                    None => self.result.push_str("Error")
+                }
+                self.result.push('\n');
+                continue;
+            }
+            // "base hex", "word u32" and "group on" switch programmer mode for following lines
+            if let Some(mode) = set_programmer_mode(calc.config_mut(), line.trim()) {
+                if !mode {
+                    self.result.push_str("Error");
+                }
+                self.result.push('\n');
+                continue;
+            }
+            let mut parser = Parser::with_config(&line, calc.config());
             match parser.parse() {
                 Ok(true) => {
-                    let num = calc.eval(&parser.program);
-                    self.result.push_str(&(num.to_string() + "\n"))
+                    let res = match calc.eval(&parser.program) {
+                        Ok(Value::Defined(_)) => String::new(),
+                        // strings are labels, shown without quotes
+                        Ok(Value::Str(label)) => label,
+                        Ok(res @ Value::Number(Number::Rational(_))) => format!("{res} ({res:#})"),
+                        Ok(res) => res.to_string_in(parser.radix.unwrap_or(calc.config().radix), calc.config()),
+                        Err(err) => {
+                            eprintln!("{:?}", err);
+                            String::from("Error")
+                        }
+                    };
+                    self.result.push_str(&(res + "\n"))
                 }
                 Ok(false) => self.result.push('\n'),
                 Err(err) => {
@@ -35,6 +112,40 @@ impl Editor {
     }
 }
 
+// Rates of the file, read again only when the file was modified since it
+// was read last, so that edits to it show up without reading it on every
+// evaluation. Errors are reported once, when the file is read.
+fn load_rates(cache: &mut HashMap<String, RatesFile>, path: &str) -> Result<Rc<Rates>, String> {
+    let modified = fs::metadata(path).and_then(|m| m.modified()).ok();
+    match cache.get(path) {
+        Some(file) if file.modified == modified => file.rates.clone(),
+        _ => {
+            let rates = Rates::load(path).map(Rc::new);
+            if let Err(err) = &rates {
+                eprintln!("{err}");
+            }
+            cache.insert(String::from(path), RatesFile { modified, rates: rates.clone() });
+            rates
+        }
+    }
+}
+
+// None if the line is not a mode switch, Some(false) if its value is invalid
+// lines like "base = 16" are assignments, not mode switches
+fn set_programmer_mode(config: &mut Config, line: &str) -> Option<bool> {
+    let words: Vec<_> = line.split_whitespace().collect();
+    let valid = match words[..] {
+        ["base", name] => Radix::parse(name).map(|r| config.radix = r).is_some(),
+        ["word", "off"] => { config.word = None; true },
+        ["word", name] => Word::parse(name).map(|w| config.word = Some(w)).is_some(),
+        ["group", "on"] => { config.grouping = true; true },
+        ["group", "off"] => { config.grouping = false; true },
+        ["group", _] => false,
+        _ => return None
+    };
+    Some(valid)
+}
+
 impl Application for Editor {
     type Message = Message;
     type Executor = iced::executor::Default;
@@ -45,11 +156,12 @@ impl Application for Editor {
         let app = Editor {
             content: text_editor::Content::new(),
             result: String::new(),
+            rates: HashMap::new(),
         };
         (app, Command::none())
     }
 
-    fn view(&self) -> Element<Self::Message> {
+    fn view(&self) -> Element<'_, Self::Message> {
         let input = text_editor(&self.content)
             .height(Length::Fill)
             .padding(0)
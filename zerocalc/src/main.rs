@@ -1,7 +1,14 @@
-use engine::Calculator;
+use engine::{Calculator, Value};
+use engine::Error;
 use engine::Parser;
+use engine::number::Number;
+use engine::number::decimal;
+use engine::number::radix::{Radix, Word};
+use engine::unit::currency::Rates;
+use engine::function::angle::Angle;
 use rustyline::DefaultEditor;
 use rustyline::error::ReadlineError;
+use std::rc::Rc;
 
 const HELLO: &str = "Welcome to Zerocalc!
 Type \"help\" to print help message.
@@ -16,6 +23,16 @@ const HELP: &str = "
     - delete, backspace to delete characters
     - type 'exit' or press ctrl-d to exit
     - type 'help' to see this message
+    - type 'precision 50' to compute floats with 50 significant digits,
+      'precision off' to go back to standard floats
+    - type 'base hex' to print integers in hex (also bin, oct, dec)
+    - type 'word u32' to wrap integers to 32 bits (u8 to u128, i8 to i128),
+      'word off' to let them grow again
+    - type 'group on' to separate groups of digits, 'group off' to stop
+    - type 'angle deg' to take and return angles in degrees (also rad, grad),
+      'angle' alone to see the current mode
+    - type 'rates rates.csv' to load exchange rates from a CSV or JSON file,
+      'rates' alone to load the same file again
 
 ** Numbers **
 
@@ -29,6 +46,11 @@ const HELP: &str = "
 
     2e-2 == 0.02
 
+    Integers grow as big as needed and division of integers
+    gives exact fractions:
+
+    1/3 * 3 == 1
+
 ** Operators **
 
     + addition, 
@@ -36,32 +58,179 @@ const HELP: &str = "
     * multiplication,
     / division, 
     ^ power, 
-    % modulo
+    % modulo or percent,
+    ! factorial, 5! == 120
+
+** Implicit multiplication **
+
+    2pi, 3(x + 1), (a + 1)(b - 1), 2 sin(x)
+
+    Same precedence as *, after ^ and unary minus:
+    2x^2 == 2*x^2, -2x == (-2)*x, 1/2x == (1/2)*x
+
+    Variables win over units of the same name: after g = 9.81, 2g == 19.62
+
+** Percentages **
+
+    200 + 15% == 230
+    50% of 80 == 40
+    20 as % of 80 == 25
+
+    % is modulo when a number, variable or parentheses follow: 7 % 3 == 1, 7 % (-2) == 1
 
 ** Constants ** 
 
-    e, pi
+    e, pi, i (imaginary unit)
+
+** Complex numbers **
+
+    3+4i, sqrt(-1), ln(-2), (-8)^(1/3)
 
 ** Functions **
 
     - abs(x)                    - absolute value of x
-    - sin(x), cos(x), tan(x)    - x in radians
-    - asin(x), acos(x), atan(x) - x in radians
+    - sin(x), cos(x), tan(x)    - x in the unit of the angle mode
+    - asin(x), acos(x), atan(x) - angle in the unit of the angle mode
+    - deg(x), rad(x)            - radians to degrees, degrees to radians
     - ln(x), log10(x)           - natural logarithm, common logarith
     - log(x, y)                 - base y logarithm
     - sqrt(x)                   - square root
     - root(x, y)                - y'th root of x
+    - re(z), im(z)              - real and imaginary part of z
+    - arg(z), conj(z)           - argument and conjugate of z
+    - min(x, ...), max(x, ...)  - smallest and largest argument
+    - sum(x, ...), avg(x, ...)  - sum and mean of arguments, mean is the same as avg
+    - median(x, ...)            - median of arguments
+    - gcd(x, ...), lcm(x, ...)  - greatest common divisor, least common multiple
+    - nCr(n, k), nPr(n, k)      - combinations and permutations of k of n items
+    - isprime(n)                - 1 if n is prime, 0 otherwise
+    - factor(n)                 - prime factors, factor(360) = 2^3*3^2*5
+    - mod_pow(b, e, m)          - b^e modulo m
+    - gamma(x)                  - gamma function, gamma(n) = (n - 1)!
+    - variance(x, ...)          - sample variance
+    - stdev(x, ...)             - sample standard deviation
+    - percentile(x, ..., p)     - p'th percentile of x, ..., 0 <= p <= 100
+    - normpdf(x, m, s)          - normal density, m and s can be omitted
+    - normcdf(x, m, s)          - normal distribution, m and s can be omitted
+    - norminv(p, m, s)          - inverse of normcdf
+    - binompdf(n, p, k)         - probability of k successes in n trials
+    - binomcdf(n, p, k)         - probability of at most k successes
+    - poissonpdf(m, k)          - probability of k events with mean m
+    - poissoncdf(m, k)          - probability of at most k events
+
+** Angles **
+
+    30°, 30 deg, 100 grad, (pi/2) rad - angle in the unit of the angle mode
+    angle deg: sin(30) == 1/2, asin(1) == 90
+    deg(pi) == 180, rad(180) == pi
+
+** Units **
+
+    Numbers can be followed by a unit, 'to' converts between units:
+
+    5 km + 300 m
+    60 mph to km/h
+    9.81 m/s^2 * 70 kg to N
+
+    SI units accept prefixes: km, mA, kWh, ms, ...
+
+** Currencies **
+
+    Currency codes are units, 'in' or 'to' exchanges them by the loaded rates:
+
+    120 EUR + 35 USD in PLN
+
+    A rates file has lines like USD,1.0835 or is JSON like
+    {\"base\": \"EUR\", \"rates\": {\"USD\": 1.0835}}, each rate is the amount
+    of the currency worth one unit of the base. Exchanged amounts are rounded
+    to cents, or whatever the smallest unit of the currency is.
 
 ** Variables **
 
     x = (1 + 2)
     y = x / 3
+
+** User functions **
+
+    f(x) = x^2 + 1
+    hyp(a, b) = sqrt(a^2 + b^2)
+    f(3) + hyp(3, 4)
+
+** Conditions **
+
+    5 km > 300 m && !(1 == 2)
+    price(x) = if(x > 10, x * 0.9, x)
+
+** Bitwise operators **
+
+    0xf0 & 0b1010 | 1
+    6 xor 3
+    ~0
+    1 << 10 >> 2
+    -1 >>> 60
+
+** Derivatives **
+
+    diff(x^2 * sin(x), x)
+    diff(x^3, x, 2)
+    g(x) = diff(x^3, x)
+
+** Integrals and equations **
+
+    integrate(x^2, x, 0, 3)
+    solve(x^2 - 2, x, 1)
+
+** Lists **
+
+    [1, 2, 3] * 2 + [1, 1, 1]
+    sum([4, 5, 6]), sqrt([4, 9])
+    x = [4, 5, 6], x[0], x[-1]
+
+    - len(list)                 - number of elements
+    - sort(list)                - elements in ascending order
+    - range(a, b, step)         - from a up to but without b
+    - map(exp, x, list)         - values of exp for each element x
+    - filter(cond, x, list)     - elements x for which cond is true
+
+** Matrices **
+
+    [[1, 2], [3, 4]] * [[5], [6]]
+    det([[1, 2], [3, 4]]), solve([[2, 1], [1, 3]], [3, 5])
+
+    - det(A), inv(A), transpose(A), rank(A), eig(A)
+    - solve(A, b)               - x with A * x = b
+    - identity(n), zeros(rows, cols)
+
+** Strings **
+
+    \"Total: \" + 5 km
+    upper(\"abc\"), len(\"abc\"), \"abc\"[0]
+
+    - upper(str), lower(str)
+    - format(x, pattern)        - number as string, like format(pi, \"0.00\")
+
+** Dates and times **
+
+    2026-10-18 + 90 days, (17:45 - 09:10) * 5, 2026-10-18T09:10 - now()
+
+    - now(), weekday(date)      - weekday is 1 for Monday to 7 for Sunday
+    - workdays(a, b)            - days from Monday to Friday from a up to b
+
+** Programmer mode **
+
+    'in' prints one result in another base, 'as' wraps to a fixed width integer:
+
+    0xff + 1 in hex
+    -1 as u32 in hex
+    300 as u8
 ";
 
 const GOODBYE: &str = "Goodbye!";
 
 struct Repl {
     calc: Calculator,
+    // file of exchange rates loaded last
+    rates_path: Option<String>,
 }
 
 impl Repl {
@@ -69,6 +238,7 @@ impl Repl {
     fn new() -> Self {
         Repl {
             calc: Calculator::new(),
+            rates_path: None,
         }
     }
 
@@ -79,6 +249,30 @@ impl Repl {
             match input.readline(">") {
                 Ok(s) if s == "exit" => { println!("{}", GOODBYE); break },
                 Ok(s) if s == "help" => println!("{}",HELP),
+                Ok(s) if command(&s, "precision").is_some() => {
+                    self.set_precision(command(&s, "precision").unwrap_or_default());
+                    input.add_history_entry(s).unwrap_or_default();
+                },
+                Ok(s) if command(&s, "base").is_some() => {
+                    self.set_radix(command(&s, "base").unwrap_or_default());
+                    input.add_history_entry(s).unwrap_or_default();
+                },
+                Ok(s) if command(&s, "word").is_some() => {
+                    self.set_word(command(&s, "word").unwrap_or_default());
+                    input.add_history_entry(s).unwrap_or_default();
+                },
+                Ok(s) if command(&s, "group").is_some() => {
+                    self.set_grouping(command(&s, "group").unwrap_or_default());
+                    input.add_history_entry(s).unwrap_or_default();
+                },
+                Ok(s) if command(&s, "angle").is_some() => {
+                    self.set_angle(command(&s, "angle").unwrap_or_default());
+                    input.add_history_entry(s).unwrap_or_default();
+                },
+                Ok(s) if command(&s, "rates").is_some() => {
+                    self.load_rates(command(&s, "rates").unwrap_or_default());
+                    input.add_history_entry(s).unwrap_or_default();
+                },
                 Ok(s) =>{
                     self.eval(&s);
                     input.add_history_entry(s).unwrap_or_default();
@@ -94,22 +288,111 @@ impl Repl {
         }
     }
 
+    fn set_precision(&mut self, arg: &str) {
+        match arg {
+            "" => match self.calc.config().precision {
+                Some(p) => println!("Precision is {p} digits"),
+                None => println!("Precision is off")
+            },
+            "off" => self.calc.config_mut().precision = None,
+            digits => match digits.parse() {
+                Ok(p) if p > 0 && p <= decimal::MAX_PRECISION => self.calc.config_mut().precision = Some(p),
+                _ => println!("Precision must be a number from 1 to {}", decimal::MAX_PRECISION)
+            }
+        }
+    }
+
+    fn set_radix(&mut self, arg: &str) {
+        match arg {
+            "" => println!("Base is {}", self.calc.config().radix),
+            name => match Radix::parse(name) {
+                Some(r) => self.calc.config_mut().radix = r,
+                None => println!("Base must be bin, oct, dec or hex")
+            }
+        }
+    }
+
+    fn set_word(&mut self, arg: &str) {
+        match arg {
+            "" => match self.calc.config().word {
+                Some(w) => println!("Word is {w}"),
+                None => println!("Word is off")
+            },
+            "off" => self.calc.config_mut().word = None,
+            name => match Word::parse(name) {
+                Some(w) => self.calc.config_mut().word = Some(w),
+                None => println!("Word must be u8, u16, u32, u64, u128 or i8 to i128")
+            }
+        }
+    }
+
+    fn set_grouping(&mut self, arg: &str) {
+        match arg {
+            "" => println!("Grouping is {}", if self.calc.config().grouping { "on" } else { "off" }),
+            "on" => self.calc.config_mut().grouping = true,
+            "off" => self.calc.config_mut().grouping = false,
+            _ => println!("Grouping must be on or off")
+        }
+    }
+
+    fn set_angle(&mut self, arg: &str) {
+        match arg {
+            "" => println!("Angle mode is {}", self.calc.angle()),
+            name => match Angle::parse(name) {
+                Some(a) => self.calc.set_angle(a),
+                _ => println!("Angle mode must be rad, deg or grad")
+            }
+        }
+    }
+
+    fn load_rates(&mut self, arg: &str) {
+        let path = match (arg, &self.rates_path) {
+            ("", Some(path)) => path.clone(),
+            ("", None) => return println!("No exchange rates loaded"),
+            (path, _) => String::from(path)
+        };
+        match Rates::load(&path) {
+            Ok(rates) => {
+                println!("Loaded {} exchange rates from {path}", rates.len());
+                self.calc.config_mut().rates = Rc::new(rates);
+                self.rates_path = Some(path);
+            },
+            Err(err) => println!("{err}")
+        }
+    }
+
     fn eval(&mut self, buffer: &str) {
-        let mut p = Parser::new(buffer);
+        let mut p = Parser::with_config(buffer, self.calc.config());
         match p.parse() {
             Ok(true) => {
-                let res = self.calc.eval(&p.program);
-                println!("{res}");
-            },
-            Err(e) => {
-                println!("{:spaces$}{:^<marks$}", " ", "^", spaces=e.span.pos + 1, marks=e.span.len);
-                println!("{}", e.message);
+                match self.calc.eval(&p.program) {
+                    // definitions like f(x) = x^2 have no value to print
+                    Ok(Value::Defined(_)) => (),
+                    Ok(res @ Value::Number(Number::Rational(_))) => println!("{res} ({res:#})"),
+                    Ok(res) => println!("{}", res.to_string_in(p.radix.unwrap_or(self.calc.config().radix), self.calc.config())),
+                    Err(e) => print_error(&e)
+                }
             },
+            Err(e) => print_error(&e),
             _ => ()
         }
     }
     }
 
+// argument of a command like "base hex", None for other input like "base = 16"
+fn command<'a>(buffer: &'a str, name: &str) -> Option<&'a str> {
+    let mut words = buffer.split_whitespace();
+    match (words.next(), words.next(), words.next()) {
+        (Some(word), arg, None) if word == name => Some(arg.unwrap_or_default()),
+        _ => None
+    }
+}
+
+fn print_error(e: &Error) {
+    println!("{:spaces$}{:^<marks$}", " ", "^", spaces=e.span.pos + 1, marks=e.span.len);
+    println!("{}", e.message);
+}
+
 fn main() {
     Repl::new().run();
 }